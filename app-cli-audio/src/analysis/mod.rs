@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use serde::Serialize;
+use crate::buffer::AudioBuffer;
+use crate::fft::{hann, magnitudes, to_db};
+
+/// Frames of the onset detection, about 12 ms at 44.1 kHz
+const TEMPO_FFT_SIZE: usize = 1024;
+const TEMPO_HOP: usize = 512;
+/// Tempos considered, narrow enough that half and double of a beat do not both fit
+const MIN_TEMPO: f32 = 70.0;
+const MAX_TEMPO: f32 = 180.0;
+
+/// What is computed, the defaults suit a picture about a thousand pixels wide
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisOptions {
+    /// Waveform points over the whole file
+    pub points: usize,
+    /// Samples per FFT frame, a power of two, larger sizes tell close frequencies apart but blur time
+    pub fft_size: usize,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions { points: 1000, fft_size: 4096 }
+    }
+}
+
+/// Levels of a slice of the audio, what a waveform view draws
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct WaveformPoint {
+    pub min: f32,
+    pub max: f32,
+    pub rms: f32,
+}
+
+impl WaveformPoint {
+    pub fn peak(&self) -> f32 {
+        self.min.abs().max(self.max.abs())
+    }
+}
+
+/// Average level of each frequency over the whole file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Spectrum {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    /// dBFS of each bin, from 0 Hz up to half the sample rate
+    pub levels: Vec<f32>,
+}
+
+impl Spectrum {
+    /// Center frequency of a bin, in Hz
+    pub fn frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.sample_rate as f32 / self.fft_size as f32
+    }
+
+    /// Loudest frequency, the DC bin aside
+    pub fn peak_frequency(&self) -> Option<f32> {
+        let (bin, _) = self.levels.iter().enumerate().skip(1).max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        Some(self.frequency(bin))
+    }
+}
+
+/// Levels of each frequency over time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Spectrogram {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    /// Samples between two columns
+    pub hop: usize,
+    /// dBFS, one column per hop, each from 0 Hz up to half the sample rate
+    pub columns: Vec<Vec<f32>>,
+}
+
+/// Everything exported as json
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Analysis {
+    /// Seconds
+    pub duration: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// dBFS
+    pub peak: f32,
+    /// dBFS
+    pub rms: f32,
+    /// Beats per minute, none when there is no steady beat
+    pub tempo: Option<f32>,
+    pub waveform: Vec<WaveformPoint>,
+    pub spectrum: Spectrum,
+}
+
+impl Analysis {
+    pub fn save_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Levels, waveform, spectrum and tempo of the audio, the channels mixed together
+pub fn analyze(buffer: &AudioBuffer, options: &AnalysisOptions) -> Analysis {
+    let mono = buffer.mono();
+    Analysis {
+        duration: buffer.duration(),
+        sample_rate: buffer.sample_rate,
+        channels: buffer.channels,
+        peak: to_db(buffer.peak()),
+        rms: to_db(buffer.rms()),
+        tempo: estimate_tempo(&mono, buffer.sample_rate),
+        waveform: waveform(&mono, options.points),
+        spectrum: spectrum(&mono, buffer.sample_rate, options.fft_size),
+    }
+}
+
+/// `points` slices of the samples with their levels, fewer when there are fewer samples
+pub fn waveform(samples: &[f32], points: usize) -> Vec<WaveformPoint> {
+    let points = points.min(samples.len());
+    (0..points)
+        .map(|point| {
+            let slice = &samples[point * samples.len() / points..(point + 1) * samples.len() / points];
+            WaveformPoint {
+                min: slice.iter().copied().fold(0.0, f32::min),
+                max: slice.iter().copied().fold(0.0, f32::max),
+                rms: (slice.iter().map(|sample| sample * sample).sum::<f32>() / slice.len() as f32).sqrt(),
+            }
+        })
+        .collect()
+}
+
+/// Average spectrum over frames overlapping by half
+pub fn spectrum(samples: &[f32], sample_rate: u32, fft_size: usize) -> Spectrum {
+    let mut power = vec![0.0f32; fft_size / 2 + 1];
+    let mut count = 0;
+    for frame in frame_magnitudes(samples, fft_size, fft_size / 2) {
+        power.iter_mut().zip(&frame).for_each(|(power, magnitude)| *power += magnitude * magnitude);
+        count += 1;
+    }
+    let count = count.max(1) as f32;
+    Spectrum { sample_rate, fft_size, levels: power.iter().map(|power| to_db((power / count).sqrt())).collect() }
+}
+
+pub fn spectrogram(samples: &[f32], sample_rate: u32, fft_size: usize, hop: usize) -> Spectrogram {
+    let columns = frame_magnitudes(samples, fft_size, hop)
+        .map(|frame| frame.into_iter().map(to_db).collect())
+        .collect();
+    Spectrogram { sample_rate, fft_size, hop, columns }
+}
+
+/// Beats per minute from how regularly the sound changes, none for short or beatless audio
+///
+/// Onsets are found with the spectral flux, how much louder each frequency gets from one frame to the next,
+/// then the most regular spacing between them is found with an autocorrelation.
+pub fn estimate_tempo(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let frame_rate = sample_rate as f32 / TEMPO_HOP as f32;
+    let min_lag = (60.0 * frame_rate / MAX_TEMPO).floor() as usize;
+    let max_lag = (60.0 * frame_rate / MIN_TEMPO).ceil() as usize;
+    // a few beats at the slowest tempo
+    if min_lag == 0 || frame_count(samples, TEMPO_HOP) < max_lag * 4 {
+        return None;
+    }
+
+    // only the frame before is kept, the flux is all that is needed of the spectra
+    let mut flux = Vec::with_capacity(frame_count(samples, TEMPO_HOP));
+    let mut before: Option<Vec<f32>> = None;
+    for frame in frame_magnitudes(samples, TEMPO_FFT_SIZE, TEMPO_HOP) {
+        let compressed: Vec<f32> = frame.iter().map(|magnitude| (1.0 + 100.0 * magnitude).ln()).collect();
+        if let Some(before) = &before {
+            flux.push(compressed.iter().zip(before).map(|(now, before)| (now - before).max(0.0)).sum::<f32>());
+        }
+        before = Some(compressed);
+    }
+    let mean = flux.iter().sum::<f32>() / flux.len() as f32;
+    flux.iter_mut().for_each(|value| *value = (*value - mean).max(0.0));
+
+    let correlation = |lag: usize| flux.iter().zip(&flux[lag..]).map(|(a, b)| a * b).sum::<f32>() / (flux.len() - lag) as f32;
+    let energy = correlation(0);
+    if energy <= f32::EPSILON {
+        return None;
+    }
+    let scores: Vec<f32> = (min_lag - 1..=max_lag + 1).map(correlation).collect();
+    let (best, score) = scores[1..scores.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(index, score)| (index + 1, *score))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    let (before, after) = (scores[best - 1], scores[best + 1]);
+    // a best lag at the edge of the range that keeps rising is the beat of another range
+    if score < energy * 0.1 || score < before || score < after {
+        return None;
+    }
+
+    // a parabola through the best lag and its neighbours finds the peak between two frames
+    let curve = before - 2.0 * score + after;
+    let offset = if curve < 0.0 { (0.5 * (before - after) / curve).clamp(-0.5, 0.5) } else { 0.0 };
+    let lag = (min_lag - 1 + best) as f32 + offset;
+    Some(60.0 * frame_rate / lag)
+}
+
+/// Number of frames [`frame_magnitudes`] gives
+fn frame_count(samples: &[f32], hop: usize) -> usize {
+    samples.len().max(1).div_ceil(hop.max(1))
+}
+
+/// Spectra of the frames starting every `hop` samples, the last one padded with silence, worked out as they are read
+fn frame_magnitudes(samples: &[f32], fft_size: usize, hop: usize) -> impl Iterator<Item = Vec<f32>> + '_ {
+    let window = hann(fft_size);
+    (0..samples.len().max(1)).step_by(hop.max(1)).map(move |start| magnitudes(&samples[start..], &window))
+}
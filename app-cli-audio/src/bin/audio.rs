@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use app_cli_audio::analysis::{analyze, spectrogram, waveform, AnalysisOptions};
+use app_cli_audio::buffer::AudioBuffer;
+use app_cli_audio::decode::decode_file;
+use app_cli_audio::effects::EffectChain;
+use app_cli_audio::generate::{NoiseColor, Signal, Waveform};
+use app_cli_audio::plot::{spectrogram_image, waveform_image};
+use app_cli_audio::wav::write_wav;
+
+fn main() {
+    let matches = Command::new("Audio")
+        .version("1.0")
+        .about("Plays audio files, test tones and noise through an effect chain, renders them to WAV or analyzes them")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("effect")
+                .short('e')
+                .long("effect")
+                .value_name("EFFECT")
+                .action(ArgAction::Append)
+                .help("lowpass=HZ, highpass=HZ, rate=X, gain=DB, fadein=S or fadeout=S, repeated or separated by commas, applied in order")
+                .global(true),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Renders to this WAV file instead of playing, no sound device needed")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("play")
+                .about("Play an mp3, wav, flac or ogg file")
+                .arg(Arg::new("file").value_name("FILE").required(true).help("Audio file")),
+        )
+        .subcommand(
+            Command::new("tone")
+                .about("Generate a test tone")
+                .arg(
+                    Arg::new("waveform")
+                        .short('w')
+                        .long("waveform")
+                        .value_name("WAVEFORM")
+                        .default_value("sine")
+                        .help("sine, square, sawtooth or triangle"),
+                )
+                .arg(
+                    Arg::new("frequency")
+                        .short('f')
+                        .long("frequency")
+                        .value_name("HZ")
+                        .value_parser(value_parser!(f32))
+                        .default_value("440"),
+                )
+                .args(generator_args()),
+        )
+        .subcommand(
+            Command::new("noise")
+                .about("Generate noise")
+                .arg(
+                    Arg::new("color")
+                        .short('c')
+                        .long("color")
+                        .value_name("COLOR")
+                        .default_value("white")
+                        .help("white, pink or brown"),
+                )
+                .args(generator_args()),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("Print the levels, loudest frequency and tempo of an audio file, and export its waveform and spectrogram")
+                .arg(Arg::new("file").value_name("FILE").required(true).help("Audio file"))
+                .arg(
+                    Arg::new("points")
+                        .long("points")
+                        .value_name("POINTS")
+                        .value_parser(value_parser!(usize))
+                        .default_value("1000")
+                        .help("Waveform points over the whole file"),
+                )
+                .arg(
+                    Arg::new("fft-size")
+                        .long("fft-size")
+                        .value_name("SAMPLES")
+                        .value_parser(value_parser!(usize))
+                        .default_value("4096")
+                        .help("FFT frame, a power of two"),
+                )
+                .arg(Arg::new("json").long("json").value_name("FILE").help("Saves the levels, waveform and spectrum as json"))
+                .arg(Arg::new("waveform").long("waveform").value_name("PNG").help("Saves a picture of the waveform"))
+                .arg(Arg::new("spectrogram").long("spectrogram").value_name("PNG").help("Saves a picture of the spectrogram"))
+                .arg(
+                    Arg::new("width")
+                        .long("width")
+                        .value_name("PIXELS")
+                        .value_parser(value_parser!(u32))
+                        .default_value("1200")
+                        .help("Width of the pictures"),
+                )
+                .arg(
+                    Arg::new("height")
+                        .long("height")
+                        .value_name("PIXELS")
+                        .value_parser(value_parser!(u32))
+                        .default_value("300")
+                        .help("Height of the pictures"),
+                ),
+        )
+        .get_matches();
+
+    let effects: Vec<&str> = matches.get_many::<String>("effect").unwrap_or_default().map(String::as_str).collect();
+    let chain = effects.join(",").parse::<EffectChain>().unwrap_or_else(|e| fail(e));
+
+    let buffer = match matches.subcommand() {
+        Some(("play", file_matches)) | Some(("analyze", file_matches)) => {
+            let file = Path::new(file_matches.get_one::<String>("file").unwrap());
+            decode_file(file).unwrap_or_else(|e| fail(format!("Cannot decode {}: {}", file.display(), e)))
+        }
+        Some(("tone", tone_matches)) => {
+            let waveform: Waveform = tone_matches.get_one::<String>("waveform").unwrap().parse().unwrap_or_else(|e| fail(e));
+            let frequency = *tone_matches.get_one::<f32>("frequency").unwrap();
+            generate(Signal::Tone { waveform, frequency }, tone_matches)
+        }
+        Some(("noise", noise_matches)) => {
+            let color: NoiseColor = noise_matches.get_one::<String>("color").unwrap().parse().unwrap_or_else(|e| fail(e));
+            generate(Signal::Noise(color), noise_matches)
+        }
+        _ => unreachable!("a subcommand is required"),
+    };
+
+    let buffer = chain.apply(buffer);
+    let output = matches.get_one::<String>("output").map(PathBuf::from);
+    if let Some(output) = &output {
+        write_wav(&buffer, output).unwrap_or_else(|e| fail(format!("Cannot write {}: {}", output.display(), e)));
+        println!("Rendered {:.1}s to {}", buffer.duration(), output.display());
+    }
+    match matches.subcommand() {
+        Some(("analyze", analyze_matches)) => analyze_command(&buffer, analyze_matches),
+        _ if output.is_none() => play(&buffer),
+        _ => {}
+    }
+}
+
+/// Prints a summary of the audio and saves the asked for json and pictures
+fn analyze_command(buffer: &AudioBuffer, matches: &ArgMatches) {
+    let options = AnalysisOptions {
+        points: *matches.get_one::<usize>("points").unwrap(),
+        fft_size: *matches.get_one::<usize>("fft-size").unwrap(),
+    };
+    if !options.fft_size.is_power_of_two() || options.fft_size < 64 {
+        fail(format!("FFT size {} is not a power of two of at least 64", options.fft_size));
+    }
+    let (width, height) = (*matches.get_one::<u32>("width").unwrap(), *matches.get_one::<u32>("height").unwrap());
+    if width == 0 || height == 0 {
+        fail("The pictures need a width and a height".to_string());
+    }
+
+    let analysis = analyze(buffer, &options);
+    println!("Duration     {:.1}s, {} Hz, {} channels", analysis.duration, analysis.sample_rate, analysis.channels);
+    println!("Peak         {:.1} dBFS", analysis.peak);
+    println!("RMS          {:.1} dBFS", analysis.rms);
+    if let Some(frequency) = analysis.spectrum.peak_frequency() {
+        println!("Loudest at   {:.0} Hz", frequency);
+    }
+    match analysis.tempo {
+        Some(tempo) => println!("Tempo        {:.1} BPM", tempo),
+        None => println!("Tempo        no steady beat"),
+    }
+
+    if let Some(path) = matches.get_one::<String>("json") {
+        analysis.save_json(Path::new(path)).unwrap_or_else(|e| fail(format!("Cannot write {}: {}", path, e)));
+        println!("Saved {}", path);
+    }
+    if let Some(path) = matches.get_one::<String>("waveform") {
+        let points = waveform(&buffer.mono(), width as usize);
+        waveform_image(&points, width, height).save(path).unwrap_or_else(|e| fail(format!("Cannot write {}: {}", path, e)));
+        println!("Saved {}", path);
+    }
+    if let Some(path) = matches.get_one::<String>("spectrogram") {
+        // at most one column per pixel, overlapping frames when the audio is short
+        let hop = buffer.frames().div_ceil(width as usize).max(1);
+        let spectrogram = spectrogram(&buffer.mono(), buffer.sample_rate, options.fft_size, hop);
+        spectrogram_image(&spectrogram, height).save(path).unwrap_or_else(|e| fail(format!("Cannot write {}: {}", path, e)));
+        println!("Saved {}", path);
+    }
+}
+
+/// Length, level and rate of generated audio
+fn generator_args() -> [Arg; 3] {
+    [
+        Arg::new("duration")
+            .short('d')
+            .long("duration")
+            .value_name("SECONDS")
+            .value_parser(value_parser!(f64))
+            .default_value("5"),
+        Arg::new("amplitude")
+            .short('a')
+            .long("amplitude")
+            .value_name("AMPLITUDE")
+            .value_parser(value_parser!(f32))
+            .default_value("0.5")
+            .help("Peak level, between 0 and 1"),
+        Arg::new("sample-rate")
+            .long("sample-rate")
+            .value_name("HZ")
+            .value_parser(value_parser!(u32))
+            .default_value("48000"),
+    ]
+}
+
+fn generate(signal: Signal, matches: &ArgMatches) -> AudioBuffer {
+    let amplitude = *matches.get_one::<f32>("amplitude").unwrap();
+    if !(0.0..=1.0).contains(&amplitude) {
+        fail(format!("Amplitude {} is not between 0 and 1", amplitude));
+    }
+    signal.generate(*matches.get_one::<f64>("duration").unwrap(), *matches.get_one::<u32>("sample-rate").unwrap(), amplitude)
+}
+
+#[cfg(feature = "playback")]
+fn play(buffer: &AudioBuffer) {
+    if let Err(e) = app_cli_audio::playback::play(buffer) {
+        fail(format!("Cannot play: {}, use --output to render to a file", e));
+    }
+}
+
+#[cfg(not(feature = "playback"))]
+fn play(_buffer: &AudioBuffer) {
+    fail("Built without playback, use --output to render to a file".to_string());
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
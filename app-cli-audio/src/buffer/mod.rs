@@ -0,0 +1,55 @@
+/// Decoded or generated audio, interleaved samples between -1 and 1
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioBuffer {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+impl AudioBuffer {
+    pub fn new(sample_rate: u32, channels: u16, samples: Vec<f32>) -> Self {
+        AudioBuffer { sample_rate, channels, samples }
+    }
+
+    pub fn silence(sample_rate: u32, channels: u16, seconds: f64) -> Self {
+        let frames = (seconds.max(0.0) * sample_rate as f64).round() as usize;
+        AudioBuffer::new(sample_rate, channels, vec![0.0; frames * channels as usize])
+    }
+
+    /// Samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    /// Seconds
+    pub fn duration(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.frames() as f64 / self.sample_rate as f64
+    }
+
+    /// Highest absolute sample
+    pub fn peak(&self) -> f32 {
+        self.samples.iter().fold(0.0, |peak, sample| peak.max(sample.abs()))
+    }
+
+    pub fn rms(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        (self.samples.iter().map(|sample| sample * sample).sum::<f32>() / self.samples.len() as f32).sqrt()
+    }
+
+    /// Average of the channels, one sample per frame
+    pub fn mono(&self) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        self.samples.chunks_exact(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect()
+    }
+
+    /// Cuts the audio after `seconds`
+    pub fn truncate(&mut self, seconds: f64) {
+        let frames = (seconds.max(0.0) * self.sample_rate as f64).round() as usize;
+        self.samples.truncate(frames * self.channels as usize);
+    }
+}
@@ -0,0 +1,230 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use crate::buffer::AudioBuffer;
+
+/// Resonance of the filters, the flattest response without a bump at the cutoff
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// One step of an `EffectChain`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    /// Cutoff in Hz, 12 dB per octave above it
+    LowPass(f32),
+    /// Cutoff in Hz, 12 dB per octave below it
+    HighPass(f32),
+    /// Speed and pitch together, 2 is twice as fast and an octave up
+    Rate(f32),
+    /// Decibels
+    Gain(f32),
+    /// Seconds from silence at the start
+    FadeIn(f64),
+    /// Seconds to silence at the end
+    FadeOut(f64),
+}
+
+impl Effect {
+    pub fn apply(&self, buffer: AudioBuffer) -> AudioBuffer {
+        match *self {
+            Effect::LowPass(cutoff) => Biquad::low_pass(cutoff, buffer.sample_rate).process(buffer),
+            Effect::HighPass(cutoff) => Biquad::high_pass(cutoff, buffer.sample_rate).process(buffer),
+            Effect::Rate(rate) => resample(buffer, rate),
+            Effect::Gain(db) => {
+                let gain = 10f32.powf(db / 20.0);
+                map_frames(buffer, |_, sample| sample * gain)
+            }
+            Effect::FadeIn(seconds) => map_frames(buffer, |time, sample| sample * ramp(time, seconds)),
+            Effect::FadeOut(seconds) => {
+                let duration = buffer.duration();
+                map_frames(buffer, |time, sample| sample * ramp(duration - time, seconds))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Effect::LowPass(cutoff) => write!(f, "lowpass={}", cutoff),
+            Effect::HighPass(cutoff) => write!(f, "highpass={}", cutoff),
+            Effect::Rate(rate) => write!(f, "rate={}", rate),
+            Effect::Gain(db) => write!(f, "gain={}", db),
+            Effect::FadeIn(seconds) => write!(f, "fadein={}", seconds),
+            Effect::FadeOut(seconds) => write!(f, "fadeout={}", seconds),
+        }
+    }
+}
+
+/// Reads `lowpass=800`, `highpass=120`, `rate=1.5`, `gain=-6`, `fadein=2` or `fadeout=3`
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, amount) = value.split_once('=').ok_or_else(|| format!("Effect {} has no value, like lowpass=800", value))?;
+        let amount: f64 = amount.trim().parse().map_err(|_| format!("Invalid value in {}", value))?;
+        let positive = |amount: f64| if amount > 0.0 { Ok(amount) } else { Err(format!("{} must be above 0", name)) };
+        let effect = match name.trim().to_lowercase().as_str() {
+            "lowpass" => Effect::LowPass(positive(amount)? as f32),
+            "highpass" => Effect::HighPass(positive(amount)? as f32),
+            "rate" => Effect::Rate(positive(amount)? as f32),
+            "gain" => Effect::Gain(amount as f32),
+            "fadein" => Effect::FadeIn(positive(amount)?),
+            "fadeout" => Effect::FadeOut(positive(amount)?),
+            _ => return Err(format!("Unknown effect {}, use lowpass, highpass, rate, gain, fadein or fadeout", name)),
+        };
+        Ok(effect)
+    }
+}
+
+/// Effects applied one after the other, the first one on the original audio
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EffectChain {
+    effects: Vec<Effect>,
+}
+
+impl EffectChain {
+    pub fn new() -> Self {
+        EffectChain::default()
+    }
+
+    pub fn then(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
+    pub fn low_pass(self, cutoff: f32) -> Self {
+        self.then(Effect::LowPass(cutoff))
+    }
+
+    pub fn high_pass(self, cutoff: f32) -> Self {
+        self.then(Effect::HighPass(cutoff))
+    }
+
+    pub fn rate(self, rate: f32) -> Self {
+        self.then(Effect::Rate(rate))
+    }
+
+    pub fn gain(self, db: f32) -> Self {
+        self.then(Effect::Gain(db))
+    }
+
+    pub fn fade_in(self, seconds: f64) -> Self {
+        self.then(Effect::FadeIn(seconds))
+    }
+
+    pub fn fade_out(self, seconds: f64) -> Self {
+        self.then(Effect::FadeOut(seconds))
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn apply(&self, buffer: AudioBuffer) -> AudioBuffer {
+        self.effects.iter().fold(buffer, |buffer, effect| effect.apply(buffer))
+    }
+}
+
+impl fmt::Display for EffectChain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let effects: Vec<String> = self.effects.iter().map(Effect::to_string).collect();
+        write!(f, "{}", effects.join(","))
+    }
+}
+
+/// Reads effects separated by commas, like `lowpass=800,fadeout=3`
+impl FromStr for EffectChain {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let effects = value.split(',').filter(|effect| !effect.trim().is_empty()).map(str::parse).collect::<Result<_, _>>()?;
+        Ok(EffectChain { effects })
+    }
+}
+
+/// Gain of a linear fade `time` seconds into a fade of `seconds`
+fn ramp(time: f64, seconds: f64) -> f32 {
+    (time / seconds).clamp(0.0, 1.0) as f32
+}
+
+/// Changes every sample, given the time of its frame in seconds
+fn map_frames(mut buffer: AudioBuffer, mut change: impl FnMut(f64, f32) -> f32) -> AudioBuffer {
+    let channels = buffer.channels.max(1) as usize;
+    let sample_rate = buffer.sample_rate.max(1) as f64;
+    for (index, frame) in buffer.samples.chunks_exact_mut(channels).enumerate() {
+        let time = index as f64 / sample_rate;
+        frame.iter_mut().for_each(|sample| *sample = change(time, *sample));
+    }
+    buffer
+}
+
+/// Plays the audio `rate` times faster, reading between samples with a linear interpolation
+fn resample(buffer: AudioBuffer, rate: f32) -> AudioBuffer {
+    let channels = buffer.channels.max(1) as usize;
+    let frames = buffer.frames();
+    if frames == 0 || rate <= 0.0 {
+        return buffer;
+    }
+    let output_frames = (frames as f64 / rate as f64).floor() as usize;
+    let mut samples = Vec::with_capacity(output_frames * channels);
+    for index in 0..output_frames {
+        let position = index as f64 * rate as f64;
+        let before = (position.floor() as usize).min(frames - 1);
+        let after = (before + 1).min(frames - 1);
+        let weight = (position - before as f64) as f32;
+        for channel in 0..channels {
+            let a = buffer.samples[before * channels + channel];
+            let b = buffer.samples[after * channels + channel];
+            samples.push(a + (b - a) * weight);
+        }
+    }
+    AudioBuffer::new(buffer.sample_rate, buffer.channels, samples)
+}
+
+/// Second order filter from the Audio EQ Cookbook, normalized so a0 is 1
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+}
+
+impl Biquad {
+    fn low_pass(cutoff: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::angle(cutoff, sample_rate);
+        Self::normalized([(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    fn high_pass(cutoff: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::angle(cutoff, sample_rate);
+        Self::normalized([(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    fn angle(cutoff: f32, sample_rate: u32) -> (f32, f32) {
+        // a cutoff at or over half the sample rate makes the filter unstable
+        let cutoff = cutoff.clamp(1.0, sample_rate as f32 * 0.49);
+        let omega = 2.0 * PI * cutoff / sample_rate as f32;
+        (omega.cos(), omega.sin() / (2.0 * BUTTERWORTH_Q))
+    }
+
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Biquad { b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]], a: [a[1] / a[0], a[2] / a[0]] }
+    }
+
+    fn process(&self, mut buffer: AudioBuffer) -> AudioBuffer {
+        let channels = buffer.channels.max(1) as usize;
+        // last two inputs and outputs of each channel
+        let mut state = vec![[0.0f32; 4]; channels];
+        for frame in buffer.samples.chunks_exact_mut(channels) {
+            for (sample, [x1, x2, y1, y2]) in frame.iter_mut().zip(state.iter_mut()) {
+                let x = *sample;
+                let y = self.b[0] * x + self.b[1] * *x1 + self.b[2] * *x2 - self.a[0] * *y1 - self.a[1] * *y2;
+                (*x2, *x1, *y2, *y1) = (*x1, x, *y1, y);
+                *sample = y;
+            }
+        }
+        buffer
+    }
+}
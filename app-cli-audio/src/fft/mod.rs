@@ -0,0 +1,63 @@
+use std::f32::consts::PI;
+
+/// In place radix-2 transform of a complex signal, the length must be a power of two
+pub fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+    let size = real.len();
+    assert!(size.is_power_of_two() && imaginary.len() == size, "FFT size must be a power of two");
+
+    // bit reversed order first, so each pass combines neighbouring halves
+    let mut j = 0;
+    for i in 1..size {
+        let mut bit = size >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= size {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..size).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (even, odd) = (start + k, start + k + length / 2);
+                let odd_real = real[odd] * cos - imaginary[odd] * sin;
+                let odd_imaginary = real[odd] * sin + imaginary[odd] * cos;
+                real[odd] = real[even] - odd_real;
+                imaginary[odd] = imaginary[even] - odd_imaginary;
+                real[even] += odd_real;
+                imaginary[even] += odd_imaginary;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+/// Hann window, tapers a frame to zero at both ends so the cut does not show up as noise
+pub fn hann(size: usize) -> Vec<f32> {
+    (0..size).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / size as f32).cos()).collect()
+}
+
+/// Amplitude of each frequency bin of a windowed frame, `size / 2 + 1` bins from 0 Hz to half the sample rate
+///
+/// A full scale sine gives about 1 in its bin. Frames shorter than the window are padded with silence.
+pub fn magnitudes(frame: &[f32], window: &[f32]) -> Vec<f32> {
+    let size = window.len();
+    let mut real: Vec<f32> = (0..size).map(|i| frame.get(i).copied().unwrap_or(0.0) * window[i]).collect();
+    let mut imaginary = vec![0.0; size];
+    fft(&mut real, &mut imaginary);
+    // the window halves the amplitude on average, and half the energy is in the mirrored bins
+    let scale = 2.0 / window.iter().sum::<f32>();
+    (0..=size / 2).map(|bin| (real[bin] * real[bin] + imaginary[bin] * imaginary[bin]).sqrt() * scale).collect()
+}
+
+/// Decibels relative to full scale, floored at -120
+pub fn to_db(amplitude: f32) -> f32 {
+    (20.0 * amplitude.max(1e-6).log10()).max(-120.0)
+}
@@ -0,0 +1,164 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use rand::Rng;
+use crate::buffer::AudioBuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [Waveform::Sine, Waveform::Square, Waveform::Sawtooth, Waveform::Triangle];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Waveform::Sine => "sine",
+            Waveform::Square => "square",
+            Waveform::Sawtooth => "sawtooth",
+            Waveform::Triangle => "triangle",
+        }
+    }
+
+    /// Value at a point of the period, `phase` between 0 and 1
+    pub fn sample(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Waveform::ALL
+            .into_iter()
+            .find(|waveform| waveform.name().eq_ignore_ascii_case(value) || (value.eq_ignore_ascii_case("saw") && *waveform == Waveform::Sawtooth))
+            .ok_or_else(|| format!("Unknown waveform {}, use sine, square, sawtooth or triangle", value))
+    }
+}
+
+/// White has the same energy at every frequency, pink and brown fall off by 3 and 6 dB per octave
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseColor {
+    #[default]
+    White,
+    Pink,
+    Brown,
+}
+
+impl NoiseColor {
+    pub const ALL: [NoiseColor; 3] = [NoiseColor::White, NoiseColor::Pink, NoiseColor::Brown];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoiseColor::White => "white",
+            NoiseColor::Pink => "pink",
+            NoiseColor::Brown => "brown",
+        }
+    }
+}
+
+impl fmt::Display for NoiseColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for NoiseColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        NoiseColor::ALL
+            .into_iter()
+            .find(|color| color.name().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("Unknown noise {}, use white, pink or brown", value))
+    }
+}
+
+/// Something to generate, always mono
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    Tone { waveform: Waveform, frequency: f32 },
+    Noise(NoiseColor),
+    Silence,
+}
+
+impl Signal {
+    /// `seconds` of the signal peaking at `amplitude`
+    pub fn generate(&self, seconds: f64, sample_rate: u32, amplitude: f32) -> AudioBuffer {
+        let frames = (seconds.max(0.0) * sample_rate as f64).round() as usize;
+        let samples = match self {
+            Signal::Tone { waveform, frequency } => {
+                let step = frequency / sample_rate as f32;
+                // the phase is kept between 0 and 1 so long tones stay precise
+                let mut phase = 0.0f32;
+                (0..frames)
+                    .map(|_| {
+                        let sample = waveform.sample(phase) * amplitude;
+                        phase = (phase + step).fract();
+                        sample
+                    })
+                    .collect()
+            }
+            Signal::Noise(color) => normalize(noise(*color, frames), amplitude),
+            Signal::Silence => vec![0.0; frames],
+        };
+        AudioBuffer::new(sample_rate, 1, samples)
+    }
+}
+
+fn noise(color: NoiseColor, frames: usize) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    let mut white = move || rng.gen_range(-1.0f32..=1.0);
+    match color {
+        NoiseColor::White => (0..frames).map(|_| white()).collect(),
+        NoiseColor::Pink => {
+            // Paul Kellett's economy filter, three poles are close enough to -3 dB per octave
+            let (mut b0, mut b1, mut b2) = (0.0f32, 0.0f32, 0.0f32);
+            (0..frames)
+                .map(|_| {
+                    let white = white();
+                    b0 = 0.99765 * b0 + white * 0.0990460;
+                    b1 = 0.96300 * b1 + white * 0.2965164;
+                    b2 = 0.57000 * b2 + white * 1.0526913;
+                    b0 + b1 + b2 + white * 0.1848
+                })
+                .collect()
+        }
+        NoiseColor::Brown => {
+            // leaky integration of white noise, the leak keeps it from drifting away
+            let mut last = 0.0f32;
+            (0..frames)
+                .map(|_| {
+                    last = (last + 0.02 * white()) / 1.02;
+                    last
+                })
+                .collect()
+        }
+    }
+}
+
+/// Scales the samples so the loudest is at `amplitude`
+fn normalize(mut samples: Vec<f32>, amplitude: f32) -> Vec<f32> {
+    let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    if peak > 0.0 {
+        samples.iter_mut().for_each(|sample| *sample *= amplitude / peak);
+    }
+    samples
+}
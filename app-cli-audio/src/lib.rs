@@ -0,0 +1,10 @@
+pub mod analysis;
+pub mod buffer;
+pub mod decode;
+pub mod effects;
+pub mod fft;
+pub mod generate;
+#[cfg(feature = "playback")]
+pub mod playback;
+pub mod plot;
+pub mod wav;
@@ -0,0 +1,13 @@
+use std::error::Error;
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, Sink};
+use crate::buffer::AudioBuffer;
+
+/// Plays on the default sound device and returns once done
+pub fn play(buffer: &AudioBuffer) -> Result<(), Box<dyn Error>> {
+    let (_stream, handle) = OutputStream::try_default()?;
+    let sink = Sink::try_new(&handle)?;
+    sink.append(SamplesBuffer::new(buffer.channels, buffer.sample_rate, buffer.samples.clone()));
+    sink.sleep_until_end();
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use image::{Rgb, RgbImage};
+use crate::analysis::{Spectrogram, WaveformPoint};
+
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 28]);
+const CENTER_LINE: Rgb<u8> = Rgb([60, 60, 68]);
+const PEAK_COLOR: Rgb<u8> = Rgb([70, 130, 200]);
+const RMS_COLOR: Rgb<u8> = Rgb([150, 200, 255]);
+/// Levels shown in a spectrogram, quieter ones are black
+const FLOOR_DB: f32 = -100.0;
+
+/// Peaks drawn around a center line with the RMS inside, each column shows the points under it
+pub fn waveform_image(points: &[WaveformPoint], width: u32, height: u32) -> RgbImage {
+    let mut image = RgbImage::from_pixel(width, height, BACKGROUND);
+    let center = height as f32 / 2.0;
+    let row = |level: f32| (center - level.clamp(-1.0, 1.0) * center).clamp(0.0, height as f32 - 1.0) as u32;
+    for x in 0..width {
+        image.put_pixel(x, height / 2, CENTER_LINE);
+        if points.is_empty() {
+            continue;
+        }
+        let first = x as usize * points.len() / width as usize;
+        let last = ((x as usize + 1) * points.len() / width as usize).max(first + 1).min(points.len());
+        let column = &points[first..last];
+        let max = column.iter().fold(0.0f32, |max, point| max.max(point.max));
+        let min = column.iter().fold(0.0f32, |min, point| min.min(point.min));
+        let rms = column.iter().fold(0.0f32, |rms, point| rms.max(point.rms));
+        for y in row(max)..=row(min) {
+            image.put_pixel(x, y, PEAK_COLOR);
+        }
+        for y in row(rms)..=row(-rms) {
+            image.put_pixel(x, y, RMS_COLOR);
+        }
+    }
+    image
+}
+
+/// One column per spectrogram column, low frequencies at the bottom, `height` rows spread over the bins
+pub fn spectrogram_image(spectrogram: &Spectrogram, height: u32) -> RgbImage {
+    let width = spectrogram.columns.len().max(1) as u32;
+    let mut image = RgbImage::from_pixel(width, height, Rgb([0, 0, 0]));
+    for (x, column) in spectrogram.columns.iter().enumerate() {
+        for y in 0..height {
+            let first = (height - 1 - y) as usize * column.len() / height as usize;
+            let last = ((height - y) as usize * column.len() / height as usize).max(first + 1).min(column.len());
+            let level = column[first..last].iter().copied().fold(FLOOR_DB, f32::max);
+            image.put_pixel(x as u32, y, heat(level));
+        }
+    }
+    image
+}
+
+/// Black through purple, red and yellow to white as the level goes from the floor to full scale
+fn heat(db: f32) -> Rgb<u8> {
+    let level = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+    let stops: [[f32; 3]; 5] = [[0.0, 0.0, 0.0], [80.0, 20.0, 120.0], [210.0, 40.0, 50.0], [250.0, 200.0, 40.0], [255.0, 255.0, 255.0]];
+    let position = level * (stops.len() - 1) as f32;
+    let index = (position as usize).min(stops.len() - 2);
+    let weight = position - index as f32;
+    let [r, g, b] = [0, 1, 2].map(|channel| (stops[index][channel] + (stops[index + 1][channel] - stops[index][channel]) * weight) as u8);
+    Rgb([r, g, b])
+}
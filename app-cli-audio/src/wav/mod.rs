@@ -0,0 +1,20 @@
+use std::error::Error;
+use std::path::Path;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use crate::buffer::AudioBuffer;
+
+/// Writes 16 bit PCM, samples beyond -1 and 1 are clipped
+pub fn write_wav(buffer: &AudioBuffer, path: &Path) -> Result<(), Box<dyn Error>> {
+    let spec = WavSpec {
+        channels: buffer.channels,
+        sample_rate: buffer.sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(path, spec)?;
+    for sample in &buffer.samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use app_cli_audio::analysis::{analyze, estimate_tempo, spectrogram, waveform, AnalysisOptions};
+    use app_cli_audio::buffer::AudioBuffer;
+    use app_cli_audio::fft::{hann, magnitudes};
+    use app_cli_audio::generate::{NoiseColor, Signal, Waveform};
+    use app_cli_audio::plot::{spectrogram_image, waveform_image};
+
+    /// Short 2 kHz blips `bpm` times a minute over quiet noise
+    fn clicks(bpm: f32, seconds: f64, sample_rate: u32) -> Vec<f32> {
+        let mut samples = Signal::Noise(NoiseColor::White).generate(seconds, sample_rate, 0.01).samples;
+        let period = (60.0 / bpm * sample_rate as f32) as usize;
+        for start in (0..samples.len()).step_by(period) {
+            for (i, sample) in samples[start..].iter_mut().take(1500).enumerate() {
+                *sample += 0.8 * (-(i as f32) / 300.0).exp() * (2.0 * std::f32::consts::PI * 2000.0 * i as f32 / sample_rate as f32).sin();
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn measures_levels_and_frequencies() {
+        let tone = Signal::Tone { waveform: Waveform::Sine, frequency: 1000.0 }.generate(2.0, 48000, 0.5);
+        let window = hann(4096);
+        let bins = magnitudes(&tone.samples[..4096], &window);
+        let (bin, amplitude) = bins.iter().enumerate().max_by(|(_, a), (_, b)| a.total_cmp(b)).unwrap();
+        assert_eq!(bin, (1000.0f32 * 4096.0 / 48000.0).round() as usize);
+        assert!((amplitude - 0.5).abs() < 0.1, "amplitude {}", amplitude);
+
+        let stereo = AudioBuffer::new(48000, 2, tone.samples.iter().flat_map(|sample| [*sample, *sample]).collect());
+        let analysis = analyze(&stereo, &AnalysisOptions { points: 100, ..Default::default() });
+        assert_eq!((analysis.channels, analysis.waveform.len()), (2, 100));
+        assert!((analysis.peak + 6.02).abs() < 0.1);
+        assert!((analysis.rms + 9.03).abs() < 0.1);
+        assert!((analysis.spectrum.peak_frequency().unwrap() - 1000.0).abs() < 12.0);
+        assert!(analysis.waveform.iter().all(|point| (point.peak() - 0.5).abs() < 0.01));
+
+        let json = serde_json::to_value(&analysis).unwrap();
+        assert_eq!(json["spectrum"]["levels"].as_array().unwrap().len(), 2049);
+        assert!(json["tempo"].is_null());
+        assert_eq!(waveform(&[0.5, -0.25], 10).len(), 2);
+    }
+
+    #[test]
+    fn estimates_tempo() {
+        for bpm in [90.0, 128.0] {
+            let tempo = estimate_tempo(&clicks(bpm, 15.0, 44100), 44100).unwrap();
+            assert!((tempo - bpm).abs() < 1.0, "{} BPM found as {}", bpm, tempo);
+        }
+        assert_eq!(estimate_tempo(&vec![0.0; 44100 * 10], 44100), None);
+        assert_eq!(estimate_tempo(&clicks(120.0, 1.0, 44100), 44100), None);
+    }
+
+    #[test]
+    fn draws_pictures() {
+        let samples = clicks(120.0, 4.0, 8000);
+        let spectrogram = spectrogram(&samples, 8000, 256, 160);
+        assert_eq!(spectrogram.columns.len(), 200);
+        assert!(spectrogram.columns.iter().all(|column| column.len() == 129));
+        let image = spectrogram_image(&spectrogram, 64);
+        assert_eq!(image.dimensions(), (200, 64));
+
+        let image = waveform_image(&waveform(&samples, 300), 300, 80);
+        assert_eq!(image.dimensions(), (300, 80));
+        assert_ne!(image.get_pixel(0, 40), image.get_pixel(0, 0));
+    }
+}
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use app_cli_audio::buffer::AudioBuffer;
+    use app_cli_audio::effects::{Effect, EffectChain};
+    use app_cli_audio::generate::{Signal, Waveform};
+
+    fn sine(frequency: f32) -> AudioBuffer {
+        Signal::Tone { waveform: Waveform::Sine, frequency }.generate(1.0, 48000, 0.5)
+    }
+
+    #[test]
+    fn parses_chains() {
+        let chain: EffectChain = "lowpass=800, rate=1.5,gain=-6,fadeout=3".parse().unwrap();
+        assert_eq!(chain, EffectChain::new().low_pass(800.0).rate(1.5).gain(-6.0).fade_out(3.0));
+        assert_eq!(chain.to_string(), "lowpass=800,rate=1.5,gain=-6,fadeout=3");
+        assert_eq!("HighPass=120".parse::<Effect>(), Ok(Effect::HighPass(120.0)));
+        assert!("rate=0".parse::<Effect>().is_err());
+        assert!("echo=2".parse::<Effect>().is_err());
+        assert!("fadein".parse::<Effect>().is_err());
+        assert!("".parse::<EffectChain>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn filters_and_fades() {
+        let low_pass = EffectChain::new().low_pass(500.0);
+        assert!(low_pass.apply(sine(100.0)).rms() > 0.3);
+        assert!(low_pass.apply(sine(8000.0)).rms() < 0.01);
+        assert!(EffectChain::new().high_pass(5000.0).apply(sine(100.0)).rms() < 0.01);
+        assert!((EffectChain::new().gain(-6.0).apply(sine(100.0)).peak() - 0.25).abs() < 0.01);
+
+        let faded = EffectChain::new().fade_in(0.5).fade_out(0.5).apply(AudioBuffer::new(100, 2, vec![1.0; 200]));
+        assert_eq!(&faded.samples[..2], &[0.0, 0.0]);
+        assert_eq!(faded.samples[100], 1.0);
+        assert!(faded.samples[198] < 0.05);
+    }
+
+    #[test]
+    fn changes_the_rate() {
+        let buffer = AudioBuffer::new(4, 2, vec![0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0]);
+        let faster = Effect::Rate(2.0).apply(buffer.clone());
+        assert_eq!(faster.samples, vec![0.0, 1.0, 2.0, 3.0]);
+        let slower = Effect::Rate(0.5).apply(buffer);
+        assert_eq!(slower.frames(), 8);
+        assert_eq!(&slower.samples[..4], &[0.0, 1.0, 0.5, 1.5]);
+    }
+}
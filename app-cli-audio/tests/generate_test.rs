@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use app_cli_audio::decode::decode_file;
+    use app_cli_audio::generate::{NoiseColor, Signal, Waveform};
+    use app_cli_audio::wav::write_wav;
+
+    #[test]
+    fn generates_tones_and_noise() {
+        let tone = Signal::Tone { waveform: Waveform::Sine, frequency: 440.0 }.generate(1.0, 48000, 0.5);
+        assert_eq!((tone.channels, tone.frames()), (1, 48000));
+        assert!((tone.peak() - 0.5).abs() < 0.001);
+        let crossings = tone.samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+        assert!((439..=441).contains(&crossings), "{} periods", crossings);
+
+        for color in NoiseColor::ALL {
+            let noise = Signal::Noise(color).generate(0.5, 8000, 0.8);
+            assert!((noise.peak() - 0.8).abs() < 0.001, "{} noise", color);
+        }
+        assert_eq!("Saw".parse::<Waveform>(), Ok(Waveform::Sawtooth));
+        assert!("pinkish".parse::<NoiseColor>().is_err());
+    }
+
+    #[test]
+    fn renders_and_decodes_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.wav");
+        let tone = Signal::Tone { waveform: Waveform::Triangle, frequency: 100.0 }.generate(0.25, 22050, 0.9);
+        write_wav(&tone, &path).unwrap();
+
+        let decoded = decode_file(&path).unwrap();
+        assert_eq!((decoded.sample_rate, decoded.channels, decoded.frames()), (22050, 1, tone.frames()));
+        let error = decoded.samples.iter().zip(&tone.samples).fold(0.0f32, |error, (a, b)| error.max((a - b).abs()));
+        assert!(error < 0.001);
+        assert!(decode_file(&dir.path().join("missing.wav")).is_err());
+    }
+}
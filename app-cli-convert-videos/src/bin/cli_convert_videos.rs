@@ -1,21 +1,53 @@
-use app_cli_convert_videos::encode_video;
-use clap::{Arg, Command as ClapCommand, Command};
+use app_cli_convert_videos::jobs::{default_state_file, JobEngine, JobState};
+use app_cli_convert_videos::quarantine::Quarantine;
+use app_cli_convert_videos::report::{load_history, summarize};
+use app_cli_convert_videos::watch::watch;
+use app_cli_convert_videos::{AppConfig, ConfigOverrides};
+use clap::{Arg, ArgAction, ArgMatches, Command as ClapCommand, Command};
 use indicatif::{ProgressBar, ProgressStyle};
+use lib_ffmpeg_utils::edit::{detect_crop, format_timestamp, parse_timestamp, Crop, EditInstructions, Rotation};
+use lib_ffmpeg_utils::encode::parse_size;
+use lib_ffmpeg_utils::capabilities::Capabilities;
+use lib_ffmpeg_utils::presets::all_presets;
+use lib_ffmpeg_utils::probe::probe;
+use lib_ffmpeg_utils::subtitles::{
+    extract_subtitle, sidecar_subtitles, subtitle_output_path, subtitle_tracks, SubtitleFormat,
+};
 use lib_egui_utils::{generate_output_path, list_files_from_dir2, SortBy};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::thread::sleep;
+use std::time::Duration;
+
+const DEFAULT_CONFIG_FILE: &str = "app_config.yaml";
 
 fn main() {
     let matches = Command::new("Video Encoder")
         .version("1.0")
-        .about("Encodes video files using ffmpeg")
+        .about("Encodes video files using ffmpeg, with the settings of app_config.yaml unless overridden")
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("CONFIG_FILE")
+                .help("Settings file shared with the UI [default: app_config.yaml]")
+                .global(true),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("PROFILE")
+                .help("Named profile of the config file to use")
+                .global(true),
+        )
         .arg(
             Arg::new("input")
                 .short('i')
                 .long("input")
                 .value_name("INPUT_FOLDER")
                 .help("Input folder containing videos")
-                .required(true),
+                .global(true),
         )
         .arg(
             Arg::new("output")
@@ -23,79 +55,708 @@ fn main() {
                 .long("output")
                 .value_name("OUTPUT_FOLDER")
                 .help("Output folder for encoded videos")
-                .required(true),
+                .global(true),
         )
         .arg(
             Arg::new("delete")
                 .short('d')
                 .long("delete")
-                .help("Delete original files after encoding"),
+                .action(ArgAction::SetTrue)
+                .help("Move original files to the quarantine folder once the encode is verified")
+                .global(true),
+        )
+        .arg(
+            Arg::new("skip-existing")
+                .long("skip-existing")
+                .action(ArgAction::SetTrue)
+                .help("Skip files already encoded with the same settings, even under another name")
+                .global(true),
         )
         .arg(
             Arg::new("format")
                 .short('f')
                 .long("format")
                 .value_name("OUTPUT_FORMAT")
-                .help("Output video format (mp4 or mpeg)")
-                .default_value("mp4"),
+                .help("Output video format (mp4 or mpeg) [default: mp4]")
+                .global(true),
+        )
+        .arg(
+            Arg::new("encoding")
+                .short('v')
+                .long("encoding")
+                .value_name("VIDEO_CODEC")
+                .help("Video encoder used without a preset [default: libx265]")
+                .global(true),
         )
         .arg(
             Arg::new("audio")
                 .short('a')
                 .long("audio")
                 .value_name("AUDIO_FORMAT")
-                .help("Audio format (aac or mp3)")
-                .default_value("aac"),
+                .help("Audio format (aac or mp3) [default: aac]")
+                .global(true),
         )
         .arg(
             Arg::new("extension")
                 .short('e')
                 .long("extension")
                 .value_name("EXTENSION")
-                .help("Input file extension (e.g., mov)")
-                .default_value("mov"),
+                .help("Input file extension (e.g., mov) [default: mov]")
+                .global(true),
+        )
+        .arg(
+            Arg::new("preset")
+                .short('p')
+                .long("preset")
+                .value_name("PRESET")
+                .help("Name of the encode preset to use (see --list-presets)")
+                .global(true),
+        )
+        .arg(
+            Arg::new("target-size")
+                .short('s')
+                .long("target-size")
+                .value_name("SIZE")
+                .help("Fit each video into SIZE (like 25MB) with a two-pass encode")
+                .global(true),
+        )
+        .arg(
+            Arg::new("workers")
+                .short('w')
+                .long("workers")
+                .value_name("COUNT")
+                .help("Number of videos encoded at the same time [default: 1]")
+                .value_parser(clap::value_parser!(usize))
+                .global(true),
+        )
+        .arg(
+            Arg::new("state")
+                .long("state")
+                .value_name("FILE")
+                .help("Job queue file, unfinished jobs in it are resumed")
+                .global(true),
+        )
+        .arg(
+            Arg::new("quarantine")
+                .long("quarantine")
+                .value_name("FOLDER")
+                .help("Folder where deleted originals are kept, see the undo command")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("jobs")
+                .about("Inspect and control the persistent job queue")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List all the jobs"))
+                .subcommand(Command::new("run").about("Run the queued jobs"))
+                .subcommand(Command::new("clear").about("Remove done, skipped and cancelled jobs"))
+                .subcommand(job_id_command("pause", "Pause a queued job"))
+                .subcommand(job_id_command("resume", "Queue a paused job again"))
+                .subcommand(job_id_command("cancel", "Cancel a job"))
+                .subcommand(job_id_command("retry", "Queue a failed or cancelled job again, or all of them with 'all'")),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Inspect the encode cache used by --skip-existing")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List the recorded encodes"))
+                .subcommand(Command::new("prune").about("Forget the encodes whose output is gone or was replaced")),
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Watch the input folder and convert new videos as they arrive"),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Summarize the history of all the runs")
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("DATE")
+                        .help("Only count jobs finished on or after DATE (YYYY-MM-DD)"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the summary as json"),
+                ),
+        )
+        .subcommand(
+            Command::new("trim")
+                .about("Encode a part of a video")
+                .arg(file_arg())
+                .arg(Arg::new("start").long("start").value_name("TIME").help("Start time, like 83.5, 1:23.5 or 00:01:23"))
+                .arg(Arg::new("end").long("end").value_name("TIME").help("End time, the end of the video by default")),
+        )
+        .subcommand(
+            Command::new("rotate")
+                .about("Encode a video rotated")
+                .arg(file_arg())
+                .arg(Arg::new("rotation").value_name("ROTATION").required(true).help("cw, ccw or 180")),
+        )
+        .subcommand(
+            Command::new("crop")
+                .about("Encode a video without its black bars")
+                .arg(file_arg())
+                .arg(
+                    Arg::new("crop")
+                        .long("crop")
+                        .value_name("W:H:X:Y")
+                        .help("Area to keep, detected with cropdetect when not given"),
+                ),
+        )
+        .subcommand(
+            Command::new("concat")
+                .about("Encode videos one after the other into a single file, named after the first one")
+                .arg(
+                    Arg::new("files")
+                        .value_name("FILE")
+                        .num_args(2..)
+                        .required(true)
+                        .help("Videos with the same resolution and codecs"),
+                ),
+        )
+        .subcommand(
+            Command::new("subtitles")
+                .about("List, extract, add or burn subtitles")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List the subtitle tracks of a video").arg(video_arg()))
+                .subcommand(
+                    Command::new("extract")
+                        .about("Save subtitle tracks as text files next to the video")
+                        .arg(video_arg())
+                        .arg(
+                            Arg::new("track")
+                                .long("track")
+                                .value_name("TRACK")
+                                .value_parser(clap::value_parser!(usize))
+                                .help("Subtitle track number, every text track by default"),
+                        )
+                        .arg(
+                            Arg::new("format")
+                                .long("format")
+                                .value_name("FORMAT")
+                                .value_parser(["srt", "ass"])
+                                .default_value("srt"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("add")
+                        .about("Encode a video with the .srt files named after it, like trip.srt or trip.en.srt")
+                        .arg(file_arg()),
+                )
+                .subcommand(
+                    Command::new("burn")
+                        .about("Encode a video with a subtitle track drawn into the picture")
+                        .arg(file_arg())
+                        .arg(
+                            Arg::new("track")
+                                .long("track")
+                                .value_name("TRACK")
+                                .value_parser(clap::value_parser!(usize))
+                                .default_value("0")
+                                .help("Subtitle track number, as listed by `subtitles list`"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("undo")
+                .about("Move quarantined originals back, the last one by default")
+                .arg(Arg::new("id").value_name("ID").help("Undo log id, or 'all'"))
+                .arg(
+                    Arg::new("list")
+                        .short('l')
+                        .long("list")
+                        .action(ArgAction::SetTrue)
+                        .help("List the quarantined originals"),
+                ),
+        )
+        .arg(
+            Arg::new("list-presets")
+                .long("list-presets")
+                .action(ArgAction::SetTrue)
+                .help("List the available encode presets and exit"),
+        )
+        .arg(
+            Arg::new("list-profiles")
+                .long("list-profiles")
+                .action(ArgAction::SetTrue)
+                .help("List the profiles of the config file and exit"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .help("Check the settings against the installed ffmpeg and exit"),
         )
         .get_matches();
 
-    let input = matches.get_one::<String>("input").unwrap();
-    let output = matches.get_one::<String>("output").unwrap();
-    let delete = matches.contains_id("delete");
-    let video_format = matches.get_one::<String>("format").unwrap();
-    let audio_format = matches.get_one::<String>("audio").unwrap();
-    let extension = matches.get_one::<String>("extension").unwrap();
+    if matches.get_flag("list-presets") {
+        for preset in all_presets() {
+            println!("{:<28} {} {}", preset.name, preset.container, preset.output_args().join(" "));
+        }
+        return;
+    }
+
+    let config = load_config(&matches);
+
+    if matches.get_flag("list-profiles") {
+        for (name, profile) in &config.profiles {
+            println!("{:<20} {}", name, serde_yaml::to_string(profile).unwrap().trim().replace('\n', ", "));
+        }
+        return;
+    }
+
+    let state_file = matches.get_one::<String>("state").map(PathBuf::from).unwrap_or_else(default_state_file);
+
+    if let Some(("undo", undo_matches)) = matches.subcommand() {
+        undo_command(&Quarantine::new(&config.quarantine_dir()), undo_matches);
+        return;
+    }
+
+    let engine = JobEngine::open(&state_file, config.engine_options())
+        .unwrap_or_else(|e| {
+            eprintln!("Cannot open the job queue {}: {}", state_file.display(), e);
+            exit(1);
+        });
+
+    match matches.subcommand() {
+        Some(("jobs", jobs_matches)) => {
+            jobs_command(&engine, jobs_matches);
+            return;
+        }
+        Some(("cache", cache_matches)) => {
+            match cache_matches.subcommand() {
+                Some(("prune", _)) => println!("Removed {} entries", engine.prune_cache()),
+                _ => {
+                    for entry in engine.cache_entries() {
+                        let status = if entry.output_is_intact() { "" } else { " (output changed)" };
+                        println!("{} {} {} -> {}{}", entry.encoded_at, entry.source_hash.get(..12).unwrap_or(&entry.source_hash), entry.source, entry.output, status);
+                    }
+                }
+            }
+            return;
+        }
+        Some(("report", report_matches)) => {
+            report_command(&engine, report_matches);
+            return;
+        }
+        Some(("subtitles", subtitles_matches)) => {
+            subtitles_command(&config, &engine, subtitles_matches);
+            return;
+        }
+        Some((name @ ("trim" | "rotate" | "crop" | "concat"), edit_matches)) => {
+            check_config(&config);
+            edit_command(&config, &engine, name, edit_matches);
+            return;
+        }
+        Some(("watch", _)) => {
+            check_config(&config);
+            if let Err(e) = watch(&config, &engine) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    check_config(&config);
+    if matches.get_flag("check") {
+        println!("Settings are valid, preset: {}", config.encode_preset().name);
+        return;
+    }
+
+    let preset = config.encode_preset();
+    let target_size = parse_size(&config.target_size);
 
     // Ensure output directory exists
-    fs::create_dir_all(output).expect("Failed to create output directory");
+    fs::create_dir_all(&config.output_folder).expect("Failed to create output directory");
 
     // Get a list of input files
-    let files: Vec<_> = list_files_from_dir2(input, extension, SortBy::LastUpdated, true);
-
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} {msg}").expect("REASON")
-            .progress_chars("#>-"),
-    );
+    let files: Vec<_> = list_files_from_dir2(&config.input_folder, &config.file_extension, SortBy::LastUpdated, true);
 
     for filename in files {
-        let file = PathBuf::from(filename.clone());
-        let output_file = generate_output_path(&filename.clone(), output.into(), video_format.into());
+        let output_file = generate_output_path(&filename, config.output_folder.clone(), preset.container.clone());
+        engine.enqueue(&filename, &output_file, &preset, target_size);
+    }
+
+    run_jobs(&engine);
+}
+
+/// Settings of the config file, then of the selected profile, then of the command line
+fn load_config(matches: &ArgMatches) -> AppConfig {
+    // what the command line used before it read the config file
+    let mut config = AppConfig {
+        video_format: "mp4".to_string(),
+        audio_format: "aac".to_string(),
+        file_extension: "mov".to_string(),
+        encoding: "libx265".to_string(),
+        workers: 1,
+        ..Default::default()
+    };
 
-        pb.set_message(format!("Encoding: {}", filename));
+    let config_file = matches.get_one::<String>("config");
+    let path = config_file.map(String::as_str).unwrap_or(DEFAULT_CONFIG_FILE);
+    if config_file.is_some() || Path::new(path).exists() {
+        config = AppConfig::load(path).unwrap_or_else(|e| {
+            eprintln!("Cannot load {}: {}", path, e);
+            exit(1);
+        });
+    }
+
+    if let Some(profile) = matches.get_one::<String>("profile") {
+        config.apply_profile(profile).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        });
+    }
+
+    let text = |id: &str| matches.get_one::<String>(id).cloned();
+    let flag = |id: &str| if matches.get_flag(id) { Some(true) } else { None };
+    config.apply(&ConfigOverrides {
+        input_folder: text("input"),
+        output_folder: text("output"),
+        video_format: text("format"),
+        audio_format: text("audio"),
+        file_extension: text("extension"),
+        delete_original: flag("delete"),
+        skip_if_exists: flag("skip-existing"),
+        encoding: text("encoding"),
+        preset: text("preset"),
+        target_size: text("target-size"),
+        workers: matches.get_one::<usize>("workers").copied(),
+        quarantine_folder: text("quarantine"),
+    });
+    config
+}
+
+/// Exits with the list of problems when the settings cannot work
+fn check_config(config: &AppConfig) {
+    let capabilities = Capabilities::detect()
+        .map_err(|e| eprintln!("Cannot check the ffmpeg encoders: {}", e))
+        .ok();
+    let problems = config.validate(capabilities.as_ref());
+    if !problems.is_empty() {
+        for problem in problems {
+            eprintln!("{}", problem);
+        }
+        exit(1);
+    }
+}
+
+fn job_id_command(name: &'static str, about: &'static str) -> Command {
+    Command::new(name)
+        .about(about)
+        .arg(Arg::new("id").value_name("JOB_ID").required(true))
+}
+
+fn file_arg() -> Arg {
+    Arg::new("file").value_name("FILE").required(true).help("Video to encode")
+}
+
+fn video_arg() -> Arg {
+    Arg::new("file").value_name("FILE").required(true).help("Video with subtitles")
+}
+
+/// Queues a single edited encode of the file and runs it
+fn edit_command(config: &AppConfig, engine: &JobEngine, name: &str, matches: &ArgMatches) {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        exit(1);
+    };
+    let timestamp = |id: &str| {
+        matches.get_one::<String>(id).map(|value| {
+            parse_timestamp(value).unwrap_or_else(|| fail(format!("Invalid {} time {}", id, value)))
+        })
+    };
+
+    let mut edits = EditInstructions::default();
+    let file = match name {
+        "concat" => {
+            let mut files = matches.get_many::<String>("files").unwrap().cloned();
+            let first = files.next().unwrap();
+            edits.append = files.collect();
+            first
+        }
+        _ => matches.get_one::<String>("file").unwrap().clone(),
+    };
+    match name {
+        "trim" => {
+            edits.start = timestamp("start");
+            edits.end = timestamp("end");
+            if let (Some(start), Some(end)) = (edits.start, edits.end) {
+                if end <= start {
+                    fail(format!("The end {} is not after the start {}", format_timestamp(end), format_timestamp(start)));
+                }
+            }
+        }
+        "rotate" => {
+            let rotation = matches.get_one::<String>("rotation").unwrap();
+            edits.rotate = rotation.parse::<Rotation>().unwrap_or_else(|e| fail(e));
+        }
+        "crop" => {
+            edits.crop = match matches.get_one::<String>("crop") {
+                Some(crop) => Some(crop.parse::<Crop>().unwrap_or_else(|e| fail(e))),
+                None => match detect_crop(Path::new(&file)) {
+                    Ok(Some(crop)) => {
+                        println!("Detected crop {}", crop);
+                        Some(crop)
+                    }
+                    Ok(None) => fail(format!("{} has no black bars to crop", file)),
+                    Err(e) => fail(format!("Cannot detect the crop of {}: {}", file, e)),
+                },
+            };
+        }
+        _ => {}
+    }
+    enqueue_edit(config, engine, &file, edits);
+}
+
+/// Queues the edited encode of `file` and runs it
+fn enqueue_edit(config: &AppConfig, engine: &JobEngine, file: &str, edits: EditInstructions) {
+    for clip in edits.clips(Path::new(file)) {
+        if !clip.is_file() {
+            eprintln!("{} does not exist", clip.display());
+            exit(1);
+        }
+    }
+
+    let preset = config.encode_preset();
+    fs::create_dir_all(&config.output_folder).expect("Failed to create output directory");
+    let output = generate_output_path(&file.to_string(), config.output_folder.clone(), preset.container.clone());
+    match engine.enqueue_edited(file, &output, &preset, parse_size(&config.target_size), edits) {
+        Some(id) => println!("Queued job {}: {} -> {}", id, file, output),
+        None => println!("{} is already queued", file),
+    }
+    run_jobs(engine);
+}
+
+fn subtitles_command(config: &AppConfig, engine: &JobEngine, matches: &ArgMatches) {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        exit(1);
+    };
+    let (name, matches) = matches.subcommand().unwrap();
+    let file = matches.get_one::<String>("file").unwrap();
+    let tracks = || {
+        let probe = probe(file).unwrap_or_else(|e| fail(format!("Cannot probe {}: {}", file, e)));
+        subtitle_tracks(&probe)
+    };
+
+    match name {
+        "list" => {
+            let tracks = tracks();
+            if tracks.is_empty() {
+                println!("{} has no subtitles", file);
+            }
+            for track in tracks {
+                println!("{}{}", track.label(), if track.is_text() { "" } else { " (pictures, cannot be extracted)" });
+            }
+            for sidecar in sidecar_subtitles(Path::new(file)) {
+                println!("sidecar {}", sidecar.display());
+            }
+        }
+        "extract" => {
+            let format = matches.get_one::<String>("format").unwrap().parse::<SubtitleFormat>().unwrap_or_else(|e| fail(e));
+            let selected: Vec<_> = match matches.get_one::<usize>("track") {
+                Some(track) => {
+                    let found = tracks().into_iter().find(|t| t.track == *track);
+                    vec![found.unwrap_or_else(|| fail(format!("{} has no subtitle track {}", file, track)))]
+                }
+                None => tracks().into_iter().filter(|track| track.is_text()).collect(),
+            };
+            if selected.is_empty() {
+                fail(format!("{} has no text subtitles", file));
+            }
+            let mut failed = false;
+            for track in selected {
+                let output = subtitle_output_path(Path::new(file), &track, format);
+                match extract_subtitle(Path::new(file), &track, format, &output) {
+                    Ok(()) => println!("Track {} saved to {}", track.track, output.display()),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                exit(1);
+            }
+        }
+        "add" => {
+            check_config(config);
+            if sidecar_subtitles(Path::new(file)).is_empty() {
+                fail(format!("No .srt file named after {}", file));
+            }
+            enqueue_edit(config, engine, file, EditInstructions { sidecar_subtitles: true, ..Default::default() });
+        }
+        "burn" => {
+            check_config(config);
+            let track = *matches.get_one::<usize>("track").unwrap();
+            match tracks().into_iter().find(|t| t.track == track) {
+                Some(found) if found.is_text() => {}
+                Some(found) => fail(format!("Track {} is made of pictures ({}), only text subtitles can be burned", track, found.codec)),
+                None => fail(format!("{} has no subtitle track {}", file, track)),
+            }
+            enqueue_edit(config, engine, file, EditInstructions { burn_subtitles: Some(track), ..Default::default() });
+        }
+        _ => unreachable!(),
+    }
+}
 
-        let status = encode_video(file.clone(), &String::from("libx265"), &output_file, audio_format);
+fn jobs_command(engine: &JobEngine, matches: &ArgMatches) {
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for job in engine.jobs() {
+                println!(
+                    "{:>4} {:<9} {} -> {} [{}{}]{}",
+                    job.id,
+                    format!("{:?}", job.state),
+                    job.input,
+                    job.output,
+                    job.preset.name,
+                    if job.edits.is_empty() { String::new() } else { format!(", {}", job.edits.summary()) },
+                    job.error.or(job.note).map(|e| format!(" {}", e)).unwrap_or_default()
+                );
+            }
+        }
+        Some(("run", _)) => run_jobs(engine),
+        Some(("clear", _)) => engine.clear_finished(),
+        Some(("retry", m)) if m.get_one::<String>("id").unwrap() == "all" => {
+            for job in engine.jobs().iter().filter(|job| matches!(job.state, JobState::Failed | JobState::Cancelled)) {
+                engine.retry(job.id);
+            }
+            run_jobs(engine);
+        }
+        Some((action, m)) => {
+            let id = m.get_one::<String>("id").unwrap().parse::<u64>().unwrap_or_else(|_| {
+                eprintln!("Invalid job id");
+                exit(1);
+            });
+            let changed = match action {
+                "pause" => engine.pause(id),
+                "resume" => engine.resume(id),
+                "cancel" => engine.cancel(id),
+                _ => engine.retry(id),
+            };
+            if !changed {
+                eprintln!("Cannot {} job {}", action, id);
+                exit(1);
+            }
+            if engine.is_running() {
+                run_jobs(engine);
+            }
+        }
+        None => {}
+    }
+}
 
-        if let Err(e) = status {
-            eprintln!("Failed to run FFmpeg for {:?}: {}", file, e);
-            continue;
+fn report_command(engine: &JobEngine, matches: &ArgMatches) {
+    let mut records = load_history(engine.report_dir()).unwrap_or_else(|e| {
+        eprintln!("Cannot read the history in {}: {}", engine.report_dir().display(), e);
+        exit(1);
+    });
+    if let Some(since) = matches.get_one::<String>("since") {
+        records.retain(|record| record.finished_at.as_str() >= since.as_str());
+    }
+    let summary = summarize(&records);
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+        return;
+    }
+
+    println!(
+        "Jobs: {} done, {} failed, {} skipped (failure rate {:.1}%)",
+        summary.done,
+        summary.failed,
+        summary.skipped,
+        summary.failure_rate * 100.0
+    );
+    println!(
+        "Size: {:.2} GB in, {:.2} GB out, {:.2} GB saved",
+        summary.input_gb, summary.output_gb, summary.saved_gb
+    );
+    if !summary.codecs.is_empty() {
+        println!("{:<16} {:>6} {:>10}", "Codec", "Files", "Avg speed");
+        for codec in &summary.codecs {
+            let speed = codec.average_speed.map(|s| format!("{:.2}x", s)).unwrap_or_else(|| "-".to_string());
+            println!("{:<16} {:>6} {:>10}", codec.codec, codec.encoded, speed);
         }
+    }
+    println!("Reports: {}", engine.report_dir().display());
+}
 
-        if delete {
-            fs::remove_file(&file).expect("Failed to delete original file");
+fn undo_command(quarantine: &Quarantine, matches: &ArgMatches) {
+    let entries = quarantine.entries().unwrap_or_else(|e| {
+        eprintln!("Cannot read the undo log in {}: {}", quarantine.dir().display(), e);
+        exit(1);
+    });
+
+    if matches.get_flag("list") {
+        for entry in entries {
+            println!("{:>4} {} {} (replaced by {})", entry.id, entry.moved_at, entry.original, entry.output);
+        }
+        return;
+    }
+
+    let results = match matches.get_one::<String>("id").map(String::as_str) {
+        None => vec![quarantine.undo_last()],
+        Some("all") => entries.iter().rev().map(|entry| quarantine.undo(entry.id)).collect(),
+        Some(id) => {
+            let id = id.parse::<u64>().unwrap_or_else(|_| {
+                eprintln!("Invalid undo log id");
+                exit(1);
+            });
+            vec![quarantine.undo(id)]
         }
+    };
+
+    let mut failed = false;
+    for result in results {
+        match result {
+            Ok(entry) => println!("Restored {}", entry.original),
+            Err(e) => {
+                eprintln!("Cannot restore: {}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+fn run_jobs(engine: &JobEngine) {
+    let (done, total) = engine.progress();
+    let pb = ProgressBar::new(total as u64);
+    pb.set_position(done as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} {msg}").expect("REASON")
+            .progress_chars("#>-"),
+    );
 
-        pb.inc(1);
+    engine.start();
+    while engine.is_running() {
+        let (done, total) = engine.progress();
+        pb.set_length(total as u64);
+        pb.set_position(done as u64);
+        let running: Vec<String> = engine
+            .jobs()
+            .iter()
+            .filter(|job| job.state == JobState::Running)
+            .map(|job| job.input.clone())
+            .collect();
+        pb.set_message(format!("Encoding: {}", running.join(", ")));
+        pb.tick();
+        sleep(Duration::from_millis(500));
     }
 
+    for job in engine.jobs().iter().filter(|job| job.state == JobState::Failed) {
+        pb.println(format!("Failed {} (job {}): {}", job.input, job.id, job.error.clone().unwrap_or_default()));
+    }
     pb.finish_with_message("Encoding completed");
 }
@@ -1,16 +1,124 @@
-use app_cli_convert_videos::{encode_video, AppConfig, FileStat};
+use app_cli_convert_videos::jobs::{default_state_file, submit_jobs, EngineOptions, Job, JobEngine, JobRequest, JobState};
+use app_cli_convert_videos::{AppConfig, ConfigOverrides};
 use eframe::egui;
 use egui::{include_image, CentralPanel, ComboBox, ProgressBar, RichText};
 use egui_extras::install_image_loaders;
 use egui_remixicon::{add_to_fonts, icons};
-use lib_egui_utils::{format_elapsed_time, format_f64_or_dash, generate_output_path, get_file_name, get_file_size_in_gb, list_files_from_dir2, my_default_options, SortBy};
-use std::collections::VecDeque;
-use std::fs::{self};
+use lib_ffmpeg_utils::edit::{detect_crop, format_timestamp, parse_timestamp, Crop, EditInstructions, Rotation};
+use lib_ffmpeg_utils::encode::parse_size;
+use lib_ffmpeg_utils::presets::{all_presets, EncodePreset};
+use lib_ffmpeg_utils::probe::probe;
+use lib_ffmpeg_utils::subtitles::{sidecar_subtitles, subtitle_tracks, SubtitleTrack};
+use lib_egui_utils::{format_elapsed_time, format_f64_or_dash, generate_output_path, get_file_name, list_files_from_dir2, my_default_options, SortBy};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver};
 use std::thread;
+use std::time::{Duration, Instant};
 use eframe::egui::{Align, Layout};
-use rand::Rng;
+
+/// Runs `work` on its own thread, its result comes through the receiver
+fn in_background<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Receiver<T> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || sender.send(work()).ok());
+    receiver
+}
+
+/// Edits of a queued job, as typed in the edit window
+struct EditForm {
+    job_id: u64,
+    input: String,
+    start: String,
+    end: String,
+    rotate: Rotation,
+    crop: String,
+    append: Vec<String>,
+    sidecar_subtitles: bool,
+    burn_subtitles: Option<usize>,
+    /// Text subtitle tracks of the input, the ones that can be burned
+    subtitle_tracks: Vec<SubtitleTrack>,
+    sidecar_count: usize,
+    /// Subtitle tracks and sidecar count, probed in the background so the window opens at once
+    probing: Option<Receiver<(Vec<SubtitleTrack>, usize)>>,
+    /// Crop found by the Detect button, cropdetect runs ffmpeg several times
+    detecting: Option<Receiver<Result<Option<Crop>, String>>>,
+    error: Option<String>,
+}
+
+impl EditForm {
+    fn new(job: &Job) -> Self {
+        let edits = &job.edits;
+        EditForm {
+            job_id: job.id,
+            input: job.input.clone(),
+            start: edits.start.map(format_timestamp).unwrap_or_default(),
+            end: edits.end.map(format_timestamp).unwrap_or_default(),
+            rotate: edits.rotate,
+            crop: edits.crop.map(|crop| crop.to_string()).unwrap_or_default(),
+            append: edits.append.clone(),
+            sidecar_subtitles: edits.sidecar_subtitles,
+            burn_subtitles: edits.burn_subtitles,
+            subtitle_tracks: Vec::new(),
+            sidecar_count: 0,
+            probing: Some(in_background({
+                let input = job.input.clone();
+                move || {
+                    let tracks = probe(&input)
+                        .map(|probe| subtitle_tracks(&probe).into_iter().filter(|track| track.is_text()).collect())
+                        .unwrap_or_default();
+                    (tracks, sidecar_subtitles(Path::new(&input)).len())
+                }
+            })),
+            detecting: None,
+            error: None,
+        }
+    }
+
+    /// Takes in what the background threads found
+    fn poll(&mut self) {
+        if let Some(Ok((tracks, sidecar_count))) = self.probing.as_ref().map(Receiver::try_recv) {
+            self.subtitle_tracks = tracks;
+            self.sidecar_count = sidecar_count;
+            self.probing = None;
+        }
+        if let Some(Ok(detected)) = self.detecting.as_ref().map(Receiver::try_recv) {
+            match detected {
+                Ok(Some(crop)) => self.crop = crop.to_string(),
+                Ok(None) => self.error = Some("No black bars found".to_string()),
+                Err(e) => self.error = Some(e),
+            }
+            self.detecting = None;
+        }
+    }
+
+    fn edits(&self) -> Result<EditInstructions, String> {
+        let timestamp = |value: &str| -> Result<Option<f64>, String> {
+            match value.trim() {
+                "" => Ok(None),
+                value => parse_timestamp(value).map(Some).ok_or_else(|| format!("Invalid time {}", value)),
+            }
+        };
+        let crop = match self.crop.trim() {
+            "" => None,
+            crop => Some(crop.parse::<Crop>()?),
+        };
+        let edits = EditInstructions {
+            start: timestamp(&self.start)?,
+            end: timestamp(&self.end)?,
+            rotate: self.rotate,
+            crop,
+            append: self.append.clone(),
+            sidecar_subtitles: self.sidecar_subtitles,
+            burn_subtitles: self.burn_subtitles,
+        };
+        if let (Some(start), Some(end)) = (edits.start, edits.end) {
+            if end <= start {
+                return Err("The end must be after the start".to_string());
+            }
+        }
+        Ok(edits)
+    }
+}
 
 // Main application structure
 struct MyApp {
@@ -21,12 +129,21 @@ struct MyApp {
     file_extension: String,
     delete_original: bool,
     skip_if_exists: bool,
-    job_queue: Arc<Mutex<VecDeque<String>>>,
-    total_jobs: usize,
-    is_encoding: Arc<Mutex<bool>>,
+    workers: usize,
+    quarantine_folder: String,
+    profiles: BTreeMap<String, ConfigOverrides>,
+    /// `None` while the CLI or another window runs the queue, new jobs are handed to it then
+    engine: Option<JobEngine>,
+    /// Why there is no engine, or what was handed over
+    queue_note: Option<String>,
+    /// When to try opening the queue again
+    reopen_at: Instant,
     progress: f32,
     encoding: String,
-    file_stats: Arc<Mutex<Vec<FileStat>>>, // Use Arc<Mutex<>>
+    preset: String,
+    presets: Vec<EncodePreset>,
+    target_size: String,
+    editing: Option<EditForm>,
 }
 
 impl MyApp {
@@ -39,13 +156,19 @@ impl MyApp {
             audio_format: String::new(),
             file_extension: "mp4".to_string(),
             encoding: "libx264".to_string(), // Default encoding
+            preset: String::new(),
+            presets: all_presets(),
+            target_size: String::new(),
             delete_original: false,
             skip_if_exists: false,
-            job_queue: Arc::new(Mutex::new(VecDeque::new())),
-            total_jobs: 0,
-            is_encoding: Arc::new(Mutex::new(false)),
+            workers: 1,
+            quarantine_folder: String::new(),
+            profiles: BTreeMap::new(),
+            engine: None,
+            queue_note: None,
+            reopen_at: Instant::now(),
             progress: 0.0,
-            file_stats: Arc::new(Mutex::new(Vec::new())),
+            editing: None,
         };
 
         // Load config if it exists
@@ -56,13 +179,17 @@ impl MyApp {
             app.audio_format = config.audio_format;
             app.file_extension = config.file_extension;
             app.encoding = config.encoding;
+            app.preset = config.preset;
+            app.target_size = config.target_size;
             app.delete_original = config.delete_original;
             app.skip_if_exists = config.skip_if_exists;
-
-            // Update the job queue with files from the loaded folder
-            app.enqueue_jobs();
+            app.workers = config.workers;
+            app.quarantine_folder = config.quarantine_folder;
+            app.profiles = config.profiles;
         }
 
+        app.open_engine();
+
         // add remixicon
         let mut fonts = egui::FontDefinitions::default();
         add_to_fonts(&mut fonts);
@@ -71,31 +198,8 @@ impl MyApp {
         app
     }
 
-    fn add_debug_stats(&self) {
-        let mut stats = self.file_stats.lock().unwrap();
-        let mut rng = rand::thread_rng();
-
-        // rng.gen_range()
-        for _ in 0..50 {
-            let input_size: f64 = rng.gen_range(100.0..1000000.0);
-            let output_size: Option<f64> = Some(rng.gen_range(1.0..1000.0));
-            let reduction: Option<f64> = Some(rng.gen_range(0.0..10.0));
-            let elapsed_time: Option<f64> = Some(rng.gen_range(0.01..3600.0)); // seconds
-            let output_file: Option<String> = None; // Always None for now
-
-            stats.push(FileStat {
-                input_file: "hello.mp4".to_string(),
-                input_size,
-                output_size,
-                reduction,
-                elapsed_time,
-                output_file,
-            });
-        }
-    }
-
-    fn save_config(&self) {
-        let config = AppConfig {
+    fn config(&self) -> AppConfig {
+        AppConfig {
             input_folder: self.input_folder.clone(),
             output_folder: self.output_folder.clone(),
             video_format: self.video_format.clone(),
@@ -104,106 +208,191 @@ impl MyApp {
             delete_original: self.delete_original,
             skip_if_exists: self.skip_if_exists,
             encoding: self.encoding.clone(),
-        };
+            preset: self.preset.clone(),
+            target_size: self.target_size.clone(),
+            workers: self.workers,
+            quarantine_folder: self.quarantine_folder.clone(),
+            // the UI does not edit the profiles, they are kept as they are
+            profiles: self.profiles.clone(),
+        }
+    }
 
-        if let Err(e) = config.save("app_config.yaml") {
+    fn save_config(&self) {
+        if let Err(e) = self.config().save("app_config.yaml") {
             eprintln!("Failed to save configuration: {}", e);
         }
     }
 
+    /// Opens the job queue and resumes the jobs left over by the last run, unless another app has it open
+    fn open_engine(&mut self) {
+        self.reopen_at = Instant::now() + Duration::from_secs(1);
+        let state_file = default_state_file();
+        if JobEngine::in_use(&state_file) {
+            if self.queue_note.is_none() {
+                self.queue_note = Some("The job queue is open in another app, new jobs are handed to it".to_string());
+            }
+            return;
+        }
+        match JobEngine::open(&state_file, EngineOptions::default()) {
+            Ok(engine) => {
+                self.engine = Some(engine);
+                self.queue_note = None;
+                if self.engine.as_ref().is_some_and(JobEngine::has_queued) {
+                    self.start_encoding();
+                }
+            }
+            Err(e) => self.queue_note = Some(format!("Cannot open the job queue: {}", e)),
+        }
+    }
+
     fn enqueue_jobs(&mut self) {
         //let files = list_files_from_dir(&self.input_folder, &self.file_extension);
         let files = list_files_from_dir2(&self.input_folder, &self.file_extension, SortBy::LastUpdated, true);
-
-        let mut job_queue = self.job_queue.lock().unwrap();
-        job_queue.clear();
-        job_queue.extend(files.clone());
-        self.total_jobs = files.len();
-        self.progress = 0.0;
-
-        // Reset file stats
-        self.file_stats.lock().unwrap().clear();
+        let preset = self.config().encode_preset();
+        let target_size = parse_size(&self.target_size);
+
+        let Some(engine) = &self.engine else {
+            let requests: Vec<JobRequest> = files
+                .iter()
+                .map(|file| JobRequest {
+                    input: file.clone(),
+                    output: generate_output_path(file, self.output_folder.clone(), preset.container.clone()),
+                    preset: preset.clone(),
+                    target_size,
+                    edits: Default::default(),
+                })
+                .collect();
+            self.queue_note = Some(match submit_jobs(&default_state_file(), &requests) {
+                Ok(()) => format!("Handed {} files to the app running the job queue", requests.len()),
+                Err(e) => format!("Cannot hand the files to the app running the job queue: {}", e),
+            });
+            return;
+        };
+        for file in files {
+            let output_file = generate_output_path(&file, self.output_folder.clone(), preset.container.clone());
+            engine.enqueue(&file, &output_file, &preset, target_size);
+        }
     }
 
     fn start_encoding(&mut self) {
-        let is_encoding = Arc::clone(&self.is_encoding);
-        let job_queue = Arc::clone(&self.job_queue);
-        let file_stats = Arc::clone(&self.file_stats);
-        let output_folder = self.output_folder.clone();
-        let video_format = self.video_format.clone();
-        let encoding = self.encoding.clone();
-        let delete_original = self.delete_original;
-        let skip_if_exists = self.skip_if_exists;
-
-        *is_encoding.lock().unwrap() = true;
-
-        thread::spawn(move || {
-            while let Some(file) = {
-                let mut queue = job_queue.lock().unwrap();
-                queue.pop_front()
-            } {
-                let output_file = generate_output_path(&file, output_folder.clone(), video_format.clone());
-
-                // Skip if output file already exists
-                if skip_if_exists && Path::new(&output_file).exists() {
-                    println!("Skipping {} as output file already exists", file);
-                    continue;
-                }
+        if let Some(engine) = &self.engine {
+            engine.set_options(self.config().engine_options());
+            engine.start();
+        }
+    }
 
-                let input_size = get_file_size_in_gb(&file).unwrap();
-                let start_time = std::time::Instant::now();
-
-                {
-                    // Add initial entry to file_stats
-                    let mut stats = file_stats.lock().unwrap();
-                    stats.push(FileStat {
-                        input_file: file.clone(),
-                        input_size,
-                        output_size: None,
-                        reduction: None,
-                        elapsed_time: None,
-                        output_file: None,
+    fn show_edit_window(&mut self, ctx: &egui::Context) {
+        let Some(form) = &mut self.editing else {
+            return;
+        };
+        form.poll();
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new(format!("{} Edit {}", icons::SCISSORS_CUT_LINE, get_file_name(&form.input).unwrap_or_default()))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("edit_form").num_columns(2).show(ui, |ui| {
+                    ui.label("Start:");
+                    ui.add(egui::TextEdit::singleline(&mut form.start).hint_text("0:00"));
+                    ui.end_row();
+                    ui.label("End:");
+                    ui.add(egui::TextEdit::singleline(&mut form.end).hint_text("end of the video"));
+                    ui.end_row();
+                    ui.label("Rotate:");
+                    ComboBox::from_id_salt("rotate").selected_text(form.rotate.label()).show_ui(ui, |ui| {
+                        for rotation in Rotation::ALL {
+                            ui.selectable_value(&mut form.rotate, rotation, rotation.label());
+                        }
                     });
-                }
-
-                let status =
-                    encode_video(PathBuf::from(&file), &encoding, &output_file, &String::from("aac"));
+                    ui.end_row();
+                    ui.label("Crop:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut form.crop).hint_text("width:height:x:y"));
+                        if form.detecting.is_some() {
+                            ui.spinner();
+                        } else if ui.button("Detect").on_hover_text("Find the black bars with cropdetect").clicked() {
+                            let input = form.input.clone();
+                            form.detecting = Some(in_background(move || detect_crop(Path::new(&input)).map_err(|e| e.to_string())));
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Append:");
+                    ui.vertical(|ui| {
+                        let mut removed = None;
+                        for (index, clip) in form.append.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(get_file_name(clip).unwrap_or_default());
+                                if ui.small_button(icons::CLOSE_LINE).on_hover_text("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = removed {
+                            form.append.remove(index);
+                        }
+                        if ui.button("Add clips").on_hover_text("Clips need the same resolution and codecs").clicked() {
+                            if let Some(files) = rfd::FileDialog::new().pick_files() {
+                                form.append.extend(files.iter().map(|file| file.display().to_string()));
+                            }
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Subtitles:");
+                    ui.vertical(|ui| {
+                        if form.probing.is_some() {
+                            ui.spinner();
+                        }
+                        ui.add_enabled(
+                            form.sidecar_count > 0 || form.sidecar_subtitles,
+                            egui::Checkbox::new(&mut form.sidecar_subtitles, format!("Add the {} .srt files named after the video", form.sidecar_count)),
+                        );
+                        let burn_label = |track: Option<usize>| match track {
+                            Some(track) => format!("Burn track #{}", track),
+                            None => "Do not burn".to_string(),
+                        };
+                        ComboBox::from_id_salt("burn_subtitles").selected_text(burn_label(form.burn_subtitles)).show_ui(ui, |ui| {
+                            ui.selectable_value(&mut form.burn_subtitles, None, burn_label(None));
+                            for track in &form.subtitle_tracks {
+                                ui.selectable_value(&mut form.burn_subtitles, Some(track.track), format!("Burn {}", track.label()));
+                            }
+                        });
+                    });
+                    ui.end_row();
+                });
 
-                if let Err(e) = status {
-                    eprintln!("Failed to run FFmpeg for {}: {}", file, e);
-                    continue;
+                if let Some(error) = &form.error {
+                    ui.colored_label(egui::Color32::RED, error);
                 }
-                let elapsed_time = start_time.elapsed().as_secs_f64();
-
-                // sleep(Duration::from_secs(3));
-                let output_size = get_file_size_in_gb(&output_file).unwrap();
-                let reduction = (input_size - output_size).max(0.0);
-
-                {
-                    // Update file_stats entry
-                    let mut stats = file_stats.lock().unwrap();
-                    if let Some(stat) = stats.iter_mut().find(|stat| stat.input_file == file) {
-                        stat.output_size = Some(output_size);
-                        stat.reduction = Some(reduction);
-                        stat.elapsed_time = Some(elapsed_time);
-                        stat.output_file = Some(output_file);
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        match form.edits().map(|edits| self.engine.as_ref().is_some_and(|engine| engine.set_edits(form.job_id, edits))) {
+                            Ok(true) => close = true,
+                            Ok(false) => form.error = Some("The job already started".to_string()),
+                            Err(e) => form.error = Some(e),
+                        }
                     }
-                }
-
-                if delete_original {
-                    fs::remove_file(&file).unwrap_or_else(|e| eprintln!("Failed to delete file: {}", e));
-                }
-            }
-
-            *is_encoding.lock().unwrap() = false;
-        });
+                    if ui.button("Clear").clicked() {
+                        form.start.clear();
+                        form.end.clear();
+                        form.rotate = Rotation::None;
+                        form.crop.clear();
+                        form.append.clear();
+                        form.sidecar_subtitles = false;
+                        form.burn_subtitles = None;
+                        form.error = None;
+                    }
+                });
+            });
+        if !open || close {
+            self.editing = None;
+        }
     }
 
-
     fn update_progress(&mut self) {
-        let queue_size = self.job_queue.lock().unwrap().len();
-        self.progress = if self.total_jobs > 0 {
-            1.0 - queue_size as f32 / self.total_jobs as f32
+        let (done, total) = self.engine.as_ref().map(JobEngine::progress).unwrap_or_default();
+        self.progress = if total > 0 {
+            done as f32 / total as f32
         } else {
             0.0
         };
@@ -213,17 +402,28 @@ impl MyApp {
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         install_image_loaders(ctx);
+        // jobs handed over by the other apps, like the video browser
+        if self.engine.is_none() && Instant::now() >= self.reopen_at {
+            self.open_engine();
+        }
+        if self.engine.as_ref().is_some_and(|engine| engine.take_submitted() > 0) {
+            self.start_encoding();
+        }
         self.update_progress();
+        self.show_edit_window(ctx);
 
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("Menu", |ui| {
-                    // Add the toggleable "Show Command" button
-                    if ui.button("Add debug items").clicked() {
-                        self.add_debug_stats();
-                    }
+                    let Some(engine) = &self.engine else {
+                        ui.label("The job queue is open in another app");
+                        return;
+                    };
                     if ui.button("Clean items").clicked() {
-                        self.file_stats.lock().unwrap().clear();
+                        engine.clear_finished();
+                    }
+                    if ui.button("Open reports").clicked() {
+                        open::that(engine.report_dir()).unwrap_or(());
                     }
                 })
             })
@@ -259,24 +459,48 @@ impl eframe::App for MyApp {
                     });
 
                     ui.horizontal(|ui| {
-                        ui.label("Output Video Container:");
-                        ComboBox::from_id_salt("video_format")
-                            .selected_text(&self.video_format)
+                        ui.label("Preset:");
+                        ComboBox::from_id_salt("preset")
+                            .selected_text(if self.preset.is_empty() { "Custom" } else { &self.preset })
                             .show_ui(ui, |ui| {
-                                ui.selectable_value(&mut self.video_format, "mp4".to_string(), "MP4");
-                                ui.selectable_value(&mut self.video_format, "mpeg".to_string(), "MPEG");
+                                ui.selectable_value(&mut self.preset, String::new(), "Custom");
+                                for preset in &self.presets {
+                                    if ui.selectable_value(&mut self.preset, preset.name.clone(), &preset.name).clicked() {
+                                        self.video_format = preset.container.clone();
+                                    }
+                                }
                             });
                     });
 
+                    if self.preset.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label("Output Video Container:");
+                            ComboBox::from_id_salt("video_format")
+                                .selected_text(&self.video_format)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.video_format, "mp4".to_string(), "MP4");
+                                    ui.selectable_value(&mut self.video_format, "mpeg".to_string(), "MPEG");
+                                });
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Encoding:");
+                            ComboBox::from_id_salt("encoding")
+                                .selected_text(&self.encoding)
+                                .show_ui(ui, |ui| {
+                                    for option in ["libx264", "libx265", "vp9", "av1"] {
+                                        ui.selectable_value(&mut self.encoding, option.to_string(), option);
+                                    }
+                                });
+                        });
+                    }
+
                     ui.horizontal(|ui| {
-                        ui.label("Encoding:");
-                        ComboBox::from_id_salt("encoding")
-                            .selected_text(&self.encoding)
-                            .show_ui(ui, |ui| {
-                                for option in ["libx264", "libx265", "vp9", "av1"] {
-                                    ui.selectable_value(&mut self.encoding, option.to_string(), option);
-                                }
-                            });
+                        ui.label("Target Size:");
+                        ui.add(egui::TextEdit::singleline(&mut self.target_size).hint_text("25MB, empty for no limit"));
+                        if !self.target_size.trim().is_empty() && parse_size(&self.target_size).is_none() {
+                            ui.colored_label(egui::Color32::RED, "Invalid size");
+                        }
                     });
 
                     ui.horizontal(|ui| {
@@ -285,12 +509,19 @@ impl eframe::App for MyApp {
                     });
 
                     ui.horizontal(|ui| {
-                        ui.checkbox(&mut self.delete_original, "Delete Original Files");
-                        ui.checkbox(&mut self.skip_if_exists, "Skip if Output File Exists");
+                        ui.checkbox(&mut self.delete_original, "Delete Original Files")
+                            .on_hover_text("Originals are moved to the quarantine folder once the encode is verified");
+                        ui.checkbox(&mut self.skip_if_exists, "Skip if Output File Exists")
+                            .on_hover_text("Also skips videos already encoded with the same settings under another name");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Parallel Encodes:");
+                        ui.add(egui::DragValue::new(&mut self.workers).range(1..=8));
                     });
 
                     // Lock the mutex to safely access the value
-                    let is_encoding_value = *self.is_encoding.lock().unwrap();
+                    let is_encoding_value = self.engine.as_ref().is_some_and(JobEngine::is_running);
                     // need horizontal
                     ui.horizontal(|ui| {
                         if is_encoding_value {
@@ -307,15 +538,15 @@ impl eframe::App for MyApp {
                     });
 
                     ui.heading(RichText::new(format!("{} Progress", icons::PROGRESS_1_FILL)).size(20.0));
-                    let remaining_jobs = self.job_queue.lock().unwrap().len();
-                    ui.label(format!(
-                        "Progress: {}/{} files processed",
-                        self.total_jobs - remaining_jobs,
-                        self.total_jobs
-                    ));
+                    if let Some(note) = &self.queue_note {
+                        ui.label(note);
+                    }
+                    let (done, total) = self.engine.as_ref().map(JobEngine::progress).unwrap_or_default();
+                    ui.label(format!("Progress: {}/{} files processed", done, total));
                     ui.add(ProgressBar::new(self.progress).text(format!("{:.0}%", self.progress * 100.0)));
 
-                    ui.heading(RichText::new(format!("{} File Stats ({})", icons::COMPUTER_LINE, self.file_stats.lock().unwrap().len())).size(20.0));
+                    let jobs = self.engine.as_ref().map(JobEngine::jobs).unwrap_or_default();
+                    ui.heading(RichText::new(format!("{} File Stats ({})", icons::COMPUTER_LINE, jobs.len())).size(20.0));
                     egui::ScrollArea::vertical() // or `horizontal()` or `both()` depending on your needs )
                         // .min_scrolled_height(300.0)
                         // .max_height()
@@ -337,11 +568,13 @@ impl eframe::App for MyApp {
                                     ui.label("");
                                     ui.label("Reduction (GB)");
                                     ui.label("Elapsed Time (s)");
+                                    ui.label("State");
+                                    ui.label("");
                                     ui.end_row();
 
                                     // Populate rows
-                                    let file_stats = self.file_stats.lock().unwrap();
-                                    for stat in file_stats.iter() {
+                                    for job in jobs.iter() {
+                                        let stat = job.file_stat();
                                         ui.label(get_file_name(&stat.input_file).unwrap());
                                         ui.label(format!("{:.2}", stat.input_size));
                                         ui.add(
@@ -369,13 +602,59 @@ impl eframe::App for MyApp {
                                                 .then(|| {
                                                     open::that(output_file).expect("Cannot open output file");
                                                 });
-                                        } else {
+                                        } else if job.state == JobState::Running {
                                             ui.image(include_image!("../../icons8-loading.gif"));
                                             ui.label("");
+                                        } else {
+                                            ui.label("-");
+                                            ui.label("");
                                         }
 
                                         ui.label(format_f64_or_dash(stat.reduction));
                                         ui.label(format_elapsed_time(stat.elapsed_time));
+                                        ui.label(format!("{:?}", job.state))
+                                            .on_hover_text(job.error.clone().or(job.note.clone()).unwrap_or_default());
+                                        ui.horizontal(|ui| {
+                                            let Some(engine) = &self.engine else {
+                                                return;
+                                            };
+                                            if matches!(job.state, JobState::Queued | JobState::Paused) {
+                                                let hint = if job.edits.is_empty() { "Trim, crop, rotate or append clips".to_string() } else { job.edits.summary() };
+                                                if ui.small_button(icons::SCISSORS_CUT_LINE).on_hover_text(hint).clicked() {
+                                                    self.editing = Some(EditForm::new(job));
+                                                }
+                                            }
+                                            match job.state {
+                                                JobState::Queued | JobState::Running => {
+                                                    if ui.small_button(icons::PAUSE_FILL).on_hover_text("Pause").clicked() {
+                                                        engine.pause(job.id);
+                                                    }
+                                                }
+                                                JobState::Paused => {
+                                                    if ui.small_button(icons::PLAY_FILL).on_hover_text("Resume").clicked() {
+                                                        engine.resume(job.id);
+                                                    }
+                                                }
+                                                JobState::Failed | JobState::Cancelled => {
+                                                    if ui.small_button(icons::REFRESH_LINE).on_hover_text("Retry").clicked() {
+                                                        engine.retry(job.id);
+                                                    }
+                                                }
+                                                JobState::Done if job.quarantined.is_some() => {
+                                                    if ui.small_button(icons::ARROW_GO_BACK_LINE).on_hover_text("Restore original").clicked() {
+                                                        if let Err(e) = engine.restore_original(job.id) {
+                                                            eprintln!("Cannot restore the original of {}: {}", job.input, e);
+                                                        }
+                                                    }
+                                                }
+                                                JobState::Done | JobState::Skipped => {}
+                                            }
+                                            if !job.state.is_finished()
+                                                && ui.small_button(icons::CLOSE_LINE).on_hover_text("Cancel").clicked()
+                                            {
+                                                engine.cancel(job.id);
+                                            }
+                                        });
                                         ui.end_row();
                                     }
                                 });
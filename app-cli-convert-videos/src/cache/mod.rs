@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use lib_ffmpeg_utils::edit::EditInstructions;
+use lib_ffmpeg_utils::presets::EncodePreset;
+
+/// An encode that was done, keyed by what went in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CacheEntry {
+    pub source_hash: String,
+    pub preset_hash: String,
+    /// Where the source was when it was encoded
+    pub source: String,
+    pub output: String,
+    /// Output size in bytes, a different size means the output was replaced
+    pub output_size: u64,
+    pub encoded_at: String,
+}
+
+impl CacheEntry {
+    /// True while the output is still the file this encode produced
+    pub fn output_is_intact(&self) -> bool {
+        fs::metadata(&self.output).is_ok_and(|metadata| metadata.len() == self.output_size)
+    }
+}
+
+/// Hash of a source, valid as long as its size and modification time do not change
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct HashedSource {
+    size: u64,
+    modified: u64,
+    hash: String,
+}
+
+/// Remembers which sources were encoded with which settings, saved next to the job queue
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct EncodeCache {
+    entries: Vec<CacheEntry>,
+    #[serde(default)]
+    sources: BTreeMap<String, HashedSource>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl EncodeCache {
+    /// An empty cache that will be saved to `path`
+    pub fn new(path: &Path) -> Self {
+        EncodeCache { path: path.to_path_buf(), ..Default::default() }
+    }
+
+    /// Loads the cache from `path`, a missing file gives an empty cache
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::new(path));
+        }
+        let mut cache: EncodeCache = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        cache.path = path.to_path_buf();
+        Ok(cache)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let yaml = serde_yaml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, yaml)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    pub fn entries(&self) -> &[CacheEntry] {
+        &self.entries
+    }
+
+    /// Hash already computed for `source`, unless the file changed since
+    pub fn known_hash(&self, source: &Path) -> Option<String> {
+        let (size, modified) = file_stamp(source).ok()?;
+        let known = self.sources.get(&source.to_string_lossy().to_string())?;
+        (known.size == size && known.modified == modified).then(|| known.hash.clone())
+    }
+
+    pub fn remember_hash(&mut self, source: &Path, hash: &str) {
+        if let Ok((size, modified)) = file_stamp(source) {
+            let hashed = HashedSource { size, modified, hash: hash.to_string() };
+            self.sources.insert(source.to_string_lossy().to_string(), hashed);
+        }
+    }
+
+    /// A previous encode of the same content with the same settings whose output is still there
+    pub fn lookup(&self, source_hash: &str, preset_hash: &str) -> Option<&CacheEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.source_hash == source_hash && entry.preset_hash == preset_hash && entry.output_is_intact())
+    }
+
+    /// The encode that produced `output`, if any
+    pub fn entry_for_output(&self, output: &Path) -> Option<&CacheEntry> {
+        let output = output.to_string_lossy();
+        self.entries.iter().rev().find(|entry| entry.output == output)
+    }
+
+    /// Records an encode, replacing what was known about the same output
+    pub fn record(&mut self, entry: CacheEntry) {
+        self.entries.retain(|existing| existing.output != entry.output);
+        self.entries.push(entry);
+    }
+
+    /// Drops the entries whose output is gone or was replaced
+    pub fn prune(&mut self) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.output_is_intact());
+        self.sources.retain(|source, _| Path::new(source).exists());
+        before - self.entries.len()
+    }
+}
+
+fn file_stamp(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    Ok((metadata.len(), modified))
+}
+
+/// SHA-256 of the content of a file, read in chunks so big videos are fine
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Hash of everything that changes the output of an encode, the preset name does not count
+pub fn preset_hash(preset: &EncodePreset, target_size: Option<u64>, edits: &EditInstructions) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(preset.container.as_bytes());
+    for arg in preset.output_args() {
+        hasher.update([0]);
+        hasher.update(arg.as_bytes());
+    }
+    if let Some(target_size) = target_size {
+        hasher.update(format!("\0target={}", target_size).as_bytes());
+    }
+    if !edits.is_empty() {
+        hasher.update(format!("\0edits={}", serde_json::to_string(edits).unwrap_or_default()).as_bytes());
+    }
+    to_hex(&hasher.finalize()[..8])
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,754 @@
+use std::cell::Cell;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use lib_ffmpeg_utils::edit::{edit_args, EditInstructions};
+use lib_ffmpeg_utils::encode::encode_to_target_size_with;
+use lib_ffmpeg_utils::presets::EncodePreset;
+use lib_ffmpeg_utils::utils::path_for;
+use lib_ffmpeg_utils::verify::verify_output;
+use lib_egui_utils::get_file_size_in_gb;
+use crate::cache::{hash_file, preset_hash, CacheEntry, EncodeCache};
+use crate::quarantine::{default_quarantine_dir, Quarantine};
+use crate::report::{append_history, JobRecord, RunReport, TIME_FORMAT};
+use crate::FileStat;
+
+/// How often a worker checks if its job was paused or cancelled
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+    Done,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// Finished jobs are never picked up again without a retry
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Skipped | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Job {
+    pub id: u64,
+    pub input: String,
+    pub output: String,
+    pub preset: EncodePreset,
+    #[serde(default)]
+    pub target_size: Option<u64>,
+    pub state: JobState,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub input_size: f64,
+    #[serde(default)]
+    pub output_size: Option<f64>,
+    #[serde(default)]
+    pub elapsed_time: Option<f64>,
+    /// Undo log id of the original, once moved to quarantine
+    #[serde(default)]
+    pub quarantined: Option<u64>,
+    /// Last ffmpeg command line run for the job
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Seconds of media in the verified output
+    #[serde(default)]
+    pub media_duration: Option<f64>,
+    /// Why the job was skipped
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Trim, crop, rotate and clips appended while encoding
+    #[serde(default, skip_serializing_if = "EditInstructions::is_empty")]
+    pub edits: EditInstructions,
+}
+
+impl Job {
+    pub fn file_stat(&self) -> FileStat {
+        FileStat {
+            input_file: self.input.clone(),
+            input_size: self.input_size,
+            output_size: self.output_size,
+            reduction: self.output_size.map(|size| (self.input_size - size).max(0.0)),
+            elapsed_time: self.elapsed_time,
+            output_file: if self.state == JobState::Done { Some(self.output.clone()) } else { None },
+        }
+    }
+}
+
+/// The list of jobs, saved to disk after every change so a restart picks up where it stopped
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct JobQueue {
+    next_id: u64,
+    jobs: Vec<Job>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl JobQueue {
+    /// Loads the queue from `path`, a missing file gives an empty queue
+    ///
+    /// Jobs that were running when the app stopped are queued again.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut queue: JobQueue = if path.exists() {
+            serde_yaml::from_str(&fs::read_to_string(path)?)?
+        } else {
+            JobQueue::default()
+        };
+        queue.path = path.to_path_buf();
+        for job in queue.jobs.iter_mut().filter(|job| job.state == JobState::Running) {
+            job.state = JobState::Queued;
+        }
+        Ok(queue)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let yaml = serde_yaml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // write aside and rename, a crash must not leave half a queue behind
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, yaml)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Adds a job, unless the same input and output is already waiting or running
+    pub fn push(&mut self, input: &str, output: &str, preset: &EncodePreset, target_size: Option<u64>) -> Option<u64> {
+        self.push_edited(input, output, preset, target_size, EditInstructions::default())
+    }
+
+    /// Same as [`Self::push`], with `edits` applied while encoding
+    pub fn push_edited(
+        &mut self,
+        input: &str,
+        output: &str,
+        preset: &EncodePreset,
+        target_size: Option<u64>,
+        edits: EditInstructions,
+    ) -> Option<u64> {
+        let pending = self.jobs.iter().any(|job| {
+            job.input == input && job.output == output && !job.state.is_finished()
+        });
+        if pending {
+            return None;
+        }
+
+        self.next_id += 1;
+        self.jobs.push(Job {
+            id: self.next_id,
+            input: input.to_string(),
+            output: output.to_string(),
+            preset: preset.clone(),
+            target_size,
+            state: JobState::Queued,
+            attempts: 0,
+            error: None,
+            input_size: get_file_size_in_gb(input).unwrap_or(0.0),
+            output_size: None,
+            elapsed_time: None,
+            quarantined: None,
+            command: None,
+            exit_code: None,
+            media_duration: None,
+            note: None,
+            edits,
+        });
+        Some(self.next_id)
+    }
+
+    /// Marks the next queued job as running and returns a copy of it
+    pub fn claim_next(&mut self) -> Option<Job> {
+        let job = self.jobs.iter_mut().find(|job| job.state == JobState::Queued)?;
+        job.state = JobState::Running;
+        job.attempts += 1;
+        job.error = None;
+        job.command = None;
+        job.exit_code = None;
+        job.note = None;
+        Some(job.clone())
+    }
+
+    pub fn is_running(&self, id: u64) -> bool {
+        self.get(id).is_some_and(|job| job.state == JobState::Running)
+    }
+
+    fn set_state(&mut self, id: u64, from: &[JobState], to: JobState) -> bool {
+        match self.get_mut(id) {
+            Some(job) if from.contains(&job.state) => {
+                job.state = to;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// A running job is stopped and will start over when resumed
+    pub fn pause(&mut self, id: u64) -> bool {
+        self.set_state(id, &[JobState::Queued, JobState::Running], JobState::Paused)
+    }
+
+    pub fn resume(&mut self, id: u64) -> bool {
+        self.set_state(id, &[JobState::Paused], JobState::Queued)
+    }
+
+    pub fn cancel(&mut self, id: u64) -> bool {
+        self.set_state(id, &[JobState::Queued, JobState::Running, JobState::Paused], JobState::Cancelled)
+    }
+
+    pub fn retry(&mut self, id: u64) -> bool {
+        self.set_state(id, &[JobState::Failed, JobState::Cancelled], JobState::Queued)
+    }
+
+    /// Changes the edits of a job that has not started yet
+    pub fn set_edits(&mut self, id: u64, edits: EditInstructions) -> bool {
+        match self.get_mut(id) {
+            Some(job) if matches!(job.state, JobState::Queued | JobState::Paused) => {
+                job.edits = edits;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drops done, skipped and cancelled jobs, failed ones are kept so they can be retried
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|job| !matches!(job.state, JobState::Done | JobState::Skipped | JobState::Cancelled));
+    }
+
+    /// Number of jobs that are finished, and of jobs that will run or have run
+    pub fn progress(&self) -> (usize, usize) {
+        let total = self.jobs.iter().filter(|job| job.state != JobState::Cancelled).count();
+        let finished = self.jobs.iter().filter(|job| job.state.is_finished() && job.state != JobState::Cancelled).count();
+        (finished, total)
+    }
+}
+
+/// A job handed over to the engine that has the state file open
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobRequest {
+    pub input: String,
+    pub output: String,
+    pub preset: EncodePreset,
+    #[serde(default)]
+    pub target_size: Option<u64>,
+    #[serde(default)]
+    pub edits: EditInstructions,
+}
+
+#[derive(Clone, Debug)]
+pub struct EngineOptions {
+    pub workers: usize,
+    pub skip_if_exists: bool,
+    /// Move the original to quarantine once the output is verified
+    pub delete_original: bool,
+    pub quarantine_dir: PathBuf,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self { workers: 1, skip_if_exists: false, delete_original: false, quarantine_dir: default_quarantine_dir() }
+    }
+}
+
+/// Runs the queued jobs on a pool of worker threads
+#[derive(Clone)]
+pub struct JobEngine {
+    queue: Arc<Mutex<JobQueue>>,
+    options: Arc<Mutex<EngineOptions>>,
+    active_workers: Arc<Mutex<usize>>,
+    /// Report of the jobs finished since the workers started
+    run: Arc<Mutex<Option<RunReport>>>,
+    cache: Arc<Mutex<EncodeCache>>,
+    log_dir: PathBuf,
+    report_dir: PathBuf,
+    /// Jobs handed over by other apps, see [`submit_jobs`]
+    inbox: PathBuf,
+    /// Held while the engine lives, so no other engine saves over the queue
+    _state_lock: Arc<File>,
+}
+
+/// Default location of the job queue
+pub fn default_state_file() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rusty-toolbox")
+        .join("convert-jobs.yaml")
+}
+
+/// Locks the state file, none when another engine already has it
+fn lock_state(state_file: &Path) -> Result<Option<File>, Box<dyn Error>> {
+    if let Some(parent) = state_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(state_file.with_extension("lock"))?;
+    match lock.try_lock() {
+        Ok(()) => Ok(Some(lock)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Opens an inbox and waits for the other apps to be done with it
+fn lock_inbox(path: &Path) -> Result<File, Box<dyn Error>> {
+    let inbox = OpenOptions::new().create(true).truncate(false).read(true).write(true).open(path)?;
+    inbox.lock()?;
+    Ok(inbox)
+}
+
+/// Empties the locked inbox, returning what was in it
+fn drain_inbox(inbox: &mut File) -> Result<Vec<JobRequest>, Box<dyn Error>> {
+    let mut content = String::new();
+    inbox.read_to_string(&mut content)?;
+    inbox.set_len(0)?;
+    inbox.seek(SeekFrom::Start(0))?;
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_yaml::from_str(&content)?)
+}
+
+/// Hands jobs to the engine that has `state_file` open, it queues them while it runs or when next opened
+pub fn submit_jobs(state_file: &Path, requests: &[JobRequest]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = state_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut inbox = lock_inbox(&state_file.with_extension("inbox.yaml"))?;
+    let mut pending = drain_inbox(&mut inbox)?;
+    pending.extend_from_slice(requests);
+    inbox.write_all(serde_yaml::to_string(&pending)?.as_bytes())?;
+    Ok(())
+}
+
+impl JobEngine {
+    /// Fails when another engine has `state_file` open, hand the jobs over with [`submit_jobs`] instead
+    pub fn open(state_file: &Path, options: EngineOptions) -> Result<Self, Box<dyn Error>> {
+        let state_lock = lock_state(state_file)?
+            .ok_or_else(|| format!("{} is already open in another app", state_file.display()))?;
+        let queue = JobQueue::load(state_file)?;
+        let cache_file = state_file.with_extension("cache.yaml");
+        let cache = EncodeCache::load(&cache_file).unwrap_or_else(|e| {
+            // losing the cache only costs encodes that could have been skipped
+            eprintln!("Cannot load the encode cache {}, starting over: {}", cache_file.display(), e);
+            EncodeCache::new(&cache_file)
+        });
+        let engine = Self {
+            queue: Arc::new(Mutex::new(queue)),
+            options: Arc::new(Mutex::new(options)),
+            active_workers: Arc::new(Mutex::new(0)),
+            run: Arc::new(Mutex::new(None)),
+            cache: Arc::new(Mutex::new(cache)),
+            log_dir: state_file.with_extension("logs"),
+            report_dir: state_file.with_extension("reports"),
+            inbox: state_file.with_extension("inbox.yaml"),
+            _state_lock: Arc::new(state_lock),
+        };
+        engine.take_submitted();
+        Ok(engine)
+    }
+
+    /// True when an engine has `state_file` open, in this app or another one
+    pub fn in_use(state_file: &Path) -> bool {
+        matches!(lock_state(state_file), Ok(None))
+    }
+
+    /// Queues the jobs handed over with [`submit_jobs`], returns how many
+    pub fn take_submitted(&self) -> usize {
+        // a cheap check first, the UI calls this every frame
+        if fs::metadata(&self.inbox).map(|m| m.len()).unwrap_or_default() == 0 {
+            return 0;
+        }
+        let requests = lock_inbox(&self.inbox).and_then(|mut inbox| drain_inbox(&mut inbox));
+        match requests {
+            Ok(requests) => self.update(|queue| {
+                requests
+                    .into_iter()
+                    .filter_map(|r| queue.push_edited(&r.input, &r.output, &r.preset, r.target_size, r.edits))
+                    .count()
+            }),
+            Err(e) => {
+                eprintln!("Failed to read the submitted jobs: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Folder with the run reports and the history of all the jobs
+    pub fn report_dir(&self) -> &Path {
+        &self.report_dir
+    }
+
+    pub fn set_options(&self, options: EngineOptions) {
+        *self.options.lock().unwrap() = options;
+    }
+
+    pub fn enqueue(&self, input: &str, output: &str, preset: &EncodePreset, target_size: Option<u64>) -> Option<u64> {
+        self.update(|queue| queue.push(input, output, preset, target_size))
+    }
+
+    pub fn enqueue_edited(
+        &self,
+        input: &str,
+        output: &str,
+        preset: &EncodePreset,
+        target_size: Option<u64>,
+        edits: EditInstructions,
+    ) -> Option<u64> {
+        self.update(|queue| queue.push_edited(input, output, preset, target_size, edits))
+    }
+
+    pub fn set_edits(&self, id: u64, edits: EditInstructions) -> bool {
+        self.update(|queue| queue.set_edits(id, edits))
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.queue.lock().unwrap().jobs().to_vec()
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        self.queue.lock().unwrap().progress()
+    }
+
+    pub fn has_queued(&self) -> bool {
+        self.queue.lock().unwrap().jobs().iter().any(|job| job.state == JobState::Queued)
+    }
+
+    pub fn pause(&self, id: u64) -> bool {
+        self.update(|queue| queue.pause(id))
+    }
+
+    pub fn resume(&self, id: u64) -> bool {
+        let resumed = self.update(|queue| queue.resume(id));
+        if resumed {
+            self.start();
+        }
+        resumed
+    }
+
+    pub fn cancel(&self, id: u64) -> bool {
+        self.update(|queue| queue.cancel(id))
+    }
+
+    pub fn retry(&self, id: u64) -> bool {
+        let retried = self.update(|queue| queue.retry(id));
+        if retried {
+            self.start();
+        }
+        retried
+    }
+
+    /// Moves the original of a done job back out of quarantine
+    pub fn restore_original(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let quarantined = self.queue.lock().unwrap().get(id).and_then(|job| job.quarantined);
+        let quarantined = quarantined.ok_or_else(|| format!("The original of job {} is not in quarantine", id))?;
+        let quarantine_dir = self.options.lock().unwrap().quarantine_dir.clone();
+        Quarantine::new(&quarantine_dir).undo(quarantined)?;
+        self.update(|queue| {
+            if let Some(job) = queue.get_mut(id) {
+                job.quarantined = None;
+            }
+        });
+        Ok(())
+    }
+
+    pub fn cache_entries(&self) -> Vec<CacheEntry> {
+        self.cache.lock().unwrap().entries().to_vec()
+    }
+
+    /// Forgets the encodes whose output is gone, returns how many
+    pub fn prune_cache(&self) -> usize {
+        self.update_cache(|cache| cache.prune())
+    }
+
+    /// Runs `change` on the encode cache and saves it
+    fn update_cache<T>(&self, change: impl FnOnce(&mut EncodeCache) -> T) -> T {
+        let mut cache = self.cache.lock().unwrap();
+        let result = change(&mut cache);
+        if let Err(e) = cache.save() {
+            eprintln!("Failed to save the encode cache: {}", e);
+        }
+        result
+    }
+
+    /// Content hash of `input`, only read again when the file changed
+    fn source_hash(&self, input: &Path) -> Result<String, Box<dyn Error>> {
+        if let Some(hash) = self.cache.lock().unwrap().known_hash(input) {
+            return Ok(hash);
+        }
+        let hash = hash_file(input)?;
+        self.update_cache(|cache| cache.remember_hash(input, &hash));
+        Ok(hash)
+    }
+
+    /// Why encoding `source_hash` with `preset_hash` into `output` can be skipped
+    fn skip_reason(&self, source_hash: &str, preset_hash: &str, output: &Path) -> Option<String> {
+        let cache = self.cache.lock().unwrap();
+        if let Some(entry) = cache.lookup(source_hash, preset_hash) {
+            return Some(if Path::new(&entry.output) == output {
+                "Already encoded with the same settings".to_string()
+            } else {
+                format!("Same video already encoded as {}", entry.output)
+            });
+        }
+        match cache.entry_for_output(output) {
+            // made from another source or with other settings, it is stale
+            Some(_) => None,
+            // not made by us, trust the name like before
+            None if output.exists() => Some("Output already exists".to_string()),
+            None => None,
+        }
+    }
+
+    pub fn clear_finished(&self) {
+        self.update(|queue| queue.clear_finished())
+    }
+
+    /// Runs `change` on the queue and saves it
+    fn update<T>(&self, change: impl FnOnce(&mut JobQueue) -> T) -> T {
+        let mut queue = self.queue.lock().unwrap();
+        let result = change(&mut queue);
+        if let Err(e) = queue.save() {
+            eprintln!("Failed to save the job queue: {}", e);
+        }
+        result
+    }
+
+    /// True while at least one worker is busy
+    pub fn is_running(&self) -> bool {
+        *self.active_workers.lock().unwrap() > 0
+    }
+
+    /// Starts workers up to the configured count, they stop once the queue is empty
+    pub fn start(&self) {
+        let workers = self.options.lock().unwrap().workers.max(1);
+        let mut active = self.active_workers.lock().unwrap();
+        if *active == 0 {
+            self.run.lock().unwrap().get_or_insert_with(RunReport::new);
+        }
+        while *active < workers {
+            *active += 1;
+            let engine = self.clone();
+            thread::spawn(move || engine.work());
+        }
+    }
+
+    /// Blocks until all the workers are done
+    pub fn wait(&self) {
+        while self.is_running() {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn work(&self) {
+        loop {
+            self.take_submitted();
+            let Some(job) = self.update(|queue| queue.claim_next()) else {
+                // re-check under the worker lock, a job may have been queued since
+                let mut active = self.active_workers.lock().unwrap();
+                if self.has_queued() {
+                    continue;
+                }
+                *active -= 1;
+                if *active == 0 {
+                    self.write_run_report();
+                }
+                return;
+            };
+
+            let options = self.options.lock().unwrap().clone();
+            let start_time = Instant::now();
+            let killed = Cell::new(false);
+            let result = self.run_job(&job, &options, &killed);
+            let elapsed_time = start_time.elapsed().as_secs_f64();
+
+            let finished = self.update(|queue| {
+                let current = queue.get_mut(job.id)?;
+                if current.state != JobState::Running && (killed.get() || result.is_err()) {
+                    // paused or cancelled while encoding, the partial output is useless
+                    fs::remove_file(&job.output).ok();
+                    return None;
+                }
+                // a pause that came after the encode finished is too late, the outcome stands
+                current.elapsed_time = Some(elapsed_time);
+                match result {
+                    Ok(JobOutcome::Skipped { note }) => {
+                        current.state = JobState::Skipped;
+                        current.note = Some(note);
+                    }
+                    Ok(JobOutcome::Encoded { quarantined, media_duration }) => {
+                        current.state = JobState::Done;
+                        current.quarantined = quarantined;
+                        current.media_duration = media_duration;
+                        current.output_size = get_file_size_in_gb(&job.output).ok();
+                    }
+                    Err(e) => {
+                        current.state = JobState::Failed;
+                        current.error = Some(e.to_string());
+                    }
+                }
+                Some(current.clone())
+            });
+
+            if let Some(finished) = finished {
+                self.record(&finished);
+            }
+        }
+    }
+
+    /// Adds a finished job to the history and to the report of the current run
+    fn record(&self, job: &Job) {
+        let record = JobRecord::from_job(job);
+        if let Err(e) = append_history(&self.report_dir, &record) {
+            eprintln!("Failed to write the job history: {}", e);
+        }
+        if let Some(run) = self.run.lock().unwrap().as_mut() {
+            run.jobs.push(record);
+        }
+    }
+
+    fn write_run_report(&self) {
+        let Some(mut run) = self.run.lock().unwrap().take() else { return };
+        if run.jobs.is_empty() {
+            return;
+        }
+        run.finished_at = chrono::Local::now().format(TIME_FORMAT).to_string();
+        if let Err(e) = run.write(&self.report_dir) {
+            eprintln!("Failed to write the run report: {}", e);
+        }
+    }
+
+    fn run_job(&self, job: &Job, options: &EngineOptions, killed: &Cell<bool>) -> Result<JobOutcome, Box<dyn Error>> {
+        let output = Path::new(&job.output);
+        let input = Path::new(&job.input);
+        let source_hash = self.source_hash(input)?;
+        let preset_hash = preset_hash(&job.preset, job.target_size, &job.edits);
+        if options.skip_if_exists {
+            if let Some(note) = self.skip_reason(&source_hash, &preset_hash, output) {
+                return Ok(JobOutcome::Skipped { note });
+            }
+        }
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // a failed or rejected encode leaves a broken file behind, that would later pass for a finished one
+        let media_duration = self.encode(job, killed).inspect_err(|_| {
+            fs::remove_file(output).ok();
+        })?;
+        self.update_cache(|cache| {
+            cache.record(CacheEntry {
+                source_hash,
+                preset_hash,
+                source: job.input.clone(),
+                output: job.output.clone(),
+                output_size: fs::metadata(output).map(|m| m.len()).unwrap_or_default(),
+                encoded_at: chrono::Local::now().format(TIME_FORMAT).to_string(),
+            })
+        });
+
+        let quarantined = if options.delete_original {
+            Some(Quarantine::new(&options.quarantine_dir).quarantine(input, output)?.id)
+        } else {
+            None
+        };
+        Ok(JobOutcome::Encoded { quarantined, media_duration })
+    }
+
+    /// Encodes the output of `job` and checks it, returns the duration of the output
+    fn encode(&self, job: &Job, killed: &Cell<bool>) -> Result<Option<f64>, Box<dyn Error>> {
+        let output = Path::new(&job.output);
+        let input = Path::new(&job.input);
+        let log_file = self.log_dir.join(format!("{}.log", job.id));
+        let run = |args: &[String]| self.run_ffmpeg(job.id, args, &log_file, killed);
+
+        match job.target_size {
+            Some(target_size) => {
+                encode_to_target_size_with(input, output, &job.preset, &job.edits, target_size, run)?;
+            }
+            None => {
+                let work_dir = tempfile::tempdir()?;
+                let mut args = vec!["-y".to_string()];
+                args.extend(edit_args(&job.preset, &job.edits, input, output, work_dir.path())?);
+                run(&args)?;
+            }
+        }
+
+        // ffmpeg exited fine, make sure the output is complete before the original goes away
+        Ok(verify_output(input, output, &job.preset, &job.edits)?.duration().map(|d| d.as_secs_f64()))
+    }
+
+    /// Runs ffmpeg with its output going to `log_file`, killing it and setting `killed` when the job stops running
+    fn run_ffmpeg(&self, id: u64, args: &[String], log_file: &Path, killed: &Cell<bool>) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.log_dir)?;
+        let log = File::create(log_file)?;
+        let command = format!("{} {}", path_for("ffmpeg"), args.join(" "));
+        self.update(|queue| {
+            if let Some(job) = queue.get_mut(id) {
+                job.command = Some(command);
+            }
+        });
+
+        let mut child = Command::new(path_for("ffmpeg"))
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(log)
+            .spawn()
+            .map_err(|e| format!("Cannot run {}: {}", path_for("ffmpeg"), e))?;
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                self.update(|queue| {
+                    if let Some(job) = queue.get_mut(id) {
+                        job.exit_code = status.code();
+                    }
+                });
+                if status.success() {
+                    return Ok(());
+                }
+                let log = fs::read_to_string(log_file).unwrap_or_default();
+                let last_line = log.lines().last().unwrap_or_default().to_string();
+                return Err(format!("ffmpeg exited with {}: {}", status, last_line).into());
+            }
+            if !self.queue.lock().unwrap().is_running(id) {
+                child.kill().ok();
+                child.wait().ok();
+                killed.set(true);
+                return Err("Interrupted".into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+enum JobOutcome {
+    Encoded { quarantined: Option<u64>, media_duration: Option<f64> },
+    Skipped { note: String },
+}
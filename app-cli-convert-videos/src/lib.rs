@@ -1,10 +1,23 @@
+pub mod cache;
+pub mod jobs;
+pub mod quarantine;
+pub mod report;
+pub mod watch;
+
+use std::collections::BTreeMap;
 use std::{fs, io};
-use std::path::PathBuf;
-use std::process::{Command, ExitStatus};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 use serde::{Deserialize, Serialize};
+use lib_ffmpeg_utils::capabilities::Capabilities;
+use lib_ffmpeg_utils::encode::parse_size;
+use lib_ffmpeg_utils::presets::{find_preset, EncodePreset};
+use crate::jobs::EngineOptions;
+use crate::quarantine::default_quarantine_dir;
 
 // Struct for configuration
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
 pub struct AppConfig {
     pub input_folder: String,
     pub output_folder: String,
@@ -14,6 +27,59 @@ pub struct AppConfig {
     pub delete_original: bool,
     pub skip_if_exists: bool,
     pub encoding: String,
+    /// Name of the encode preset, empty means the raw `encoding`
+    #[serde(default)]
+    pub preset: String,
+    /// Fit each output into this size ("25MB") with a two-pass encode, empty to disable
+    #[serde(default)]
+    pub target_size: String,
+    /// Number of videos encoded at the same time
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// Where originals are moved instead of being deleted, empty for the default folder
+    #[serde(default)]
+    pub quarantine_folder: String,
+    /// Named variations of the settings above, picked with `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ConfigOverrides>,
+}
+
+/// Settings that replace the ones of an `AppConfig`, from a profile or the command line
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct ConfigOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_extension: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_original: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_if_exists: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quarantine_folder: Option<String>,
+}
+
+fn default_workers() -> usize {
+    1
+}
+
+fn or_default<'a>(value: &'a str, default: &'a str) -> &'a str {
+    if value.is_empty() { default } else { value }
 }
 
 impl AppConfig {
@@ -26,6 +92,105 @@ impl AppConfig {
         let yaml = fs::read_to_string(path)?;
         serde_yaml::from_str(&yaml).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
+
+    /// Replaces the settings that `overrides` sets
+    pub fn apply(&mut self, overrides: &ConfigOverrides) {
+        fn set<T: Clone>(value: &mut T, new_value: &Option<T>) {
+            if let Some(new_value) = new_value {
+                *value = new_value.clone();
+            }
+        }
+        set(&mut self.input_folder, &overrides.input_folder);
+        set(&mut self.output_folder, &overrides.output_folder);
+        set(&mut self.video_format, &overrides.video_format);
+        set(&mut self.audio_format, &overrides.audio_format);
+        set(&mut self.file_extension, &overrides.file_extension);
+        set(&mut self.delete_original, &overrides.delete_original);
+        set(&mut self.skip_if_exists, &overrides.skip_if_exists);
+        set(&mut self.encoding, &overrides.encoding);
+        set(&mut self.preset, &overrides.preset);
+        set(&mut self.target_size, &overrides.target_size);
+        set(&mut self.workers, &overrides.workers);
+        set(&mut self.quarantine_folder, &overrides.quarantine_folder);
+    }
+
+    /// Applies the profile called `name` on top of the base settings
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                format!("Unknown profile {}, the config has no profiles", name)
+            } else {
+                format!("Unknown profile {}, available: {}", name, names.join(", "))
+            }
+        })?;
+        self.apply(&profile);
+        Ok(())
+    }
+
+    /// Everything that would make a run fail, checked against `capabilities` when given
+    pub fn validate(&self, capabilities: Option<&Capabilities>) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.input_folder.is_empty() {
+            problems.push("No input folder".to_string());
+        } else if !Path::new(&self.input_folder).is_dir() {
+            problems.push(format!("Input folder {} does not exist", self.input_folder));
+        }
+        if self.output_folder.is_empty() {
+            problems.push("No output folder".to_string());
+        }
+        if self.file_extension.is_empty() {
+            problems.push("No input file extension".to_string());
+        }
+        if self.workers == 0 {
+            problems.push("Workers must be at least 1".to_string());
+        }
+        if !self.preset.is_empty() && find_preset(&self.preset).is_none() {
+            problems.push(format!("Unknown preset {}", self.preset));
+        }
+
+        let preset = self.encode_preset();
+        if !self.target_size.is_empty() {
+            if parse_size(&self.target_size).is_none() {
+                problems.push(format!("Invalid target size {}", self.target_size));
+            }
+            if !matches!(preset.video_codec.as_deref(), Some("libx264") | Some("libx265")) {
+                problems.push("A target size needs the libx264 or libx265 encoder".to_string());
+            }
+        }
+        if let Some(capabilities) = capabilities {
+            problems.extend(capabilities.check_preset(&preset));
+        }
+        problems
+    }
+
+    /// The named preset, or one built from the raw `encoding` settings
+    pub fn encode_preset(&self) -> EncodePreset {
+        find_preset(&self.preset).unwrap_or_else(|| {
+            EncodePreset::from_codecs(
+                or_default(&self.encoding, "libx264"),
+                or_default(&self.audio_format, "aac"),
+                or_default(&self.video_format, "mp4"),
+            )
+        })
+    }
+
+    pub fn quarantine_dir(&self) -> PathBuf {
+        if self.quarantine_folder.is_empty() {
+            default_quarantine_dir()
+        } else {
+            PathBuf::from(&self.quarantine_folder)
+        }
+    }
+
+    pub fn engine_options(&self) -> EngineOptions {
+        EngineOptions {
+            workers: self.workers,
+            skip_if_exists: self.skip_if_exists,
+            delete_original: self.delete_original,
+            quarantine_dir: self.quarantine_dir(),
+        }
+    }
 }
 
 
@@ -39,20 +204,6 @@ pub struct FileStat {
 }
 
 
-pub fn encode_video(file: PathBuf, encoding: &String, output_file: &String, audio: &String) -> io::Result<ExitStatus> {
-    let status = Command::new("/opt/homebrew/bin/ffmpeg")
-        .arg("-hwaccel")
-        .arg("auto")
-        .arg("-i")
-        .arg(&file)
-        .arg("-c:v")
-        .arg(&encoding)
-        .arg("-preset")
-        .arg("fast")
-        .arg("-c:a")
-        .arg(audio)
-        .arg(&output_file)
-        .status();
-    
-    status
-}
\ No newline at end of file
+pub fn encode_with_preset(file: &Path, preset: &EncodePreset, output_file: &Path) -> io::Result<ExitStatus> {
+    preset.command(file, output_file).status()
+}
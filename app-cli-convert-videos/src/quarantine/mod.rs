@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+const UNDO_LOG: &str = "undo-log.yaml";
+
+/// Workers of the same process share the undo log
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// An original moved aside after its encode was verified
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QuarantineEntry {
+    pub id: u64,
+    pub original: String,
+    pub quarantined: String,
+    /// The encode that replaced the original
+    pub output: String,
+    pub moved_at: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct UndoLog {
+    next_id: u64,
+    entries: Vec<QuarantineEntry>,
+}
+
+/// Folder where originals go instead of being deleted, with a log to put them back
+pub struct Quarantine {
+    dir: PathBuf,
+}
+
+/// Default quarantine folder
+pub fn default_quarantine_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rusty-toolbox")
+        .join("quarantine")
+}
+
+impl Quarantine {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Originals currently in quarantine, oldest first
+    pub fn entries(&self) -> io::Result<Vec<QuarantineEntry>> {
+        Ok(self.load()?.entries)
+    }
+
+    /// Records `file` in the undo log and moves it into the quarantine folder
+    ///
+    /// The log is written first, so a quarantined file is never left without a way back.
+    pub fn quarantine(&self, file: &Path, output: &Path) -> io::Result<QuarantineEntry> {
+        let _lock = LOG_LOCK.lock().unwrap();
+        let mut log = self.load()?;
+
+        log.next_id += 1;
+        let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+        let quarantined = self.dir.join(format!("{}-{}", log.next_id, file_name));
+        fs::create_dir_all(&self.dir)?;
+
+        let entry = QuarantineEntry {
+            id: log.next_id,
+            original: file.to_string_lossy().to_string(),
+            quarantined: quarantined.to_string_lossy().to_string(),
+            output: output.to_string_lossy().to_string(),
+            moved_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        };
+        log.entries.push(entry.clone());
+        self.save(&log)?;
+        if let Err(e) = move_file(file, &quarantined) {
+            log.entries.pop();
+            self.save(&log)?;
+            return Err(e);
+        }
+        Ok(entry)
+    }
+
+    /// Moves a quarantined file back to where it was, refusing to overwrite anything
+    pub fn undo(&self, id: u64) -> io::Result<QuarantineEntry> {
+        let _lock = LOG_LOCK.lock().unwrap();
+        let mut log = self.load()?;
+
+        let position = log.entries.iter().position(|entry| entry.id == id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("No quarantined file with id {}", id))
+        })?;
+        let entry = log.entries[position].clone();
+        let original = Path::new(&entry.original);
+        if original.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", original.display()),
+            ));
+        }
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(Path::new(&entry.quarantined), original)?;
+
+        log.entries.remove(position);
+        if let Err(e) = self.save(&log) {
+            // the log still lists the file, it has to stay where the log says
+            move_file(original, Path::new(&entry.quarantined)).ok();
+            return Err(e);
+        }
+        Ok(entry)
+    }
+
+    /// Puts back the most recently quarantined file
+    pub fn undo_last(&self) -> io::Result<QuarantineEntry> {
+        let last = self.entries()?.last().map(|entry| entry.id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "The quarantine is empty")
+        })?;
+        self.undo(last)
+    }
+
+    fn load(&self) -> io::Result<UndoLog> {
+        let path = self.dir.join(UNDO_LOG);
+        if !path.exists() {
+            return Ok(UndoLog::default());
+        }
+        serde_yaml::from_str(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn save(&self, log: &UndoLog) -> io::Result<()> {
+        let yaml = serde_yaml::to_string(log).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let path = self.dir.join(UNDO_LOG);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, yaml)?;
+        fs::rename(&tmp, &path)
+    }
+}
+
+/// Renames `from`, copying it when the quarantine is on another disk
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
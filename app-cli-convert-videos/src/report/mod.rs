@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::jobs::{Job, JobState};
+
+const HISTORY_FILE: &str = "history.jsonl";
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// What happened to one file, as written to the reports
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JobRecord {
+    pub job_id: u64,
+    pub input: String,
+    pub output: String,
+    pub preset: String,
+    /// Video codec, or the audio codec for audio-only presets
+    pub codec: String,
+    pub state: JobState,
+    /// Last ffmpeg command of the job
+    pub command: Option<String>,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    /// Sizes in GB
+    pub input_size: f64,
+    pub output_size: Option<f64>,
+    /// Seconds spent on the job
+    pub elapsed_time: Option<f64>,
+    /// Seconds of media in the output
+    pub media_duration: Option<f64>,
+    pub finished_at: String,
+}
+
+impl JobRecord {
+    pub fn from_job(job: &Job) -> Self {
+        let codec = job.preset.video_codec.clone().or_else(|| job.preset.audio_codec.clone()).unwrap_or_default();
+        JobRecord {
+            job_id: job.id,
+            input: job.input.clone(),
+            output: job.output.clone(),
+            preset: job.preset.name.clone(),
+            codec,
+            state: job.state,
+            command: job.command.clone(),
+            exit_code: job.exit_code,
+            error: job.error.clone(),
+            input_size: job.input_size,
+            output_size: job.output_size,
+            elapsed_time: job.elapsed_time,
+            media_duration: job.media_duration,
+            finished_at: chrono::Local::now().format(TIME_FORMAT).to_string(),
+        }
+    }
+
+    /// Encoding speed relative to playback, 2.0 means twice as fast as real time
+    pub fn speed(&self) -> Option<f64> {
+        match (self.media_duration, self.elapsed_time) {
+            (Some(duration), Some(elapsed)) if elapsed > 0.0 => Some(duration / elapsed),
+            _ => None,
+        }
+    }
+
+    pub fn saved(&self) -> Option<f64> {
+        self.output_size.map(|size| self.input_size - size)
+    }
+}
+
+/// All the jobs that finished between the workers starting and going idle
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunReport {
+    pub started_at: String,
+    pub finished_at: String,
+    pub command_line: String,
+    pub jobs: Vec<JobRecord>,
+}
+
+impl RunReport {
+    pub fn new() -> Self {
+        RunReport {
+            started_at: chrono::Local::now().format(TIME_FORMAT).to_string(),
+            finished_at: String::new(),
+            command_line: std::env::args().collect::<Vec<_>>().join(" "),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Writes `run-<start time>.json` and `.csv` into `dir`, returns the csv path
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let base = format!("run-{}", self.started_at.replace([' ', ':'], "-"));
+        // two short runs can start within the same second
+        let mut name = base.clone();
+        let mut count = 1;
+        while dir.join(format!("{}.json", name)).exists() {
+            count += 1;
+            name = format!("{}-{}", base, count);
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dir.join(format!("{}.json", name)), json)?;
+        let path = dir.join(format!("{}.csv", name));
+        fs::write(&path, self.to_csv())?;
+        Ok(path)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "job_id,input,output,preset,codec,state,exit_code,input_size_gb,output_size_gb,elapsed_seconds,media_seconds,finished_at,command,error\n",
+        );
+        for job in &self.jobs {
+            let fields = [
+                job.job_id.to_string(),
+                job.input.clone(),
+                job.output.clone(),
+                job.preset.clone(),
+                job.codec.clone(),
+                format!("{:?}", job.state),
+                optional(job.exit_code),
+                job.input_size.to_string(),
+                optional(job.output_size),
+                optional(job.elapsed_time),
+                optional(job.media_duration),
+                job.finished_at.clone(),
+                job.command.clone().unwrap_or_default(),
+                job.error.clone().unwrap_or_default(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+impl Default for RunReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Adds a record to the history kept in `dir`, one json object per line
+pub fn append_history(dir: &Path, record: &JobRecord) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let line = serde_json::to_string(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(dir.join(HISTORY_FILE))?;
+    writeln!(file, "{}", line)
+}
+
+/// All the records of the history in `dir`, lines that cannot be read are skipped
+pub fn load_history(dir: &Path) -> io::Result<Vec<JobRecord>> {
+    let path = dir.join(HISTORY_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct CodecSummary {
+    pub codec: String,
+    pub encoded: usize,
+    /// Average speed relative to playback
+    pub average_speed: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub done: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub input_gb: f64,
+    pub output_gb: f64,
+    pub saved_gb: f64,
+    /// Failed jobs out of the jobs that ran, skipped ones do not count
+    pub failure_rate: f64,
+    pub codecs: Vec<CodecSummary>,
+}
+
+/// Totals over `records`, sizes only count the encoded files
+pub fn summarize(records: &[JobRecord]) -> Summary {
+    let mut summary = Summary::default();
+    let mut speeds: BTreeMap<String, (usize, Vec<f64>)> = BTreeMap::new();
+
+    for record in records {
+        match record.state {
+            JobState::Done => {
+                summary.done += 1;
+                summary.input_gb += record.input_size;
+                summary.output_gb += record.output_size.unwrap_or_default();
+                summary.saved_gb += record.saved().unwrap_or_default();
+                let codec = speeds.entry(record.codec.clone()).or_default();
+                codec.0 += 1;
+                codec.1.extend(record.speed());
+            }
+            JobState::Failed => summary.failed += 1,
+            JobState::Skipped => summary.skipped += 1,
+            _ => {}
+        }
+    }
+
+    let ran = summary.done + summary.failed;
+    if ran > 0 {
+        summary.failure_rate = summary.failed as f64 / ran as f64;
+    }
+    summary.codecs = speeds
+        .into_iter()
+        .map(|(codec, (encoded, speeds))| CodecSummary {
+            codec,
+            encoded,
+            average_speed: if speeds.is_empty() { None } else { Some(speeds.iter().sum::<f64>() / speeds.len() as f64) },
+        })
+        .collect();
+    summary
+}
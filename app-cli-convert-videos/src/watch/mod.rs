@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use notify::{EventKind, RecursiveMode, Watcher};
+use lib_ffmpeg_utils::encode::parse_size;
+use crate::jobs::{JobEngine, JobState};
+use crate::AppConfig;
+
+/// A file is considered complete once its size did not change for this long
+pub const SETTLE_TIME: Duration = Duration::from_secs(10);
+/// How often growing files are checked again
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Files seen by the watcher that may still be copied
+#[derive(Default)]
+pub struct PendingFiles {
+    files: HashMap<PathBuf, (Option<(u64, SystemTime)>, Instant)>,
+}
+
+impl PendingFiles {
+    /// Starts or restarts the wait for `path`
+    pub fn touch(&mut self, path: &Path, now: Instant) {
+        self.files.insert(path.to_path_buf(), (file_state(path), now));
+    }
+
+    /// Files whose size and modification time stayed the same for `settle_time`
+    ///
+    /// A file that changed since the last check starts waiting again, a removed file is forgotten.
+    pub fn settled(&mut self, now: Instant, settle_time: Duration) -> Vec<PathBuf> {
+        let mut settled = Vec::new();
+        self.files.retain(|path, (last_state, since)| {
+            if !path.exists() {
+                return false;
+            }
+            let state = file_state(path);
+            if state != *last_state {
+                *last_state = state;
+                *since = now;
+                return true;
+            }
+            if now.duration_since(*since) >= settle_time {
+                settled.push(path.clone());
+                return false;
+            }
+            true
+        });
+        settled.sort();
+        settled
+    }
+}
+
+fn file_state(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// True for files with the wanted extension, outside hidden files and folders
+pub fn is_candidate(input_root: &Path, path: &Path, extension: &str) -> bool {
+    let relative = path.strip_prefix(input_root).unwrap_or(path);
+    let hidden = relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+    let matches = path.extension().is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension));
+    !hidden && matches
+}
+
+/// Candidate files under `dir`, in all the subfolders
+pub fn scan_recursive(input_root: &Path, dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                files.extend(scan_recursive(input_root, &path, extension));
+            }
+        } else if is_candidate(input_root, &path, extension) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Output file for `file`, in the same subfolder under `output_root` as it is under `input_root`
+pub fn mirrored_output(input_root: &Path, output_root: &Path, file: &Path, container: &str) -> PathBuf {
+    let relative = file.strip_prefix(input_root).unwrap_or(Path::new(file.file_name().unwrap_or_default()));
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let folder = match relative.parent() {
+        Some(parent) => output_root.join(parent),
+        None => output_root.to_path_buf(),
+    };
+    folder.join(format!("{}.{}", stem, container))
+}
+
+/// Watches the config input folder and encodes every completed video, never returns unless the watch fails
+///
+/// Files already in the folder are picked up too, `skip_if_exists` avoids encoding them again.
+pub fn watch(config: &AppConfig, engine: &JobEngine) -> Result<(), Box<dyn Error>> {
+    let input_root = fs::canonicalize(&config.input_folder)
+        .map_err(|e| format!("Cannot watch {}: {}", config.input_folder, e))?;
+    fs::create_dir_all(&config.output_folder)?;
+    let output_root = fs::canonicalize(&config.output_folder)?;
+    let preset = config.encode_preset();
+    let target_size = parse_size(&config.target_size);
+    engine.set_options(config.engine_options());
+
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&input_root, RecursiveMode::Recursive)?;
+    println!("Watching {} for .{} files, preset {}", input_root.display(), config.file_extension, preset.name);
+
+    let mut pending = PendingFiles::default();
+    for file in scan_recursive(&input_root, &input_root, &config.file_extension) {
+        pending.touch(&file, Instant::now());
+    }
+    // finished jobs already in the queue are not reported again
+    let mut reported: HashSet<u64> =
+        engine.jobs().iter().filter(|job| job.state.is_finished()).map(|job| job.id).collect();
+    engine.start();
+
+    loop {
+        match rx.recv_timeout(CHECK_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if path.is_dir() {
+                            // a folder moved in at once only sends one event
+                            for file in scan_recursive(&input_root, &path, &config.file_extension) {
+                                pending.touch(&file, Instant::now());
+                            }
+                        } else if is_candidate(&input_root, &path, &config.file_extension) {
+                            pending.touch(&path, Instant::now());
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!("Watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err("The folder watcher stopped".into()),
+        }
+
+        for file in pending.settled(Instant::now(), SETTLE_TIME) {
+            // the output folder may sit inside the input folder, our own encodes are not sources
+            if file.starts_with(&output_root) {
+                continue;
+            }
+            let output = mirrored_output(&input_root, &output_root, &file, &preset.container);
+            let input = file.to_string_lossy();
+            if engine.enqueue(&input, &output.to_string_lossy(), &preset, target_size).is_some() {
+                println!("Queued {}", input);
+                engine.start();
+            }
+        }
+
+        for job in engine.jobs().iter().filter(|job| job.state.is_finished()) {
+            if reported.insert(job.id) {
+                match job.state {
+                    JobState::Failed => println!("Failed {}: {}", job.input, job.error.clone().unwrap_or_default()),
+                    state => println!("{:?} {} -> {}", state, job.input, job.output),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use app_cli_convert_videos::cache::{hash_file, preset_hash, CacheEntry, EncodeCache};
+    use lib_ffmpeg_utils::edit::{EditInstructions, Rotation};
+    use lib_ffmpeg_utils::presets::{EncodePreset, Quality};
+
+    fn entry(source_hash: &str, preset_hash: &str, output: &PathBuf) -> CacheEntry {
+        CacheEntry {
+            source_hash: source_hash.to_string(),
+            preset_hash: preset_hash.to_string(),
+            source: "in/a.mov".to_string(),
+            output: output.to_string_lossy().to_string(),
+            output_size: fs::metadata(output).unwrap().len(),
+            encoded_at: "2024-05-01 10:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn same_content_same_hash() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("a.mov"), "footage").unwrap();
+        fs::write(dir.join("copy of a.mov"), "footage").unwrap();
+        fs::write(dir.join("b.mov"), "other footage").unwrap();
+
+        let a = hash_file(&dir.join("a.mov")).unwrap();
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, hash_file(&dir.join("copy of a.mov")).unwrap());
+        assert_ne!(a, hash_file(&dir.join("b.mov")).unwrap());
+    }
+
+    #[test]
+    fn preset_hash_ignores_the_name() {
+        let preset = EncodePreset::from_codecs("libx265", "aac", "mp4");
+        let renamed = EncodePreset { name: "my archive".to_string(), ..preset.clone() };
+        let better = EncodePreset { quality: Quality::Crf { value: 18 }, ..preset.clone() };
+
+        let none = EditInstructions::default();
+        let rotated = EditInstructions { rotate: Rotation::Clockwise, ..Default::default() };
+
+        assert_eq!(preset_hash(&preset, None, &none), preset_hash(&renamed, None, &none));
+        assert_ne!(preset_hash(&preset, None, &none), preset_hash(&better, None, &none));
+        assert_ne!(preset_hash(&preset, None, &none), preset_hash(&preset, Some(25_000_000), &none));
+        assert_ne!(preset_hash(&preset, None, &none), preset_hash(&preset, None, &rotated));
+    }
+
+    #[test]
+    fn lookup_needs_an_intact_output() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let output = dir.join("a.mp4");
+        fs::write(&output, "encoded").unwrap();
+
+        let mut cache = EncodeCache::load(&dir.join("cache.yaml")).unwrap();
+        cache.record(entry("source", "preset", &output));
+        cache.save().unwrap();
+
+        let mut cache = EncodeCache::load(&dir.join("cache.yaml")).unwrap();
+        assert!(cache.lookup("source", "preset").is_some());
+        assert!(cache.lookup("source", "other preset").is_none());
+        assert!(cache.entry_for_output(&output).is_some());
+
+        // replaced by hand, the entry no longer counts
+        fs::write(&output, "something else").unwrap();
+        assert!(cache.lookup("source", "preset").is_none());
+        assert_eq!(cache.prune(), 1);
+        assert!(cache.entries().is_empty());
+    }
+
+    #[test]
+    fn known_hash_follows_the_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let source = dir.join("a.mov");
+        fs::write(&source, "footage").unwrap();
+
+        let mut cache = EncodeCache::new(&dir.join("cache.yaml"));
+        assert_eq!(cache.known_hash(&source), None);
+        cache.remember_hash(&source, "abc");
+        assert_eq!(cache.known_hash(&source).as_deref(), Some("abc"));
+
+        fs::write(&source, "longer footage").unwrap();
+        assert_eq!(cache.known_hash(&source), None);
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use app_cli_convert_videos::{AppConfig, ConfigOverrides};
+
+    const CONFIG: &str = "
+input_folder: /
+output_folder: /tmp/out
+video_format: mp4
+audio_format: aac
+file_extension: mov
+delete_original: false
+skip_if_exists: true
+encoding: libx265
+profiles:
+  phone:
+    target_size: 25MB
+    encoding: libx264
+  archive:
+    preset: archive H.265 CRF 22
+    delete_original: true
+";
+
+    #[test]
+    fn profiles_and_overrides() {
+        let mut config: AppConfig = serde_yaml::from_str(CONFIG).unwrap();
+        assert_eq!(config.workers, 1);
+
+        config.apply_profile("phone").unwrap();
+        assert_eq!(config.encoding, "libx264");
+        assert_eq!(config.target_size, "25MB");
+        assert!(config.skip_if_exists);
+
+        config.apply(&ConfigOverrides { workers: Some(3), encoding: Some("libx265".to_string()), ..Default::default() });
+        assert_eq!(config.workers, 3);
+        assert_eq!(config.encode_preset().video_codec.as_deref(), Some("libx265"));
+        assert_eq!(config.output_folder, "/tmp/out");
+
+        let error = config.apply_profile("tablet").unwrap_err();
+        assert_eq!(error, "Unknown profile tablet, available: archive, phone");
+    }
+
+    #[test]
+    fn partial_config_files_load() {
+        let config: AppConfig = serde_yaml::from_str("input_folder: /videos\nworkers: 2\n").unwrap();
+        assert_eq!(config.input_folder, "/videos");
+        assert_eq!(config.workers, 2);
+        assert!(config.profiles.is_empty());
+        // profiles are not written when there are none
+        assert!(!serde_yaml::to_string(&config).unwrap().contains("profiles"));
+    }
+
+    #[test]
+    fn validation() {
+        let mut config: AppConfig = serde_yaml::from_str(CONFIG).unwrap();
+        assert_eq!(config.validate(None), Vec::<String>::new());
+
+        config.apply(&ConfigOverrides {
+            input_folder: Some("/does/not/exist".to_string()),
+            preset: Some("nope".to_string()),
+            target_size: Some("big".to_string()),
+            workers: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(
+            config.validate(None),
+            vec![
+                "Input folder /does/not/exist does not exist".to_string(),
+                "Workers must be at least 1".to_string(),
+                "Unknown preset nope".to_string(),
+                "Invalid target size big".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use app_cli_convert_videos::jobs::{submit_jobs, EngineOptions, JobEngine, JobQueue, JobRequest, JobState};
+    use lib_ffmpeg_utils::edit::EditInstructions;
+    use lib_ffmpeg_utils::presets::EncodePreset;
+
+    fn preset() -> EncodePreset {
+        EncodePreset::from_codecs("libx264", "aac", "mp4")
+    }
+
+    #[test]
+    fn pending_jobs_are_not_queued_twice() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut queue = JobQueue::load(&temp.path().join("jobs.yaml")).unwrap();
+        assert_eq!(queue.push("a.mov", "out/a.mp4", &preset(), None), Some(1));
+        assert_eq!(queue.push("a.mov", "out/a.mp4", &preset(), None), None);
+        assert_eq!(queue.push("b.mov", "out/b.mp4", &preset(), Some(25_000_000)), Some(2));
+
+        // once done the same file can be encoded again
+        queue.claim_next().unwrap();
+        assert_eq!(queue.jobs()[0].state, JobState::Running);
+        assert!(queue.cancel(1));
+        assert_eq!(queue.push("a.mov", "out/a.mp4", &preset(), None), Some(3));
+    }
+
+    #[test]
+    fn state_transitions() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut queue = JobQueue::load(&temp.path().join("jobs.yaml")).unwrap();
+        queue.push("a.mov", "out/a.mp4", &preset(), None);
+        queue.push("b.mov", "out/b.mp4", &preset(), None);
+
+        assert!(queue.pause(1));
+        assert_eq!(queue.claim_next().unwrap().id, 2);
+        assert!(queue.claim_next().is_none());
+        assert!(!queue.retry(2));
+
+        assert!(queue.resume(1));
+        let job = queue.claim_next().unwrap();
+        assert_eq!((job.id, job.attempts), (1, 1));
+
+        assert!(queue.cancel(1));
+        assert!(!queue.pause(1));
+        assert!(queue.retry(1));
+        assert_eq!(queue.claim_next().unwrap().attempts, 2);
+
+        assert_eq!(queue.progress(), (0, 2));
+        queue.cancel(2);
+        assert_eq!(queue.progress(), (0, 1));
+        queue.clear_finished();
+        assert_eq!(queue.jobs().len(), 1);
+    }
+
+    #[test]
+    fn running_jobs_resume_after_a_restart() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("jobs.yaml");
+        let mut queue = JobQueue::load(&path).unwrap();
+        queue.push("a.mov", "out/a.mp4", &preset(), Some(8_000_000));
+        let trim = EditInstructions { start: Some(2.0), end: Some(10.0), ..Default::default() };
+        queue.push_edited("b.mov", "out/b.mp4", &preset(), None, trim.clone());
+        queue.claim_next();
+        queue.pause(2);
+        queue.save().unwrap();
+
+        let mut reloaded = JobQueue::load(&path).unwrap();
+        assert_eq!(reloaded.jobs()[0].state, JobState::Queued);
+        assert_eq!(reloaded.jobs()[0].target_size, Some(8_000_000));
+        assert_eq!(reloaded.jobs()[1].state, JobState::Paused);
+        assert_eq!(reloaded.jobs()[1].edits, trim);
+        // edits can change until the job starts
+        assert!(reloaded.set_edits(2, EditInstructions::default()));
+        assert_eq!(reloaded.push("c.mov", "out/c.mp4", &preset(), None), Some(3));
+        assert_eq!(reloaded.claim_next().unwrap().id, 1);
+        assert!(!reloaded.set_edits(1, trim));
+    }
+
+    #[test]
+    fn other_apps_hand_their_jobs_to_the_open_engine() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("jobs.yaml");
+        let engine = JobEngine::open(&path, EngineOptions::default()).unwrap();
+        assert!(JobEngine::in_use(&path));
+        assert!(JobEngine::open(&path, EngineOptions::default()).is_err());
+
+        let request = JobRequest {
+            input: "a.mov".to_string(),
+            output: "out/a.mp4".to_string(),
+            preset: preset(),
+            target_size: None,
+            edits: EditInstructions::default(),
+        };
+        submit_jobs(&path, &[request.clone(), request]).unwrap();
+        // the same job twice is queued once
+        assert_eq!(engine.take_submitted(), 1);
+        assert_eq!(engine.take_submitted(), 0);
+        assert_eq!(engine.jobs()[0].input, "a.mov");
+
+        drop(engine);
+        assert!(!JobEngine::in_use(&path));
+    }
+
+    #[test]
+    fn failed_jobs_leave_no_output_behind() {
+        let temp = tempfile::tempdir().unwrap();
+        let (input, output) = (temp.path().join("a.mov"), temp.path().join("out/a.mp4"));
+        std::fs::write(&input, "not a video").unwrap();
+        std::fs::create_dir_all(output.parent().unwrap()).unwrap();
+        std::fs::write(&output, "half an encode").unwrap();
+
+        let options = EngineOptions { quarantine_dir: temp.path().join("quarantine"), ..Default::default() };
+        let engine = JobEngine::open(&temp.path().join("jobs.yaml"), options.clone()).unwrap();
+        engine.enqueue(&input.to_string_lossy(), &output.to_string_lossy(), &preset(), None);
+        engine.start();
+        engine.wait();
+        assert_eq!(engine.jobs()[0].state, JobState::Failed);
+        assert!(!output.exists());
+
+        // so a retry does not take the broken file for a finished encode
+        engine.set_options(EngineOptions { skip_if_exists: true, ..options });
+        engine.retry(1);
+        engine.wait();
+        assert_eq!(engine.jobs()[0].state, JobState::Failed);
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use app_cli_convert_videos::quarantine::Quarantine;
+
+    #[test]
+    fn quarantine_and_undo() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let quarantine = Quarantine::new(&dir.join("quarantine"));
+        let original = dir.join("movies").join("holiday.mov");
+        fs::create_dir_all(original.parent().unwrap()).unwrap();
+        fs::write(&original, "footage").unwrap();
+
+        let entry = quarantine.quarantine(&original, &dir.join("holiday.mp4")).unwrap();
+        assert!(!original.exists());
+        assert_eq!(fs::read_to_string(&entry.quarantined).unwrap(), "footage");
+        assert_eq!(quarantine.entries().unwrap(), vec![entry.clone()]);
+
+        // the undo log survives a new instance
+        let quarantine = Quarantine::new(&dir.join("quarantine"));
+        assert_eq!(quarantine.undo_last().unwrap().id, entry.id);
+        assert_eq!(fs::read_to_string(&original).unwrap(), "footage");
+        assert!(quarantine.entries().unwrap().is_empty());
+        assert!(quarantine.undo_last().is_err());
+
+        // a file that cannot be moved is not left in the log
+        assert!(quarantine.quarantine(&dir.join("missing.mov"), &dir.join("missing.mp4")).is_err());
+        assert!(quarantine.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn same_name_twice_and_no_overwrite() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let quarantine = Quarantine::new(&dir.join("quarantine"));
+        let original = dir.join("clip.mov");
+
+        fs::write(&original, "first").unwrap();
+        let first = quarantine.quarantine(&original, &dir.join("clip.mp4")).unwrap();
+        fs::write(&original, "second").unwrap();
+        let second = quarantine.quarantine(&original, &dir.join("clip.mp4")).unwrap();
+        assert_ne!(first.quarantined, second.quarantined);
+
+        // a new file took the original name, restoring must not replace it
+        fs::write(&original, "new").unwrap();
+        assert!(quarantine.undo(first.id).is_err());
+        assert_eq!(fs::read_to_string(&original).unwrap(), "new");
+
+        fs::remove_file(&original).unwrap();
+        quarantine.undo(first.id).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "first");
+        assert_eq!(quarantine.entries().unwrap(), vec![second]);
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use app_cli_convert_videos::jobs::JobState;
+    use app_cli_convert_videos::report::{append_history, load_history, summarize, JobRecord, RunReport};
+
+    fn record(job_id: u64, codec: &str, state: JobState, sizes: (f64, Option<f64>), times: (f64, Option<f64>)) -> JobRecord {
+        JobRecord {
+            job_id,
+            input: format!("in/{}.mov", job_id),
+            output: format!("out/{}.mp4", job_id),
+            preset: codec.to_string(),
+            codec: codec.to_string(),
+            state,
+            command: Some(format!("ffmpeg -i in/{}.mov out/{}.mp4", job_id, job_id)),
+            exit_code: Some(0),
+            error: None,
+            input_size: sizes.0,
+            output_size: sizes.1,
+            elapsed_time: Some(times.0),
+            media_duration: times.1,
+            finished_at: "2024-05-01 10:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn summary_over_history() {
+        let records = vec![
+            record(1, "libx265", JobState::Done, (2.0, Some(0.5)), (60.0, Some(120.0))),
+            record(2, "libx265", JobState::Done, (1.0, Some(0.5)), (60.0, Some(60.0))),
+            record(3, "libx264", JobState::Done, (1.0, Some(0.8)), (10.0, None)),
+            record(4, "libx264", JobState::Failed, (1.0, None), (5.0, None)),
+            record(5, "libx264", JobState::Skipped, (1.0, None), (0.0, None)),
+        ];
+
+        let summary = summarize(&records);
+        assert_eq!((summary.done, summary.failed, summary.skipped), (3, 1, 1));
+        assert!((summary.input_gb - 4.0).abs() < 1e-9);
+        assert!((summary.saved_gb - 2.2).abs() < 1e-9);
+        assert!((summary.failure_rate - 0.25).abs() < 1e-9);
+
+        assert_eq!(summary.codecs.len(), 2);
+        assert_eq!(summary.codecs[0].codec, "libx264");
+        assert_eq!(summary.codecs[0].average_speed, None);
+        assert_eq!(summary.codecs[1].encoded, 2);
+        assert_eq!(summary.codecs[1].average_speed, Some(1.5));
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let mut failed = record(7, "libx264", JobState::Failed, (1.0, None), (3.0, None));
+        failed.input = "in/holiday, day 1.mov".to_string();
+        failed.error = Some("ffmpeg exited with \"1\"".to_string());
+        let report = RunReport { jobs: vec![failed], ..RunReport::new() };
+
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("job_id,input,output"));
+        assert!(lines[1].starts_with("7,\"in/holiday, day 1.mov\",out/7.mp4,libx264,libx264,Failed,0,1,,3,,"));
+        assert!(lines[1].ends_with(",\"ffmpeg exited with \"\"1\"\"\""));
+    }
+
+    #[test]
+    fn history_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("reports");
+
+        assert!(load_history(&dir).unwrap().is_empty());
+        let first = record(1, "libx265", JobState::Done, (2.0, Some(0.5)), (60.0, Some(120.0)));
+        let second = record(2, "libx265", JobState::Failed, (1.0, None), (1.0, None));
+        append_history(&dir, &first).unwrap();
+        append_history(&dir, &second).unwrap();
+
+        assert_eq!(load_history(&dir).unwrap(), vec![first, second]);
+    }
+}
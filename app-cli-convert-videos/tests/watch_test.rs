@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+    use app_cli_convert_videos::watch::{is_candidate, mirrored_output, scan_recursive, PendingFiles};
+
+    #[test]
+    fn output_mirrors_subfolders() {
+        let output = mirrored_output(Path::new("/in"), Path::new("/out"), Path::new("/in/2024/trip/IMG_1.MOV"), "mp4");
+        assert_eq!(output, PathBuf::from("/out/2024/trip/IMG_1.mp4"));
+        let output = mirrored_output(Path::new("/in"), Path::new("/out"), Path::new("/in/clip.mov"), "mkv");
+        assert_eq!(output, PathBuf::from("/out/clip.mkv"));
+    }
+
+    #[test]
+    fn candidates_skip_hidden_paths() {
+        let root = Path::new("/in");
+        assert!(is_candidate(root, Path::new("/in/a/b.MOV"), "mov"));
+        assert!(!is_candidate(root, Path::new("/in/a/b.mp4"), "mov"));
+        assert!(!is_candidate(root, Path::new("/in/.Trash/b.mov"), "mov"));
+        assert!(!is_candidate(root, Path::new("/in/a/._b.mov"), "mov"));
+    }
+
+    #[test]
+    fn scan_finds_nested_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("day1/cam")).unwrap();
+        fs::create_dir_all(dir.join(".hidden")).unwrap();
+        for file in ["top.mov", "day1/cam/a.mov", "day1/notes.txt", ".hidden/c.mov"] {
+            fs::write(dir.join(file), "x").unwrap();
+        }
+
+        let files = scan_recursive(dir, dir, "mov");
+        assert_eq!(files, vec![dir.join("day1/cam/a.mov"), dir.join("top.mov")]);
+    }
+
+    #[test]
+    fn growing_files_are_not_settled() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let file = dir.join("copy.mov");
+        fs::write(&file, "part").unwrap();
+
+        let start = Instant::now();
+        let settle = Duration::from_secs(10);
+        let mut pending = PendingFiles::default();
+        pending.touch(&file, start);
+        assert!(pending.settled(start + Duration::from_secs(5), settle).is_empty());
+
+        // the copy goes on, the wait starts over
+        fs::write(&file, "part and more").unwrap();
+        assert!(pending.settled(start + Duration::from_secs(11), settle).is_empty());
+        assert!(pending.settled(start + Duration::from_secs(20), settle).is_empty());
+        assert_eq!(pending.settled(start + Duration::from_secs(21), settle), vec![file.clone()]);
+        // settled files are only returned once
+        assert!(pending.settled(start + Duration::from_secs(40), settle).is_empty());
+
+        pending.touch(&file, start);
+        fs::remove_file(&file).unwrap();
+        assert!(pending.settled(start + Duration::from_secs(60), settle).is_empty());
+    }
+}
@@ -84,6 +84,25 @@ impl MyApp {
         self.replace_or_insert(reg1, reg2, new1);
     }
 
+    /// Applies the codec, speed, tune and screen size of a shared encode preset
+    fn set_encode_preset(& mut self, preset: &EncodePreset) {
+        if let Some(codec) = &preset.video_codec {
+            self.set_codec(codec.clone());
+        }
+        if let Some(speed) = &preset.speed {
+            self.set_preset(speed.clone());
+        }
+        if let Some(tune) = &preset.tune {
+            self.set_tune(tune.clone());
+        }
+        // only fixed sizes map to -s, "-2:720" style sizes are skipped
+        if let Some((w, h)) = preset.scale.as_ref().and_then(|s| s.split_once(':')) {
+            if let (Ok(w), Ok(h)) = (w.parse::<u32>(), h.parse::<u32>()) {
+                self.set_screen(format!("{}x{}", w, h));
+            }
+        }
+    }
+
     fn replace_or_insert(&mut self, reg1: &str, reg2: &str, new1: String) {
         // Regular expressions
         let preset_re = Regex::new(reg1).unwrap();
@@ -241,6 +260,14 @@ impl eframe::App for MyApp {
                         }
                     });
 
+                    ui.menu_button("Encode Preset", |ui| {
+                        for preset in all_presets().iter().filter(|p| p.has_video()) {
+                            if ui.button(&preset.name).clicked() {
+                                self.set_encode_preset(preset);
+                            }
+                        }
+                    });
+
                     //-s 960x540
 
                 });
@@ -420,6 +447,7 @@ use lib_egui_utils::my_default_options;
 use regex::Regex;
 use lib_ffmpeg_utils::devices::DeviceLister;
 use lib_ffmpeg_utils::log::append_to_home_log;
+use lib_ffmpeg_utils::presets::{all_presets, EncodePreset};
 use lib_ffmpeg_utils::utils::{clean_up_parameters, ffmpeg_binary, format_duration};
 
 fn compute_wait_duration(start_time_str: &str) -> Result<Duration, Box<dyn Error>> {
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use crate::catalog::CatalogEntry;
+
+/// Default template of the rename action
+pub const DEFAULT_RENAME_TEMPLATE: &str = "{date} {name}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Move,
+    Copy,
+}
+
+/// File name built from a template, the extension of the video is kept
+///
+/// `{name}` is the original name without extension, `{date}` and `{time}` the modification time,
+/// `{resolution}` the height of the video like `1080p` and `{n}` the position in the batch.
+pub fn render_name(template: &str, entry: &CatalogEntry, number: usize) -> String {
+    let path = Path::new(&entry.path);
+    let modified = DateTime::from_timestamp(entry.modified as i64, 0).map(|time| time.with_timezone(&Local));
+    let resolution = entry
+        .metadata()
+        .video_stream()
+        .and_then(|stream| stream.resolution())
+        .map(|(_, height)| format!("{}p", height))
+        .unwrap_or_default();
+
+    let name = template
+        .replace("{name}", &path.file_stem().unwrap_or_default().to_string_lossy())
+        .replace("{date}", &modified.map(|time| time.format("%Y-%m-%d").to_string()).unwrap_or_default())
+        .replace("{time}", &modified.map(|time| time.format("%H-%M-%S").to_string()).unwrap_or_default())
+        .replace("{resolution}", &resolution)
+        .replace("{n}", &number.to_string());
+    // placeholders without a value leave double spaces behind
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ").replace(['/', '\\', ':'], "-");
+    match path.extension() {
+        Some(extension) => format!("{}.{}", name, extension.to_string_lossy()),
+        None => name,
+    }
+}
+
+/// New path of each video, refused as a whole when a name is empty or taken
+pub fn rename_plan(entries: &[CatalogEntry], template: &str) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let sources: HashSet<&Path> = entries.iter().map(|entry| Path::new(&entry.path)).collect();
+    let mut targets = HashSet::new();
+    let mut plan = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let source = PathBuf::from(&entry.path);
+        let name = render_name(template, entry, index + 1);
+        if Path::new(&name).file_stem().is_none() {
+            return Err(format!("The template gives an empty name for {}", source.display()));
+        }
+        let target = source.with_file_name(&name);
+        if !targets.insert(target.clone()) {
+            return Err(format!("Several videos would be named {}", name));
+        }
+        // a video of the batch may free its name before this one takes it
+        if target != source && target.exists() && !sources.contains(target.as_path()) {
+            return Err(format!("{} already exists", target.display()));
+        }
+        plan.push((source, target));
+    }
+    Ok(plan)
+}
+
+/// Renames the videos of a plan, through temporary names so they can swap names
+///
+/// When a rename fails, the ones already done are undone so every video keeps its old name.
+pub fn apply_renames(plan: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    let pending: Vec<_> = plan.iter().filter(|(source, target)| source != target).collect();
+    let steps: Vec<(&PathBuf, PathBuf, &PathBuf)> = pending
+        .iter()
+        .enumerate()
+        .map(|(index, (source, target))| {
+            let name = format!(".renaming-{}-{}", index, source.file_name().unwrap_or_default().to_string_lossy());
+            (source, source.with_file_name(name), target)
+        })
+        .collect();
+    let moves = steps
+        .iter()
+        .map(|(source, step, _)| (source.as_path(), step.as_path()))
+        .chain(steps.iter().map(|(_, step, target)| (step.as_path(), target.as_path())));
+
+    let mut done = Vec::new();
+    for (from, to) in moves {
+        if let Err(e) = fs::rename(from, to) {
+            for (from, to) in done.into_iter().rev() {
+                fs::rename(to, from).ok();
+            }
+            return Err(e);
+        }
+        done.push((from, to));
+    }
+    Ok(())
+}
+
+/// Contact sheet of a video in `folder`, named after the video
+pub fn sheet_path(video: &Path, folder: &Path, extension: &str) -> PathBuf {
+    folder.join(format!("{}-sheet.{}", video.file_stem().unwrap_or_default().to_string_lossy(), extension))
+}
+
+/// Moves or copies a video into `folder`, an existing file is never replaced
+pub fn transfer(source: &Path, folder: &Path, mode: Transfer) -> io::Result<PathBuf> {
+    let target = folder.join(source.file_name().unwrap_or_default());
+    if target.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", target.display())));
+    }
+    match mode {
+        Transfer::Copy => {
+            fs::copy(source, &target)?;
+        }
+        // rename fails across drives, copy then delete instead
+        Transfer::Move => {
+            if fs::rename(source, &target).is_err() {
+                fs::copy(source, &target)?;
+                fs::remove_file(source)?;
+            }
+        }
+    }
+    Ok(target)
+}
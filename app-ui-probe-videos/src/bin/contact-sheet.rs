@@ -0,0 +1,85 @@
+use app_ui_probe_videos::batch::sheet_path;
+use app_ui_probe_videos::contact_sheet;
+use app_ui_probe_videos::sheet::SheetOptions;
+use clap::{value_parser, Arg, ArgAction, Command};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+fn main() {
+    let matches = Command::new("Contact Sheet")
+        .version("1.0")
+        .about("Renders a grid of timestamped frames of each video, under a header with its details")
+        .arg(
+            Arg::new("videos")
+                .value_name("VIDEO")
+                .help("Videos to render a sheet for")
+                .required(true)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("OUTPUT")
+                .help("Image to write, only with a single video [default: <video>-sheet.<format> next to it]"),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .help("Image format of the default outputs")
+                .value_parser(["png", "jpg"])
+                .default_value("png"),
+        )
+        .arg(
+            Arg::new("columns")
+                .long("columns")
+                .value_name("COLUMNS")
+                .value_parser(value_parser!(u32).range(1..))
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("rows")
+                .long("rows")
+                .value_name("ROWS")
+                .value_parser(value_parser!(u32).range(1..))
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("width")
+                .long("width")
+                .value_name("PIXELS")
+                .help("Width of each frame")
+                .value_parser(value_parser!(u32).range(16..))
+                .default_value("320"),
+        )
+        .get_matches();
+
+    let videos: Vec<&String> = matches.get_many::<String>("videos").unwrap().collect();
+    let output = matches.get_one::<String>("output");
+    if output.is_some() && videos.len() > 1 {
+        eprintln!("--output needs a single video, the sheets of several videos are written next to them");
+        exit(2);
+    }
+    let options = SheetOptions {
+        columns: *matches.get_one("columns").unwrap(),
+        rows: *matches.get_one("rows").unwrap(),
+        frame_width: *matches.get_one("width").unwrap(),
+    };
+    let format = matches.get_one::<String>("format").unwrap();
+
+    let mut failed = false;
+    for video in videos {
+        let video_path = Path::new(video);
+        let folder = video_path.parent().unwrap_or(Path::new("."));
+        let output = output.map(PathBuf::from).unwrap_or_else(|| sheet_path(video_path, folder, format));
+        if let Err(e) = contact_sheet(video, &output.to_string_lossy(), &options) {
+            eprintln!("{}", e);
+            failed = true;
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
@@ -8,9 +8,11 @@ fn main() {
     } else {
         println!("The file does not exist.");
     }
-    let metadata = extract_metadata(video_path.into());
-    // Print the parsed metadata
-    println!("{:#?}", metadata.format.filename);
+    match extract_metadata(video_path.into()) {
+        // Print the parsed metadata
+        Ok(metadata) => println!("{:#?}", metadata),
+        Err(e) => eprintln!("Error: {}", e),
+    }
 
     let timestamp = "01:00:01"; // Extract frame at 1-second mark
     let output_image = "output_frame.png";
@@ -1,29 +1,117 @@
-use app_ui_probe_videos::{extract_frame, extract_metadata, Metadata};
+use app_cli_convert_videos::jobs::{default_state_file, submit_jobs, EngineOptions, JobEngine, JobRequest};
+use app_ui_probe_videos::batch::{apply_renames, rename_plan, sheet_path, transfer, Transfer, DEFAULT_RENAME_TEMPLATE};
+use app_ui_probe_videos::catalog::{default_catalog_file, index_file, Catalog, CatalogEntry};
+use app_ui_probe_videos::duplicates::{fill_frame_hashes, find_duplicates, DuplicateGroup, DuplicateKind};
+use app_ui_probe_videos::filmstrip::scrub_index;
+use app_ui_probe_videos::contact_sheet;
+use app_ui_probe_videos::query::{MediaFields, Query};
+use app_ui_probe_videos::sheet::SheetOptions;
 use eframe::egui;
 use egui::TextStyle::Small;
 use egui::{Color32, ComboBox, Context, FontId, RichText, Rounding, ScrollArea, TextStyle, Ui};
 use egui_extras::install_image_loaders;
+use lib_ffmpeg_utils::loudness::AudioFilters;
+use lib_ffmpeg_utils::presets::all_presets;
+use lib_ffmpeg_utils::probe::ProbeResult;
+use lib_ffmpeg_utils::subtitles::subtitle_tracks;
 use lib_egui_utils::{configure_text_styles, my_default_options};
 use open;
 use rfd::FileDialog;
-use std::fs;
+use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::thread::available_parallelism;
+use tokio::sync::Semaphore;
 use tokio::task;
+use ui_extract_audio::extract::{extract_audio, output_path, AudioFormat, AudioSource};
 
 #[derive(Clone, Default)]
 struct VideoFile {
     path: PathBuf,
-    metadata: Metadata,
+    metadata: ProbeResult,
     thumbnail: Option<PathBuf>, // Path to the cached thumbnail
+    filmstrip: Vec<PathBuf>,
+    size: u64,
+    modified: u64,
+    tags: Vec<String>,
+    rating: u8,
+    /// Embedded subtitle tracks and sidecar file, shown as a CC badge
+    subtitles: Vec<String>,
+}
+
+impl VideoFile {
+    fn from_entry(entry: &CatalogEntry) -> Self {
+        let path = PathBuf::from(&entry.path);
+        let metadata = entry.metadata();
+        let mut subtitles: Vec<String> = subtitle_tracks(&metadata).iter().map(|track| track.label()).collect();
+        if entry.sidecar_subtitles.is_some() {
+            subtitles.push("sidecar .srt".to_string());
+        }
+        VideoFile {
+            path,
+            metadata,
+            thumbnail: entry.thumbnail.as_ref().map(PathBuf::from),
+            filmstrip: entry.filmstrip.iter().flatten().map(PathBuf::from).collect(),
+            size: entry.size,
+            modified: entry.modified,
+            tags: entry.tags.clone(),
+            rating: entry.rating,
+            subtitles,
+        }
+    }
+
+    fn fields(&self) -> MediaFields<'_> {
+        MediaFields {
+            file_name: self.path.file_name().and_then(|name| name.to_str()).unwrap_or_default(),
+            metadata: &self.metadata,
+            size: self.size,
+            modified: self.modified,
+            tags: &self.tags,
+            rating: self.rating,
+        }
+    }
+}
+
+/// The videos of the catalog, every change bumps the version so the cached views refresh
+#[derive(Default)]
+struct VideoList {
+    files: Vec<Arc<VideoFile>>,
+    version: u64,
+}
+
+impl Deref for VideoList {
+    type Target = Vec<Arc<VideoFile>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.files
+    }
+}
+
+impl DerefMut for VideoList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.version += 1;
+        &mut self.files
+    }
+}
+
+/// Videos matching the query and tags in use, only worked out again when one of them changes
+#[derive(Default)]
+struct ViewCache {
+    query: String,
+    version: Option<u64>,
+    videos: Arc<Vec<Arc<VideoFile>>>,
+    tags: Vec<String>,
 }
 
 #[derive(Default)]
 struct VideoApp {
     folder_path: String,
-    video_files: Arc<Mutex<Vec<VideoFile>>>,
+    video_files: Arc<Mutex<VideoList>>,
+    view: ViewCache,
+    catalog: Arc<Mutex<Catalog>>,
     load_in_progress: Arc<Mutex<bool>>,
     sort_field: SortField,
     sort_asc: bool,
@@ -32,8 +120,24 @@ struct VideoApp {
     show_filesize: bool,
     show_date: bool,
     list_mode: bool,
-    file_to_delete: Option<String>,
+    show_filmstrip: bool,
+    files_to_delete: Vec<String>,
     show_confirmation: bool,
+    show_duplicates: bool,
+    duplicates: Arc<Mutex<Vec<DuplicateGroup>>>,
+    duplicates_in_progress: Arc<Mutex<bool>>,
+    query: String,
+    collection_name: String,
+    /// Path of the video whose tags are edited, and the tags as typed
+    tag_editor: Option<(String, String)>,
+    /// Videos picked with ctrl or cmd click for the batch actions
+    selected: BTreeSet<PathBuf>,
+    batch_preset: String,
+    audio_format: AudioFormat,
+    rename_template: String,
+    batch_status: Arc<Mutex<Option<String>>>,
+    /// Opened on the first encode, shares the queue of convert-videos
+    engine: Option<JobEngine>,
 }
 
 
@@ -51,9 +155,18 @@ impl VideoApp {
             .unwrap_or_else(|| PathBuf::from("."))
             .join(".video-gallery");
 
+        let catalog_file = default_catalog_file();
+        let catalog = Catalog::load(&catalog_file).unwrap_or_else(|e| {
+            eprintln!("Failed to load the catalog {}: {}", catalog_file.display(), e);
+            Catalog::new(&catalog_file)
+        });
+        let video_files = VideoList { files: catalog.entries().map(|entry| Arc::new(VideoFile::from_entry(entry))).collect(), version: 0 };
+
         let mut app = Self {
             folder_path: String::new(),
-            video_files: Arc::new(Mutex::new(Vec::new())),
+            video_files: Arc::new(Mutex::new(video_files)),
+            view: ViewCache::default(),
+            catalog: Arc::new(Mutex::new(catalog)),
             load_in_progress: Arc::new(Mutex::new(false)),
             sort_field: SortField::default(),
             sort_asc: true,
@@ -62,16 +175,55 @@ impl VideoApp {
             show_date: true,
             config_file,
             list_mode: true,
-            file_to_delete: None,
+            show_filmstrip: false,
+            files_to_delete: Vec::new(),
             show_confirmation :false,
+            show_duplicates: false,
+            duplicates: Arc::new(Mutex::new(Vec::new())),
+            duplicates_in_progress: Arc::new(Mutex::new(false)),
+            query: String::new(),
+            collection_name: String::new(),
+            tag_editor: None,
+            selected: BTreeSet::new(),
+            batch_preset: String::new(),
+            audio_format: AudioFormat::default(),
+            rename_template: DEFAULT_RENAME_TEMPLATE.to_string(),
+            batch_status: Arc::new(Mutex::new(None)),
+            engine: None,
         };
         configure_text_styles(&cc.egui_ctx);
         install_image_loaders(&cc.egui_ctx);
 
         app.load_settings();
+        // the folder of the settings file was the only root before the catalog
+        if app.catalog.lock().unwrap().add_root(&app.folder_path.clone()) {
+            app.save_catalog();
+        }
+        app.sort_videos();
+        app.load_videos();
         app
     }
 
+    fn save_catalog(&self) {
+        save_catalog(&self.catalog);
+    }
+
+    fn add_root(&mut self, folder: &str) {
+        self.folder_path = folder.to_string();
+        self.save_settings();
+        if self.catalog.lock().unwrap().add_root(folder) {
+            self.save_catalog();
+            self.load_videos();
+        }
+    }
+
+    fn remove_root(&mut self, root: &str) {
+        self.catalog.lock().unwrap().remove_root(root);
+        self.save_catalog();
+        let root = Path::new(root);
+        self.video_files.lock().unwrap().retain(|file| !file.path.starts_with(root));
+    }
+
     fn load_settings(&mut self) {
         if let Ok(mut file) = File::open(&self.config_file) {
             let mut contents = String::new();
@@ -94,6 +246,7 @@ impl VideoApp {
                 self.show_date = parts.get(4).unwrap().parse().unwrap();
                 self.show_filesize = parts.get(5).unwrap().parse().unwrap();
                 self.list_mode = parts.get(6).unwrap_or(&"true").parse().unwrap();
+                self.show_filmstrip = parts.get(7).unwrap_or(&"false").parse().unwrap_or(false);
             }
         }
     }
@@ -106,7 +259,7 @@ impl VideoApp {
                 SortField::Name => "Name",
             };
             let order = if self.sort_asc { "asc" } else { "desc" };
-            let settings = format!("{},{},{},{},{},{},{}", self.folder_path, field, order, self.show_filename, self.show_date, self.show_filesize, self.list_mode);
+            let settings = format!("{},{},{},{},{},{},{},{}", self.folder_path, field, order, self.show_filename, self.show_date, self.show_filesize, self.list_mode, self.show_filmstrip);
             let _ = file.write_all(settings.as_bytes());
         }
     }
@@ -119,16 +272,8 @@ impl VideoApp {
                 SortField::Name => {
                     a.path.file_name().unwrap_or_default().cmp(b.path.file_name().unwrap_or_default())
                 }
-                SortField::Date => {
-                    let a_date = fs::metadata(&a.path).and_then(|m| m.modified()).ok();
-                    let b_date = fs::metadata(&b.path).and_then(|m| m.modified()).ok();
-                    a_date.cmp(&b_date) // Option implements Ord
-                }
-                SortField::FileSize => {
-                    let a_size = fs::metadata(&a.path).and_then(|m| Ok(m.len())).unwrap_or(0);
-                    let b_size = fs::metadata(&b.path).and_then(|m| Ok(m.len())).unwrap_or(0);
-                    a_size.cmp(&b_size)
-                }
+                SortField::Date => a.modified.cmp(&b.modified),
+                SortField::FileSize => a.size.cmp(&b.size),
             };
 
             if self.sort_asc {
@@ -140,6 +285,7 @@ impl VideoApp {
     }
 
 
+    /// Rescans the roots in the background, only new and changed files are probed
     fn load_videos(&self) {
         let mut in_progress = self.load_in_progress.lock().unwrap();
         if *in_progress {
@@ -149,73 +295,65 @@ impl VideoApp {
 
         drop(in_progress); // Drop the lock early to avoid blocking
 
-        let folder_path = self.folder_path.clone();
+        let catalog = Arc::clone(&self.catalog);
         let video_files = Arc::clone(&self.video_files);
         let load_in_progress = Arc::clone(&self.load_in_progress);
 
-        // TODO: decide to clear or not before.
-        video_files.lock().unwrap().clear();
-
         tokio::spawn(async move {
-            if let Ok(entries) = fs::read_dir(&folder_path) {
-                let thumbnail_dir = Path::new(&folder_path).join(".thumbnails");
-                fs::create_dir_all(&thumbnail_dir).ok(); // Create thumbnail directory if it doesn't exist
-                let mut tasks = vec![];
-
-                for entry in entries.filter_map(Result::ok) {
-                    let path = entry.path();
-
-                    // Filter valid video files
-                    if path
-                        .extension()
-                        .map_or(false, |ext| matches!(ext.to_str(), Some("mp4" | "mov" | "mpeg")))
-                        && !path.file_name().map_or(false, |name| name.to_str().map_or(false, |s| s.starts_with('.')))
-                    {
-                        let video_files = Arc::clone(&video_files);
-                        let thumbnail_dir = thumbnail_dir.clone();
-
-                        tasks.push(task::spawn(async move {
-                            let metadata = extract_metadata(path.to_string_lossy().to_string());
-
-                            let thumbnail_path = thumbnail_dir.join(
-                                path.file_name()
-                                    .unwrap_or_default()
-                                    .to_string_lossy()
-                                    .to_string()
-                                    + ".png",
-                            );
-
-                            if !thumbnail_path.exists() {
-                                // Generate thumbnail at 00:00:01 timestamp
-                                let timestamp = "00:00:01";
-                                if let Err(err) =
-                                    extract_frame(&path.to_string_lossy(), timestamp, &thumbnail_path.to_string_lossy())
-                                {
-                                    eprintln!("Failed to extract thumbnail: {}", err);
-                                }
-                            }
-
-                            let video_file = VideoFile {
-                                path: path.clone(),
-                                metadata,
-                                thumbnail: if thumbnail_path.exists() {
-                                    Some(thumbnail_path)
-                                } else {
-                                    None
-                                },
-                            };
+            // the walk only needs the stamps, the catalog stays free for the UI meanwhile
+            let stamps = catalog.lock().unwrap().stamps();
+            let changes = task::spawn_blocking(move || stamps.changes()).await.unwrap_or_default();
+
+            let mut removed = Vec::new();
+            if !changes.removed.is_empty() {
+                let mut catalog = catalog.lock().unwrap();
+                removed.extend(changes.removed.iter().filter_map(|path| catalog.remove(path)));
+                video_files.lock().unwrap().retain(|file| catalog.get(&file.path.to_string_lossy()).is_some());
+            }
 
-                            video_files.lock().unwrap().push(video_file);
-                        }));
-                    }
-                }
+            let thumbnail_dir = catalog.lock().unwrap().thumbnail_dir();
+            let removed = Arc::new(removed);
+            // each file runs ffprobe and a dozen ffmpeg, a first scan must not start them all at once
+            let slots = Arc::new(Semaphore::new(available_parallelism().map_or(2, |count| count.get())));
+            let mut tasks = vec![];
+
+            for (root, path) in changes.added.into_iter().chain(changes.changed) {
+                let catalog = Arc::clone(&catalog);
+                let video_files = Arc::clone(&video_files);
+                let thumbnail_dir = thumbnail_dir.clone();
+                let removed = Arc::clone(&removed);
+                let Ok(slot) = Arc::clone(&slots).acquire_owned().await else {
+                    break;
+                };
+
+                tasks.push(task::spawn_blocking(move || {
+                    let _slot = slot;
+                    let entry = match index_file(&root, &path, &thumbnail_dir, &removed) {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            eprintln!("Failed to index {}: {}", path.display(), err);
+                            return;
+                        }
+                    };
 
-                // Wait for all tasks to complete
-                futures::future::join_all(tasks).await;
+                    // catalog before video_files, the order every other place takes them in
+                    let mut catalog = catalog.lock().unwrap();
+                    let mut video_files = video_files.lock().unwrap();
+                    video_files.retain(|file| file.path != path);
+                    video_files.push(Arc::new(VideoFile::from_entry(&entry)));
+                    catalog.insert_indexed(entry);
+                }));
+            }
 
-                // Reset loading flag
-                *load_in_progress.lock().unwrap() = false;
+            // Wait for all tasks to complete
+            let changed = !tasks.is_empty() || !removed.is_empty();
+            futures::future::join_all(tasks).await;
+            if changed {
+                save_catalog(&catalog);
             }
+
+            // Reset loading flag
+            *load_in_progress.lock().unwrap() = false;
         });
     }
 
@@ -228,24 +366,152 @@ impl VideoApp {
             // ui.label(RichText::new("Loading videos...").text_style(Small));
         }
         if self.show_filesize {
-            let file_size = fs::metadata(&video_file.path).map(|m| m.len()).unwrap_or(0);
-            let size_in_gb = file_size as f64 / 1_073_741_824.0; // Convert bytes to GB
+            let size_in_gb = video_file.size as f64 / 1_073_741_824.0; // Convert bytes to GB
             ui.label(RichText::new(format!("{:.2} GB", size_in_gb)).text_style(Small));
         }
         if self.show_date {
-            let modified_date = fs::metadata(&video_file.path)
-                .and_then(|m| m.created())
-                .ok()
-                .map(|t| {
-                    chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M").to_string()
-                })
+            let modified_date = chrono::DateTime::from_timestamp(video_file.modified as i64, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
                 .unwrap_or_else(|| "Unknown".to_string());
             ui.label(RichText::new(format!("{}", modified_date)).text_style(Small));
         }
+
+        ui.horizontal(|ui| {
+            if !video_file.subtitles.is_empty() {
+                ui.label(RichText::new("CC").text_style(Small).strong().background_color(ui.visuals().faint_bg_color))
+                    .on_hover_text(format!("Subtitles:\n{}", video_file.subtitles.join("\n")));
+            }
+            for star in 1..=5u8 {
+                let text = if star <= video_file.rating { "★" } else { "☆" };
+                if ui.add(egui::Label::new(RichText::new(text).text_style(Small)).sense(egui::Sense::click())).clicked() {
+                    // clicking the current rating again clears it
+                    let rating = if star == video_file.rating { 0 } else { star };
+                    self.update_entry(&video_file.path, |catalog, path| catalog.set_rating(path, rating));
+                }
+            }
+            let tags = if video_file.tags.is_empty() { "+ tags".to_string() } else { video_file.tags.join(", ") };
+            if ui.add(egui::Label::new(RichText::new(tags).text_style(Small)).sense(egui::Sense::click()))
+                .on_hover_text("Edit the tags")
+                .clicked()
+            {
+                self.tag_editor = Some((video_file.path.to_string_lossy().to_string(), video_file.tags.join(", ")));
+            }
+        });
+    }
+
+    /// Changes the catalog entry of a video, then saves the catalog and refreshes the video
+    fn update_entry(&self, path: &Path, change: impl FnOnce(&mut Catalog, &str) -> bool) {
+        let path = path.to_string_lossy();
+        {
+            let mut catalog = self.catalog.lock().unwrap();
+            if !change(&mut catalog, &path) {
+                return;
+            }
+            if let Some(entry) = catalog.get(&path) {
+                let mut video_files = self.video_files.lock().unwrap();
+                if let Some(video_file) = video_files.iter_mut().find(|file| file.path == Path::new(entry.path.as_str())) {
+                    let video_file = Arc::make_mut(video_file);
+                    video_file.tags = entry.tags.clone();
+                    video_file.rating = entry.rating;
+                }
+            }
+        }
+        self.save_catalog();
+    }
+
+    fn tag_editor(&mut self, ctx: &Context) {
+        let Some((path, tags)) = &mut self.tag_editor else {
+            return;
+        };
+        let mut close = false;
+        let mut save = false;
+        egui::Window::new("Tags")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(Path::new(path.as_str()).file_name().unwrap_or_default().to_string_lossy());
+                ui.add(egui::TextEdit::singleline(tags).hint_text("trip, family, 2024"));
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    close = ui.button("Cancel").clicked();
+                });
+            });
+        if save {
+            let (path, tags) = self.tag_editor.take().unwrap();
+            let tags: Vec<String> = tags.split(',').map(String::from).collect();
+            self.update_entry(Path::new(&path), |catalog, path| catalog.set_tags(path, &tags));
+        } else if close {
+            self.tag_editor = None;
+        }
+    }
+
+    /// Filters the videos and gathers their tags again when the query or the videos changed
+    fn refresh_view(&mut self) {
+        let video_files = self.video_files.lock().unwrap();
+        if self.view.version == Some(video_files.version) && self.view.query == self.query {
+            return;
+        }
+        // an invalid query shows everything
+        let query = Query::parse(&self.query).unwrap_or_default();
+        let mut tags: Vec<String> = video_files.iter().flat_map(|file| file.tags.iter().cloned()).collect();
+        tags.sort();
+        tags.dedup();
+        self.view = ViewCache {
+            query: self.query.clone(),
+            version: Some(video_files.version),
+            videos: Arc::new(video_files.iter().filter(|file| query.matches(&file.fields())).cloned().collect()),
+            tags,
+        };
+    }
+
+    /// Smart collections and tags, clicking one replaces the query
+    fn collections_sidebar(&mut self, ui: &mut Ui) {
+        let collections = self.catalog.lock().unwrap().collections().to_vec();
+        let tags = self.view.tags.clone();
+
+        ui.heading("Collections");
+        if ui.selectable_label(self.query.trim().is_empty(), "All videos").clicked() {
+            self.query.clear();
+        }
+        for collection in &collections {
+            ui.horizontal(|ui| {
+                if ui.selectable_label(self.query == collection.query, &collection.name).on_hover_text(&collection.query).clicked() {
+                    self.query = collection.query.clone();
+                }
+                if ui.small_button("x").on_hover_text("Remove the collection").clicked() {
+                    self.catalog.lock().unwrap().remove_collection(&collection.name);
+                    self.save_catalog();
+                }
+            });
+        }
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.collection_name).hint_text("Name").desired_width(90.0));
+            let can_save = !self.collection_name.trim().is_empty() && Query::parse(&self.query).is_ok_and(|q| !q.is_empty());
+            if ui.add_enabled(can_save, egui::Button::new("Save")).on_hover_text("Save the query as a collection").clicked() {
+                self.catalog.lock().unwrap().save_collection(self.collection_name.trim(), self.query.trim());
+                self.save_catalog();
+                self.collection_name.clear();
+            }
+        });
+
+        ui.separator();
+        ui.heading("Tags");
+        for tag in tags {
+            let query = format!("tag:{}", tag);
+            if ui.selectable_label(self.query == query, &tag).clicked() {
+                self.query = query;
+            }
+        }
     }
 
     fn video_image(&mut self, video_file: &&VideoFile, thumbnail: &PathBuf, ui: &mut Ui) {
-        let _f = format!("file://{}", thumbnail.to_str().unwrap());
+        // frame under the pointer, as found during the last repaint
+        let scrub_id = ui.id().with(&video_file.path);
+        let scrubbed: Option<usize> = ui.data(|data| data.get_temp(scrub_id));
+        let frame = scrubbed.and_then(|index| video_file.filmstrip.get(index)).unwrap_or(thumbnail);
+        let _f = format!("file://{}", frame.to_str().unwrap());
         let img =
             egui::ImageButton::new(_f.clone())
                 .frame(false)
@@ -255,12 +521,16 @@ impl VideoApp {
 
         if res.secondary_clicked() {
             // trash::delete().unwrap();
-            self.file_to_delete = Some(format!("{}",video_file.path.to_string_lossy().to_string()));
+            self.files_to_delete = vec![format!("{}",video_file.path.to_string_lossy().to_string())];
             self.show_confirmation = true;
         }
 
 
-        if res.clicked() {
+        if res.clicked() && ui.input(|input| input.modifiers.command) {
+            if !self.selected.remove(&video_file.path) {
+                self.selected.insert(video_file.path.clone());
+            }
+        } else if res.clicked() {
             if let Err(err) = open::that(&video_file.path) {
                 eprintln!("Failed to open video: {:?}", err);
             }
@@ -271,47 +541,418 @@ impl VideoApp {
                 color: Color32::from_black_alpha(200),
             });
         };
+        if self.selected.contains(&video_file.path) {
+            ui.painter().rect_stroke(res.rect, 10.0, egui::Stroke { width: 3.0, color: ui.visuals().selection.bg_fill });
+        }
+
+        let frames = video_file.filmstrip.len();
+        match res.hover_pos().filter(|_| frames > 0) {
+            Some(pos) => {
+                let index = scrub_index((pos.x - res.rect.left()) / res.rect.width(), frames);
+                ui.data_mut(|data| data.insert_temp(scrub_id, index));
+                // position of the frame in the video
+                let width = res.rect.width() / frames as f32;
+                let left = res.rect.left() + width * index as f32;
+                let marker = egui::Rect::from_min_max(
+                    egui::pos2(left, res.rect.bottom() - 4.0),
+                    egui::pos2(left + width, res.rect.bottom()),
+                );
+                ui.painter().rect_filled(marker, 2.0, Color32::from_white_alpha(200));
+            }
+            None => ui.data_mut(|data| data.remove::<usize>(scrub_id)),
+        }
+    }
+
+    /// Small frames of the filmstrip in a row
+    fn filmstrip(&self, video_file: &VideoFile, ui: &mut Ui) {
+        for frame in &video_file.filmstrip {
+            ui.add_sized(
+                [80.0, 45.0],
+                egui::Image::new(format!("file://{}", frame.to_string_lossy())).rounding(Rounding::from(4.0)),
+            );
+        }
+    }
+
+    /// Groups the catalog videos by content and by look, in the background
+    fn find_duplicates(&self) {
+        let mut in_progress = self.duplicates_in_progress.lock().unwrap();
+        if *in_progress {
+            return;
+        }
+        *in_progress = true;
+        drop(in_progress);
+
+        let catalog = Arc::clone(&self.catalog);
+        let duplicates = Arc::clone(&self.duplicates);
+        let duplicates_in_progress = Arc::clone(&self.duplicates_in_progress);
+
+        task::spawn_blocking(move || {
+            let mut entries: Vec<CatalogEntry> = catalog.lock().unwrap().entries().cloned().collect();
+            if fill_frame_hashes(&mut entries) {
+                // only the hashes go back, the entries may have been rated or rescanned meanwhile
+                {
+                    let mut catalog = catalog.lock().unwrap();
+                    for entry in entries.iter().filter(|entry| !entry.frame_hashes.is_empty()) {
+                        catalog.fill_frame_hashes(&entry.path, &entry.frame_hashes);
+                    }
+                }
+                save_catalog(&catalog);
+            }
+            *duplicates.lock().unwrap() = find_duplicates(&entries);
+            *duplicates_in_progress.lock().unwrap() = false;
+        });
+    }
+
+    /// Duplicate groups side by side, the first video of each group is the one kept
+    fn duplicates_view(&mut self, ui: &mut Ui) {
+        if *self.duplicates_in_progress.lock().unwrap() {
+            ui.label(RichText::new("Looking for duplicates...").text_style(Small));
+            return;
+        }
+        let groups = self.duplicates.lock().unwrap().clone();
+        if groups.is_empty() {
+            ui.label("No duplicates found");
+            return;
+        }
+        let video_files = self.video_files.lock().unwrap().clone();
+
+        ScrollArea::vertical().show(ui, |ui| {
+            for group in &groups {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(match group.kind {
+                            DuplicateKind::Exact => "Exact copies",
+                            DuplicateKind::Similar => "Similar videos",
+                        });
+                        if ui.button(format!("Trash {} extras", group.extras().len())).clicked() {
+                            self.files_to_delete = group.extras().to_vec();
+                            self.show_confirmation = true;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        for (index, path) in group.paths.iter().enumerate() {
+                            let Some(video_file) = video_files.iter().map(Arc::as_ref).find(|file| file.path == Path::new(path)) else {
+                                continue;
+                            };
+                            ui.vertical(|ui| {
+                                if let Some(thumbnail) = &video_file.thumbnail {
+                                    self.video_image(&video_file, thumbnail, ui);
+                                }
+                                self.layout(&video_file, ui);
+                                ui.label(RichText::new(video_details(video_file)).text_style(Small));
+                                if index == 0 {
+                                    ui.label(RichText::new("Kept").text_style(Small));
+                                } else if ui.small_button("Trash").clicked() {
+                                    self.files_to_delete = vec![path.clone()];
+                                    self.show_confirmation = true;
+                                }
+                            });
+                        }
+                    });
+                });
+            }
+        });
+    }
+
+    /// Catalog entries of the selected videos, in the order of their paths
+    fn selected_entries(&self) -> Vec<CatalogEntry> {
+        let catalog = self.catalog.lock().unwrap();
+        self.selected.iter().filter_map(|path| catalog.get(&path.to_string_lossy()).cloned()).collect()
+    }
+
+    fn set_batch_status(&self, status: impl Into<String>) {
+        *self.batch_status.lock().unwrap() = Some(status.into());
+    }
+
+    /// Actions on the selected videos, shown once at least one is selected
+    fn batch_bar(&mut self, ui: &mut Ui, shown: &[Arc<VideoFile>]) {
+        ui.horizontal_wrapped(|ui| {
+            if self.selected.is_empty() {
+                ui.label(RichText::new("Ctrl or cmd click videos to select them").text_style(Small));
+                if ui.small_button("Select All").on_hover_text("Select the videos shown").clicked() {
+                    self.selected = shown.iter().map(|file| file.path.clone()).collect();
+                }
+            } else {
+                ui.label(format!("{} selected", self.selected.len()));
+                if ui.small_button("Clear").clicked() {
+                    self.selected.clear();
+                }
+                ui.separator();
+
+                let presets = all_presets();
+                if !presets.iter().any(|preset| preset.name == self.batch_preset) {
+                    self.batch_preset = presets.first().map(|preset| preset.name.clone()).unwrap_or_default();
+                }
+                ComboBox::from_id_salt("batch_preset").selected_text(&self.batch_preset).show_ui(ui, |ui| {
+                    for preset in &presets {
+                        ui.selectable_value(&mut self.batch_preset, preset.name.clone(), &preset.name);
+                    }
+                });
+                if ui.button("Encode...").on_hover_text("Add to the convert-videos queue, in a folder of your choice").clicked() {
+                    self.encode_selected();
+                }
+                ui.separator();
+
+                ComboBox::from_id_salt("batch_audio").selected_text(self.audio_format.to_string()).show_ui(ui, |ui| {
+                    for format in AudioFormat::ALL {
+                        ui.selectable_value(&mut self.audio_format, format, format.to_string());
+                    }
+                });
+                if ui.button("Extract Audio").on_hover_text("Save the soundtracks next to the videos").clicked() {
+                    self.extract_selected_audio();
+                }
+                ui.separator();
+
+                if ui.button("Move to...").clicked() {
+                    self.transfer_selected(Transfer::Move);
+                }
+                if ui.button("Copy to...").clicked() {
+                    self.transfer_selected(Transfer::Copy);
+                }
+                ui.separator();
+
+                ui.add(egui::TextEdit::singleline(&mut self.rename_template).desired_width(140.0))
+                    .on_hover_text("{name} {date} {time} {resolution} {n}, the extension is kept");
+                if ui.button("Rename").clicked() {
+                    self.rename_selected();
+                }
+                ui.separator();
+
+                if ui.button("Contact Sheets...").on_hover_text("A grid of frames of each video, in a folder of your choice").clicked() {
+                    self.export_contact_sheets();
+                }
+            }
+
+            if let Some(engine) = &self.engine {
+                if engine.is_running() {
+                    let (done, total) = engine.progress();
+                    ui.label(RichText::new(format!("Encoding {}/{}", done, total)).text_style(Small));
+                    ui.ctx().request_repaint_after(std::time::Duration::from_millis(500));
+                } else {
+                    // done, let the converter open the queue
+                    self.engine = None;
+                }
+            }
+            if let Some(status) = self.batch_status.lock().unwrap().as_ref() {
+                ui.label(RichText::new(status).text_style(Small));
+            }
+        });
+    }
+
+    /// Queues the selected videos with the chosen preset and starts encoding right away,
+    /// or hands them to the converter when it has the queue open
+    fn encode_selected(&mut self) {
+        let Some(preset) = all_presets().into_iter().find(|preset| preset.name == self.batch_preset) else {
+            return;
+        };
+        let Some(folder) = FileDialog::new().pick_folder() else {
+            return;
+        };
+        let state_file = default_state_file();
+        if self.engine.is_none() && JobEngine::in_use(&state_file) {
+            let requests: Vec<JobRequest> = self
+                .selected
+                .iter()
+                .map(|input| JobRequest {
+                    input: input.to_string_lossy().to_string(),
+                    output: preset.output_path(input, &folder).to_string_lossy().to_string(),
+                    preset: preset.clone(),
+                    target_size: None,
+                    edits: Default::default(),
+                })
+                .collect();
+            match submit_jobs(&state_file, &requests) {
+                Ok(()) => self.set_batch_status(format!("Handed {} videos to the open converter", requests.len())),
+                Err(e) => self.set_batch_status(format!("Cannot hand the videos to the converter: {}", e)),
+            }
+            return;
+        }
+        if self.engine.is_none() {
+            match JobEngine::open(&state_file, EngineOptions::default()) {
+                Ok(engine) => self.engine = Some(engine),
+                Err(e) => {
+                    self.set_batch_status(format!("Cannot open the encode queue: {}", e));
+                    return;
+                }
+            }
+        }
+        let engine = self.engine.as_ref().unwrap();
+        let queued = self
+            .selected
+            .iter()
+            .filter_map(|input| {
+                let output = preset.output_path(input, &folder);
+                engine.enqueue(&input.to_string_lossy(), &output.to_string_lossy(), &preset, None)
+            })
+            .count();
+        engine.start();
+        self.set_batch_status(format!("Queued {} videos with {}", queued, preset.name));
+    }
+
+    /// Saves the default audio track of each selected video, tracks come from the catalog
+    fn extract_selected_audio(&self) {
+        let sources: Vec<AudioSource> = {
+            let catalog = self.catalog.lock().unwrap();
+            self.selected
+                .iter()
+                .map(|input| match catalog.get(&input.to_string_lossy()) {
+                    Some(entry) => AudioSource::from_probe(input, &entry.metadata()),
+                    None => AudioSource { path: input.clone(), ..Default::default() },
+                })
+                .collect()
+        };
+        let format = self.audio_format;
+        let status = Arc::clone(&self.batch_status);
+        task::spawn_blocking(move || {
+            let mut failed = 0;
+            for (index, source) in sources.iter().enumerate() {
+                *status.lock().unwrap() = Some(format!("Extracting audio {}/{}", index + 1, sources.len()));
+                let track = source.default_track().map(|track| &source.tracks[track]);
+                let result = match track {
+                    Some(track) => {
+                        let output = output_path(&source.path, track, format, false);
+                        extract_audio(source, track, format, &AudioFilters::default(), &output)
+                    }
+                    None => Err(format!("{} has no audio", source.path.display())),
+                };
+                if let Err(e) = result {
+                    eprintln!("{}", e);
+                    failed += 1;
+                }
+            }
+            *status.lock().unwrap() = Some(format!("Extracted {} soundtracks, {} failed", sources.len() - failed, failed));
+        });
+    }
+
+    /// Moves or copies the selection in the background, the catalog follows moved videos
+    ///
+    /// Copies landing in a root are indexed from the entry of their original, nothing is probed again.
+    fn transfer_selected(&mut self, mode: Transfer) {
+        let Some(folder) = FileDialog::new().pick_folder() else {
+            return;
+        };
+        let entries = self.selected_entries();
+        if mode == Transfer::Move {
+            self.selected.clear();
+        }
+        let catalog = Arc::clone(&self.catalog);
+        let video_files = Arc::clone(&self.video_files);
+        let status = Arc::clone(&self.batch_status);
+        task::spawn_blocking(move || {
+            let mut failed = 0;
+            for (index, entry) in entries.iter().enumerate() {
+                *status.lock().unwrap() = Some(format!("{:?} {}/{}", mode, index + 1, entries.len()));
+                let target = match transfer(Path::new(&entry.path), &folder, mode) {
+                    Ok(target) => target,
+                    Err(e) => {
+                        eprintln!("Failed to transfer {}: {}", entry.path, e);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                let mut catalog = catalog.lock().unwrap();
+                match mode {
+                    Transfer::Move => follow_move(&mut catalog, &mut video_files.lock().unwrap(), &entry.path, &target),
+                    Transfer::Copy => {
+                        let root = catalog.roots().iter().find(|root| target.starts_with(root.as_str())).cloned();
+                        if let Some(root) = root {
+                            match index_file(&root, &target, &catalog.thumbnail_dir(), std::slice::from_ref(entry)) {
+                                Ok(copy) => {
+                                    video_files.lock().unwrap().push(Arc::new(VideoFile::from_entry(&copy)));
+                                    catalog.insert_indexed(copy);
+                                }
+                                Err(e) => eprintln!("Failed to index {}: {}", target.display(), e),
+                            }
+                        }
+                    }
+                }
+            }
+            save_catalog(&catalog);
+            let done = if mode == Transfer::Move { "Moved" } else { "Copied" };
+            *status.lock().unwrap() = Some(format!("{} {} videos, {} failed", done, entries.len() - failed, failed));
+        });
+    }
+
+    fn rename_selected(&mut self) {
+        let entries = self.selected_entries();
+        let plan = match rename_plan(&entries, &self.rename_template) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.set_batch_status(e);
+                return;
+            }
+        };
+        if let Err(e) = apply_renames(&plan) {
+            self.set_batch_status(format!("Rename failed: {}", e));
+            // some files may have moved, the rescan puts the catalog back in line
+            self.load_videos();
+            return;
+        }
+        {
+            let mut catalog = self.catalog.lock().unwrap();
+            let mut video_files = self.video_files.lock().unwrap();
+            for (source, target) in &plan {
+                follow_move(&mut catalog, &mut video_files, &source.to_string_lossy(), target);
+            }
+        }
+        self.save_catalog();
+        self.selected = plan.into_iter().map(|(_, target)| target).collect();
+        self.set_batch_status(format!("Renamed {} videos", self.selected.len()));
+        self.sort_videos();
+    }
+
+    fn export_contact_sheets(&self) {
+        let Some(folder) = FileDialog::new().pick_folder() else {
+            return;
+        };
+        let videos: Vec<PathBuf> = self.selected.iter().cloned().collect();
+        let status = Arc::clone(&self.batch_status);
+        task::spawn_blocking(move || {
+            let mut failed = 0;
+            for (index, video) in videos.iter().enumerate() {
+                *status.lock().unwrap() = Some(format!("Contact sheet {}/{}", index + 1, videos.len()));
+                let output = sheet_path(video, &folder, "png");
+                if let Err(e) = contact_sheet(&video.to_string_lossy(), &output.to_string_lossy(), &SheetOptions::default()) {
+                    eprintln!("{}", e);
+                    failed += 1;
+                }
+            }
+            *status.lock().unwrap() = Some(format!("Saved {} contact sheets, {} failed", videos.len() - failed, failed));
+        });
     }
 
     fn trash_dialog(&mut self, ctx: &Context) {
         if self.show_confirmation {
-            let fil = &self.file_to_delete.clone().unwrap();
-            println!("File to delete: {:?}",fil);
+            let files = self.files_to_delete.clone();
             egui::Window::new("Confirm Trash")
                 .collapsible(false)
                 .resizable(false)
                 .show(ctx, |ui| {
-                    ui.label(format!("Are you sure you want to move\n {:?}\n to the trash?", fil));
+                    match &files[..] {
+                        [file] => ui.label(format!("Are you sure you want to move\n {:?}\n to the trash?", file)),
+                        _ => ui.label(format!("Are you sure you want to move these {} files to the trash?\n {}", files.len(), files.join("\n "))),
+                    };
                     ui.horizontal(|ui| {
                         if ui.button("Yes").clicked() {
-                            if let file = fil {
+                            for file in &files {
                                 if let Err(err) = trash::delete(file) {
                                     eprintln!("Failed to trash file: {}", err);
                                 } else {
                                     println!("File '{}' moved to trash.", file);
-                                    //self.video_files.lock().unwrap().iter().find(|file| file.path) {
-                                    // {
-                                    //     let mut files = &self.video_files.lock().unwrap();
-                                    //     // let mut toremove;
-                                    //     // if let Some(pos) = files.iter().find(|file| file.path.to_str().unwrap() == fil) {
-                                    //     //     // if fs::metadata(fil).is_err() {
-                                    //     //     //     let _ = files.remove(pos);
-                                    //     //     //     println!("Removed: {}", fil);
-                                    //     //     // }
-                                    //     //     toremove = pos;
-                                    //     //     // files.
-                                    //     // }
-                                    //     &self.video_files.get_mut().
-                                    // }
-
+                                    self.catalog.lock().unwrap().remove(file);
+                                    self.video_files.lock().unwrap().retain(|video| video.path != Path::new(file));
+                                    let mut duplicates = self.duplicates.lock().unwrap();
+                                    duplicates.iter_mut().for_each(|group| group.paths.retain(|path| path != file));
+                                    duplicates.retain(|group| group.paths.len() > 1);
                                 }
                             }
+                            self.save_catalog();
                             self.show_confirmation = false;
-                            self.file_to_delete = None;
+                            self.files_to_delete.clear();
                         }
                         if ui.button("No").clicked() {
                             self.show_confirmation = false;
-                            self.file_to_delete = None;
+                            self.files_to_delete.clear();
                         }
                     });
                 });
@@ -322,6 +963,12 @@ impl VideoApp {
 impl eframe::App for VideoApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.trash_dialog(ctx);
+        self.tag_editor(ctx);
+        self.refresh_view();
+
+        egui::SidePanel::left("collections").resizable(true).default_width(160.0).show(ctx, |ui| {
+            ScrollArea::vertical().show(ui, |ui| self.collections_sidebar(ui));
+        });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             // Calculate the number of loaded videos
@@ -336,20 +983,34 @@ impl eframe::App for VideoApp {
 
                 ui.horizontal(|ui| {
                     ui.vertical(|ui| {
-                        ui.label("Folder Path:");
-                        ui.text_edit_singleline(&mut self.folder_path);
+                        ui.label("Folders:");
+                        let roots = self.catalog.lock().unwrap().roots().to_vec();
+                        for root in roots {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("x").on_hover_text("Remove from the catalog").clicked() {
+                                    self.remove_root(&root);
+                                }
+                                ui.label(RichText::new(&root).text_style(Small));
+                            });
+                        }
 
-                        if ui.button("Select Folder").clicked() {
+                        if ui.button("Add Folder").clicked() {
                             if let Some(folder) = FileDialog::new().pick_folder() {
-                                self.folder_path = folder.to_string_lossy().to_string();
-                                self.load_videos(); // Automatically load videos after selecting folder
+                                self.add_root(&folder.to_string_lossy()); // Automatically scans the new folder
                             }
                         }
 
-                        if ui.button("Load Videos").clicked() {
+                        if ui.button("Rescan").on_hover_text("Look for new, changed and deleted videos").clicked() {
                             self.load_videos();
                         }
 
+                        if ui.button(if self.show_duplicates { "Show All Videos" } else { "Find Duplicates" }).clicked() {
+                            self.show_duplicates = !self.show_duplicates;
+                            if self.show_duplicates {
+                                self.find_duplicates();
+                            }
+                        }
+
                         // Show loading message if necessary
                         if *self.load_in_progress.lock().unwrap() {
                             ui.label(RichText::new("Loading videos...").text_style(Small));
@@ -401,14 +1062,41 @@ impl eframe::App for VideoApp {
                         if ui.checkbox(&mut self.list_mode, "List Mode").clicked() {
                             self.save_settings();
                         };
+                        if ui.checkbox(&mut self.show_filmstrip, "Show Filmstrip")
+                            .on_hover_text("Frames of the whole video next to each tile in list mode")
+                            .clicked()
+                        {
+                            self.save_settings();
+                        };
                     });
                 });
             });
 
+            // an invalid query shows everything, with the error next to it
+            let query = Query::parse(&self.query);
+            let video_files = Arc::clone(&self.view.videos);
+            ui.horizontal(|ui| {
+                ui.label("Filter:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("duration>5m res>=1080 codec:hevc size>1GB date>=2024-01-01 tag:trip rating>=4 name")
+                        .desired_width(ui.available_width() - 120.0),
+                );
+                match &query {
+                    Ok(_) => ui.label(RichText::new(format!("{} / {}", video_files.len(), video_count)).text_style(Small)),
+                    Err(e) => ui.colored_label(Color32::RED, e),
+                };
+            });
+            self.batch_bar(ui, &video_files);
+
             ui.separator();
 
+            if self.show_duplicates {
+                self.duplicates_view(ui);
+                return;
+            }
+
             ScrollArea::vertical().show(ui, |ui| {
-                let video_files = self.video_files.lock().unwrap().clone();
 
 
                 ui.set_min_width(ui.available_width());
@@ -423,7 +1111,7 @@ impl eframe::App for VideoApp {
                     //
                     // println!("2.{}",ui.available_width());
 
-                    for (i, video_file) in video_files.iter().enumerate() {
+                    for (i, video_file) in video_files.iter().map(Arc::as_ref).enumerate() {
 
 
 
@@ -433,6 +1121,9 @@ impl eframe::App for VideoApp {
                                         ui.horizontal(|ui| {
                                             self.video_image(&video_file, thumbnail, ui);
                                             self.layout(&video_file, ui);
+                                            if self.show_filmstrip {
+                                                self.filmstrip(video_file, ui);
+                                            }
                                         });
                                     } else {
                                         ui.vertical(|ui| {
@@ -487,6 +1178,41 @@ impl eframe::App for VideoApp {
     }
 }
 
+/// Keeps saves in order, each writes the catalog as it was when it started
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// Saves a copy of the catalog, so the UI does not wait for the disk
+fn save_catalog(catalog: &Mutex<Catalog>) {
+    let _saving = SAVING.lock().unwrap();
+    let snapshot = catalog.lock().unwrap().clone();
+    if let Err(e) = snapshot.save() {
+        eprintln!("Failed to save the catalog: {}", e);
+    }
+}
+
+/// Points the catalog entry and the tile of a moved video to its new path
+fn follow_move(catalog: &mut Catalog, video_files: &mut Vec<Arc<VideoFile>>, from: &str, to: &Path) {
+    catalog.move_entry(from, to);
+    match catalog.get(&to.to_string_lossy()) {
+        Some(entry) => {
+            if let Some(video_file) = video_files.iter_mut().find(|file| file.path == Path::new(from)) {
+                Arc::make_mut(video_file).path = PathBuf::from(&entry.path);
+            }
+        }
+        None => video_files.retain(|file| file.path != Path::new(from)),
+    }
+}
+
+/// Duration, resolution and codec, what tells two encodings apart
+fn video_details(video_file: &VideoFile) -> String {
+    let metadata = &video_file.metadata;
+    let duration = metadata.duration().map(|d| format!("{:.1}s", d.as_secs_f64())).unwrap_or_default();
+    let video = metadata.video_stream();
+    let resolution = video.and_then(|s| s.resolution()).map(|(w, h)| format!("{}x{}", w, h)).unwrap_or_default();
+    let codec = video.and_then(|s| s.codec_name.clone()).unwrap_or_default();
+    format!("{} {} {}", duration, resolution, codec)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), eframe::Error> {
     // let options = eframe::NativeOptions::default();
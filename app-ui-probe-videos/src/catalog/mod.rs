@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use lib_ffmpeg_utils::probe::{parse_probe_output, probe_json, ProbeResult};
+use crate::duplicates::frame_hashes;
+use crate::extract_frame;
+use crate::query::SmartCollection;
+use crate::filmstrip::{detect_scenes, extract_filmstrip, pick_frame_times, FILMSTRIP_FRAMES};
+
+/// Extensions of the files listed in the catalog
+pub const VIDEO_EXTENSIONS: [&str; 3] = ["mp4", "mov", "mpeg"];
+/// Bytes read at each end of a file by [`quick_hash`]
+const QUICK_HASH_CHUNK: u64 = 1024 * 1024;
+/// Where thumbnails are taken, in seconds
+const THUMBNAIL_TIME: &str = "00:00:01";
+
+/// What the catalog knows about a video
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct CatalogEntry {
+    pub path: String,
+    /// Root folder the video was found in
+    pub root: String,
+    pub size: u64,
+    /// Modification time, in seconds since the epoch
+    pub modified: u64,
+    pub hash: String,
+    /// The json printed by ffprobe, null when probing failed
+    #[serde(default)]
+    pub probe: serde_json::Value,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// Frames spread over the video, `None` when the entry predates filmstrips
+    #[serde(default)]
+    pub filmstrip: Option<Vec<String>>,
+    /// Perceptual hashes of the filmstrip frames, to find other encodings of the same clip
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frame_hashes: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Stars given by the user, 0 when not rated
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rating: u8,
+    /// Subtitle file next to the video with the same name, as found when it was indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sidecar_subtitles: Option<String>,
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+impl CatalogEntry {
+    pub fn metadata(&self) -> ProbeResult {
+        if self.probe.is_null() {
+            return ProbeResult::default();
+        }
+        parse_probe_output(&self.probe.to_string()).unwrap_or_default()
+    }
+}
+
+/// Files that appeared, changed or disappeared since the last scan
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    /// Root and path of the new files
+    pub added: Vec<(String, PathBuf)>,
+    pub changed: Vec<(String, PathBuf)>,
+    /// Paths of the entries whose file is gone
+    pub removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Videos of all the root folders, saved as json so the browser opens without probing anything
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct Catalog {
+    roots: Vec<String>,
+    entries: BTreeMap<String, CatalogEntry>,
+    #[serde(default)]
+    collections: Vec<SmartCollection>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Catalog {
+    /// An empty catalog that will be saved to `path`
+    pub fn new(path: &Path) -> Self {
+        Catalog { path: path.to_path_buf(), ..Default::default() }
+    }
+
+    /// Loads the catalog from `path`, a missing file gives an empty catalog
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::new(path));
+        }
+        let mut catalog: Catalog = serde_json::from_str(&fs::read_to_string(path)?)?;
+        catalog.path = path.to_path_buf();
+        Ok(catalog)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// Folder with the thumbnails, named after the hash of the videos
+    pub fn thumbnail_dir(&self) -> PathBuf {
+        self.path.with_extension("thumbnails")
+    }
+
+    pub fn roots(&self) -> &[String] {
+        &self.roots
+    }
+
+    /// Adds a root folder, false when it is already there
+    pub fn add_root(&mut self, root: &str) -> bool {
+        if root.is_empty() || self.roots.iter().any(|r| r == root) {
+            return false;
+        }
+        self.roots.push(root.to_string());
+        true
+    }
+
+    /// Removes a root folder and forgets its videos
+    pub fn remove_root(&mut self, root: &str) {
+        self.roots.retain(|r| r != root);
+        self.entries.retain(|_, entry| entry.root != root);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &CatalogEntry> {
+        self.entries.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&CatalogEntry> {
+        self.entries.get(path)
+    }
+
+    pub fn insert(&mut self, entry: CatalogEntry) {
+        self.entries.insert(entry.path.clone(), entry);
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<CatalogEntry> {
+        self.entries.remove(path)
+    }
+
+    /// Follows a video renamed or moved by the browser, it is forgotten when it leaves the roots
+    pub fn move_entry(&mut self, from: &str, to: &Path) -> bool {
+        let Some(mut entry) = self.entries.remove(from) else {
+            return false;
+        };
+        if let Some(root) = self.roots.iter().find(|root| to.starts_with(root.as_str())) {
+            entry.root = root.clone();
+            entry.path = to.to_string_lossy().to_string();
+            self.insert(entry);
+        }
+        true
+    }
+
+    /// Stores a freshly indexed video, keeping the tags and rating of the entry it replaces
+    pub fn insert_indexed(&mut self, mut entry: CatalogEntry) {
+        if let Some(previous) = self.entries.get(&entry.path) {
+            if entry.tags.is_empty() && entry.rating == 0 {
+                entry.tags = previous.tags.clone();
+                entry.rating = previous.rating;
+            }
+        }
+        self.insert(entry);
+    }
+
+    /// Replaces the tags of a video, they are trimmed, deduplicated and sorted
+    pub fn set_tags(&mut self, path: &str, tags: &[String]) -> bool {
+        let Some(entry) = self.entries.get_mut(path) else {
+            return false;
+        };
+        let mut tags: Vec<String> = tags.iter().map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect();
+        tags.sort();
+        tags.dedup();
+        entry.tags = tags;
+        true
+    }
+
+    /// Sets the stars of a video, from 0 to 5
+    pub fn set_rating(&mut self, path: &str, rating: u8) -> bool {
+        match self.entries.get_mut(path) {
+            Some(entry) => {
+                entry.rating = rating.min(5);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stores the frame hashes worked out for an entry that had none, false when it is gone or already has some
+    pub fn fill_frame_hashes(&mut self, path: &str, hashes: &[u64]) -> bool {
+        match self.entries.get_mut(path) {
+            Some(entry) if entry.frame_hashes.is_empty() => {
+                entry.frame_hashes = hashes.to_vec();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Every tag in use, sorted
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.entries.values().flat_map(|entry| entry.tags.iter().cloned()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    pub fn collections(&self) -> &[SmartCollection] {
+        &self.collections
+    }
+
+    /// Saves a query, replacing the collection with the same name
+    pub fn save_collection(&mut self, name: &str, query: &str) {
+        let collection = SmartCollection { name: name.to_string(), query: query.to_string() };
+        match self.collections.iter_mut().find(|c| c.name == name) {
+            Some(existing) => *existing = collection,
+            None => self.collections.push(collection),
+        }
+    }
+
+    pub fn remove_collection(&mut self, name: &str) {
+        self.collections.retain(|c| c.name != name);
+    }
+
+    /// What a scan compares the files with, quick to take so the walk does not hold the catalog
+    pub fn stamps(&self) -> CatalogStamps {
+        CatalogStamps {
+            roots: self.roots.clone(),
+            entries: self
+                .entries
+                .values()
+                .map(|entry| {
+                    let stamp = Stamp {
+                        root: entry.root.clone(),
+                        size: entry.size,
+                        modified: entry.modified,
+                        has_filmstrip: entry.filmstrip.is_some(),
+                    };
+                    (entry.path.clone(), stamp)
+                })
+                .collect(),
+        }
+    }
+
+    /// Walks the roots, see [`CatalogStamps::changes`]
+    pub fn changes(&self) -> Changes {
+        self.stamps().changes()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Stamp {
+    root: String,
+    size: u64,
+    modified: u64,
+    has_filmstrip: bool,
+}
+
+/// Roots of the catalog with the size and modification time of its entries
+#[derive(Debug, Clone, Default)]
+pub struct CatalogStamps {
+    roots: Vec<String>,
+    entries: BTreeMap<String, Stamp>,
+}
+
+impl CatalogStamps {
+    /// Walks the roots and compares sizes and modification times with the catalog
+    ///
+    /// Files of a root that cannot be read are kept, an unplugged drive does not empty the catalog.
+    /// Entries without a filmstrip count as changed so they get one.
+    pub fn changes(&self) -> Changes {
+        let mut changes = Changes::default();
+        let mut seen = Vec::new();
+        for root in &self.roots {
+            if !Path::new(root).is_dir() {
+                seen.extend(self.entries.iter().filter(|(_, stamp)| &stamp.root == root).map(|(path, _)| path.clone()));
+                continue;
+            }
+            for path in scan_root(Path::new(root)) {
+                let key = path.to_string_lossy().to_string();
+                match (self.entries.get(&key), file_stamp(&path)) {
+                    (None, _) => changes.added.push((root.clone(), path)),
+                    (Some(entry), Ok(stamp)) if stamp != (entry.size, entry.modified) || !entry.has_filmstrip => {
+                        changes.changed.push((root.clone(), path))
+                    }
+                    _ => {}
+                }
+                seen.push(key);
+            }
+        }
+        seen.sort();
+        changes.removed = self.entries.keys().filter(|path| seen.binary_search(path).is_err()).cloned().collect();
+        changes
+    }
+}
+
+/// Catalog file of the video browser
+pub fn default_catalog_file() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rusty-toolbox")
+        .join("probe-videos")
+        .join("catalog.json")
+}
+
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Videos under `root`, hidden files and folders like `.thumbnails` are skipped
+pub fn scan_root(root: &Path) -> Vec<PathBuf> {
+    let mut videos = Vec::new();
+    let Ok(entries) = fs::read_dir(root) else {
+        return videos;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            videos.extend(scan_root(&path));
+        } else if is_video(&path) {
+            videos.push(path);
+        }
+    }
+    videos
+}
+
+/// Size and modification time of a file
+pub fn file_stamp(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    Ok((metadata.len(), modified))
+}
+
+/// SHA-256 of the size and of the first and last megabyte, enough to recognize a moved video
+pub fn quick_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+
+    let mut buffer = Vec::new();
+    (&mut file).take(QUICK_HASH_CHUNK).read_to_end(&mut buffer)?;
+    if size > 2 * QUICK_HASH_CHUNK {
+        file.seek(SeekFrom::Start(size - QUICK_HASH_CHUNK))?;
+        file.take(QUICK_HASH_CHUNK).read_to_end(&mut buffer)?;
+    } else if size > QUICK_HASH_CHUNK {
+        file.read_to_end(&mut buffer)?;
+    }
+    hasher.update(&buffer);
+    Ok(hasher.finalize()[..16].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Probes, hashes and takes the thumbnail and filmstrip of a video
+///
+/// A video of `removed` with the same hash was moved, its probe is reused instead of running ffprobe.
+/// Frames are named after the hash, so a moved video also keeps its pictures.
+pub fn index_file(root: &str, path: &Path, thumbnail_dir: &Path, removed: &[CatalogEntry]) -> io::Result<CatalogEntry> {
+    let (size, modified) = file_stamp(path)?;
+    let hash = quick_hash(path)?;
+
+    let moved = removed.iter().find(|entry| entry.hash == hash && !entry.probe.is_null());
+    let probe = match moved {
+        Some(moved) => moved.probe.clone(),
+        None => probe_json(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_else(|| {
+                eprintln!("Failed to probe {}", path.display());
+                serde_json::Value::Null
+            }),
+    };
+
+    fs::create_dir_all(thumbnail_dir)?;
+    let filmstrip = match moved.and_then(|moved| moved.filmstrip.clone()) {
+        Some(filmstrip) => filmstrip,
+        None => {
+            let duration = parse_probe_output(&probe.to_string())
+                .ok()
+                .and_then(|metadata| metadata.duration())
+                .map(|duration| duration.as_secs_f64())
+                .unwrap_or_default();
+            let scenes = detect_scenes(path).unwrap_or_else(|err| {
+                eprintln!("Failed to detect the scenes of {}: {}", path.display(), err);
+                Vec::new()
+            });
+            let times = pick_frame_times(duration, FILMSTRIP_FRAMES, &scenes);
+            extract_filmstrip(path, &times, thumbnail_dir, &hash)
+                .iter()
+                .map(|frame| frame.to_string_lossy().to_string())
+                .collect()
+        }
+    };
+
+    // the first frame of the filmstrip skips the fade-in, the fixed time is for videos without one
+    let thumbnail = match filmstrip.first() {
+        Some(frame) => PathBuf::from(frame),
+        None => thumbnail_dir.join(format!("{}.png", hash)),
+    };
+    if !thumbnail.exists() {
+        if let Err(err) = extract_frame(&path.to_string_lossy(), THUMBNAIL_TIME, &thumbnail.to_string_lossy()) {
+            eprintln!("Failed to extract thumbnail: {}", err);
+        }
+    }
+
+    Ok(CatalogEntry {
+        path: path.to_string_lossy().to_string(),
+        root: root.to_string(),
+        size,
+        modified,
+        hash,
+        probe,
+        thumbnail: thumbnail.exists().then(|| thumbnail.to_string_lossy().to_string()),
+        frame_hashes: moved.map(|moved| moved.frame_hashes.clone()).unwrap_or_else(|| frame_hashes(&filmstrip)),
+        filmstrip: Some(filmstrip),
+        tags: moved.map(|moved| moved.tags.clone()).unwrap_or_default(),
+        rating: moved.map(|moved| moved.rating).unwrap_or_default(),
+        // only the plain name is checked, listing the folder for every video would be slow
+        sidecar_subtitles: Some(path.with_extension("srt")).filter(|srt| srt.exists()).map(|srt| srt.to_string_lossy().to_string()),
+    })
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use image::imageops::FilterType;
+use sha2::{Digest, Sha256};
+use crate::catalog::CatalogEntry;
+
+/// Durations closer than this, in seconds, can be the same clip
+const MIN_DURATION_TOLERANCE: f64 = 1.0;
+/// Or closer than this share of the duration, re-encodes often gain or lose a few frames
+const DURATION_TOLERANCE_RATIO: f64 = 0.01;
+/// Average number of differing bits, out of 64, below which frames look the same
+const MAX_FRAME_DISTANCE: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// Same content, byte for byte
+    Exact,
+    /// Same duration and frames that look alike, usually another encoding
+    Similar,
+}
+
+/// Videos that are copies of each other, the first one is the one to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    pub paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    /// Everything but the video to keep
+    pub fn extras(&self) -> &[String] {
+        &self.paths[1..]
+    }
+}
+
+/// Exact duplicates first, then the similar ones among the remaining videos
+pub fn find_duplicates(entries: &[CatalogEntry]) -> Vec<DuplicateGroup> {
+    let mut groups = find_exact(entries);
+    // one copy of each exact group takes part in the similarity search
+    let extras: Vec<String> = groups.iter().flat_map(|group| group.extras()).cloned().collect();
+    let remaining: Vec<CatalogEntry> = entries.iter().filter(|entry| !extras.contains(&entry.path)).cloned().collect();
+    groups.extend(find_similar(&remaining));
+    groups
+}
+
+/// Videos with the same size and quick hash, confirmed with a hash of the whole file
+pub fn find_exact(entries: &[CatalogEntry]) -> Vec<DuplicateGroup> {
+    let mut candidates: BTreeMap<(u64, &str), Vec<&CatalogEntry>> = BTreeMap::new();
+    for entry in entries {
+        candidates.entry((entry.size, &entry.hash)).or_default().push(entry);
+    }
+
+    let mut groups = Vec::new();
+    for candidates in candidates.into_values().filter(|candidates| candidates.len() > 1) {
+        let mut by_hash: BTreeMap<String, Vec<&CatalogEntry>> = BTreeMap::new();
+        for entry in candidates {
+            match full_hash(Path::new(&entry.path)) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(entry),
+                Err(err) => eprintln!("Failed to hash {}: {}", entry.path, err),
+            }
+        }
+        groups.extend(by_hash.into_values().filter(|same| same.len() > 1).map(|same| group(DuplicateKind::Exact, same)));
+    }
+    groups
+}
+
+/// Videos of about the same duration whose filmstrip frames look alike
+pub fn find_similar(entries: &[CatalogEntry]) -> Vec<DuplicateGroup> {
+    let mut videos: Vec<(f64, &CatalogEntry)> = entries
+        .iter()
+        .filter(|entry| !entry.frame_hashes.is_empty())
+        .filter_map(|entry| Some((entry.metadata().duration()?.as_secs_f64(), entry)))
+        .collect();
+    videos.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // union find over the pairs that match, videos are sorted so only close durations are compared
+    let mut parents: Vec<usize> = (0..videos.len()).collect();
+    for i in 0..videos.len() {
+        let (duration, entry) = videos[i];
+        let tolerance = MIN_DURATION_TOLERANCE.max(duration * DURATION_TOLERANCE_RATIO);
+        for (j, &(other_duration, other)) in videos.iter().enumerate().skip(i + 1) {
+            if other_duration - duration > tolerance {
+                break;
+            }
+            if frames_distance(&entry.frame_hashes, &other.frame_hashes) <= MAX_FRAME_DISTANCE {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[b] = a;
+            }
+        }
+    }
+
+    let mut similar: BTreeMap<usize, Vec<&CatalogEntry>> = BTreeMap::new();
+    for (i, (_, entry)) in videos.iter().enumerate() {
+        similar.entry(root(&mut parents, i)).or_default().push(entry);
+    }
+    similar.into_values().filter(|same| same.len() > 1).map(|same| group(DuplicateKind::Similar, same)).collect()
+}
+
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Biggest file first, it is usually the best quality
+fn group(kind: DuplicateKind, mut entries: Vec<&CatalogEntry>) -> DuplicateGroup {
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    DuplicateGroup { kind, paths: entries.iter().map(|entry| entry.path.clone()).collect() }
+}
+
+/// SHA-256 of the whole file
+pub fn full_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Difference hash, one bit per pair of neighbour pixels of a 9x8 grayscale copy
+pub fn dhash(image: &image::DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Computes the frame hashes missing from entries indexed before they existed, true when any changed
+pub fn fill_frame_hashes(entries: &mut [CatalogEntry]) -> bool {
+    let mut changed = false;
+    for entry in entries.iter_mut().filter(|entry| entry.frame_hashes.is_empty()) {
+        if let Some(filmstrip) = entry.filmstrip.as_ref().filter(|filmstrip| !filmstrip.is_empty()) {
+            entry.frame_hashes = frame_hashes(filmstrip);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Difference hashes of the frames that can be read
+pub fn frame_hashes(frames: &[String]) -> Vec<u64> {
+    frames.iter().filter_map(|frame| image::open(frame).ok()).map(|image| dhash(&image)).collect()
+}
+
+/// Average distance from each frame of `a` to the closest frame of `b`
+///
+/// Frames are not compared one to one, scene detection can pick other times in another encoding.
+pub fn frames_distance(a: &[u64], b: &[u64]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return f64::MAX;
+    }
+    let total: u32 = a.iter().map(|x| b.iter().map(|y| (x ^ y).count_ones()).min().unwrap_or(64)).sum();
+    total as f64 / a.len() as f64
+}
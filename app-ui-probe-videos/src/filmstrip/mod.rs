@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use regex::Regex;
+use lib_ffmpeg_utils::utils::path_for;
+
+/// Frames in the filmstrip of a video
+pub const FILMSTRIP_FRAMES: usize = 8;
+/// Scene score above which a frame counts as a cut, between 0 and 1
+const SCENE_THRESHOLD: f64 = 0.3;
+/// Mean luma below which a frame counts as black, between 0 and 1
+const DARK_LUMA: f32 = 0.08;
+/// Times a dark frame is retried further on before it is kept anyway
+const DARK_RETRIES: usize = 3;
+/// Seconds a dark frame is nudged by when there is no next frame to stay before
+const DARK_NUDGE: f64 = 1.0;
+
+/// Times of the scene changes, from the showinfo lines of the scene detection pass
+pub fn parse_scene_times(stderr: &str) -> Vec<f64> {
+    let re = Regex::new(r"Parsed_showinfo.*pts_time:\s*([0-9.]+)").unwrap();
+    let mut times: Vec<f64> = re.captures_iter(stderr).filter_map(|caps| caps[1].parse().ok()).collect();
+    times.sort_by(|a, b| a.total_cmp(b));
+    times
+}
+
+/// Scene changes of a video, keyframes only so long videos stay quick to scan
+pub fn detect_scenes(video: &Path) -> Result<Vec<f64>, String> {
+    let output = Command::new(path_for("ffmpeg"))
+        .args(["-hide_banner", "-skip_frame", "nokey", "-i"])
+        .arg(video)
+        .args([
+            "-an",
+            "-vf",
+            &format!("scale=160:-2,select='gt(scene,{})',showinfo", SCENE_THRESHOLD),
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("FFmpeg error: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(parse_scene_times(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// `count` times spread over `duration`, each moved to the first scene change of its slot
+///
+/// Frames right after a cut are rarely black, unlike the fade-in at the start of most videos.
+pub fn pick_frame_times(duration: f64, count: usize, scenes: &[f64]) -> Vec<f64> {
+    if duration <= 0.0 || count == 0 {
+        return Vec::new();
+    }
+    let slot = duration / count as f64;
+    (0..count)
+        .map(|i| {
+            let (start, end) = (i as f64 * slot, (i + 1) as f64 * slot);
+            scenes
+                .iter()
+                .copied()
+                .find(|&time| time >= start && time < end)
+                .unwrap_or(start + slot / 2.0)
+        })
+        .collect()
+}
+
+/// Mean luma of an image, between 0 and 1, `None` when it can't be read
+pub fn mean_luma(image: &Path) -> Option<f32> {
+    let pixels = image::open(image).ok()?.to_luma8();
+    if pixels.is_empty() {
+        return None;
+    }
+    let total: u64 = pixels.iter().map(|&luma| luma as u64).sum();
+    Some(total as f32 / pixels.len() as f32 / 255.0)
+}
+
+/// Extracts a 200 pixels high frame at each of `times`, named `<prefix>-<index>.png` in `dir`
+///
+/// Dark frames, such as fades, are retried a little later, staying before the next time.
+/// Frames that fail are left out, the filmstrip is shorter rather than missing.
+pub fn extract_filmstrip(video: &Path, times: &[f64], dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let mut frames = Vec::new();
+    for (index, &time) in times.iter().enumerate() {
+        let frame = dir.join(format!("{}-{}.png", prefix, index));
+        if !frame.exists() && extract_frame(video, time, &frame) {
+            let nudge = times.get(index + 1).map_or(DARK_NUDGE, |next| (next - time) / (DARK_RETRIES + 1) as f64);
+            let retry = dir.join(format!("{}-{}.retry.png", prefix, index));
+            let mut luma = mean_luma(&frame).unwrap_or(1.0);
+            for attempt in 1..=DARK_RETRIES {
+                if luma >= DARK_LUMA || !extract_frame(video, time + nudge * attempt as f64, &retry) {
+                    break;
+                }
+                let retry_luma = mean_luma(&retry).unwrap_or(0.0);
+                if retry_luma > luma && fs::rename(&retry, &frame).is_ok() {
+                    luma = retry_luma;
+                }
+            }
+            let _ = fs::remove_file(&retry);
+        }
+        if frame.exists() {
+            frames.push(frame);
+        }
+    }
+    frames
+}
+
+/// Extracts the 200 pixels high frame at `time` into `frame`, false when FFmpeg fails
+fn extract_frame(video: &Path, time: f64, frame: &Path) -> bool {
+    let output = Command::new(path_for("ffmpeg"))
+        .args(["-hide_banner", "-y", "-ss", &format!("{:.3}", time), "-i"])
+        .arg(video)
+        .args(["-frames:v", "1", "-vf", "scale=-1:200"])
+        .arg(frame)
+        .output();
+    match output {
+        Ok(result) if result.status.success() => frame.exists(),
+        Ok(result) => {
+            eprintln!("FFmpeg error: {}", String::from_utf8_lossy(&result.stderr));
+            false
+        }
+        Err(e) => {
+            eprintln!("Failed to execute FFmpeg: {}", e);
+            false
+        }
+    }
+}
+
+/// Frame shown when the pointer is at `fraction` of the width of a tile, from 0 to 1
+pub fn scrub_index(fraction: f32, frames: usize) -> usize {
+    ((fraction.clamp(0.0, 1.0) * frames as f32) as usize).min(frames.saturating_sub(1))
+}
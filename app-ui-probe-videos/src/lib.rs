@@ -1,59 +1,19 @@
+pub mod batch;
+pub mod catalog;
+pub mod duplicates;
+pub mod filmstrip;
+pub mod query;
+pub mod sheet;
+
+use std::error::Error;
 use std::path::Path;
 use std::process::Command;
-use serde::Deserialize;
-use serde_json::from_str;
-
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct Format {
-    pub filename: String,
-    nb_streams: u32,
-    nb_programs: u32,
-    nb_stream_groups: u32,
-    format_name: String,
-    format_long_name: String,
-    start_time: String,
-    duration: String,
-    size: String,
-    bit_rate: String,
-    probe_score: u32,
-    tags: Tags,
-}
-
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct Tags {
-    major_brand: String,
-    minor_version: String,
-    compatible_brands: String,
-    encoder: Option<String>,
-}
-
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct Metadata {
-    pub format: Format,
-}
-
-
-pub fn extract_metadata(video_path: String) -> Metadata {
-    // Run the ffprobe command
-
-    let output = Command::new("/opt/homebrew/bin/ffprobe")
-        .arg("-v")
-        .arg("quiet")
-        .arg("-print_format")
-        .arg("json")
-        .arg("-show_format")
-        // .arg("-show_streams")
-        .arg(video_path)
-        .output()
-        .expect("Failed to execute ffprobe");
+use lib_ffmpeg_utils::probe::{probe, ProbeResult};
+use crate::filmstrip::{extract_filmstrip, pick_frame_times};
+use crate::sheet::{header_lines, render_sheet, SheetOptions};
 
-    // Parse and print the JSON output
-    let metadata_string = String::from_utf8_lossy(&output.stdout);
-    println!("Extracted metadata:\n{}", metadata_string);
-
-    // Parse JSON into the Metadata struct
-    let metadata: Metadata = from_str(metadata_string.as_ref()).expect("Failed to parse JSON");
-    metadata
+pub fn extract_metadata(video_path: String) -> Result<ProbeResult, Box<dyn Error>> {
+    probe(&video_path)
 }
 
 pub fn extract_frame(video_path: &str, timestamp: &str, output_image: &str) -> Result<(), String> {
@@ -102,3 +62,37 @@ pub fn extract_frame(video_path: &str, timestamp: &str, output_image: &str) -> R
         Err(e) => Err(format!("Failed to execute FFmpeg: {}", e)),
     }
 }
+
+/// Renders a grid of timestamped frames under a header with the name, duration, resolution, codecs and size
+///
+/// The image format follows the extension of `output_image`, PNG or JPEG.
+pub fn contact_sheet(video_path: &str, output_image: &str, options: &SheetOptions) -> Result<(), String> {
+    let video = Path::new(video_path);
+    if !video.exists() {
+        return Err(format!("Video file '{}' does not exist.", video_path));
+    }
+    let metadata = probe(video_path).map_err(|e| format!("Failed to probe {}: {}", video_path, e))?;
+    let duration = metadata.duration().ok_or_else(|| format!("Unknown duration for {}", video_path))?;
+    let size = video.metadata().map(|m| m.len()).unwrap_or_default();
+
+    let dir = tempfile::tempdir().map_err(|e| format!("Failed to create a temporary folder: {}", e))?;
+    let mut frames = Vec::new();
+    for (index, time) in pick_frame_times(duration.as_secs_f64(), options.frame_count(), &[]).into_iter().enumerate() {
+        // one call per frame, so a missing one does not shift the times of the others
+        for frame in extract_filmstrip(video, &[time], dir.path(), &format!("sheet-{}", index)) {
+            match image::open(&frame) {
+                Ok(image) => frames.push((image, time)),
+                Err(e) => eprintln!("Failed to read {}: {}", frame.display(), e),
+            }
+        }
+    }
+    if frames.is_empty() {
+        return Err(format!("No frame could be extracted from {}", video_path));
+    }
+
+    let file_name = video.file_name().unwrap_or_default().to_string_lossy();
+    let sheet = render_sheet(&header_lines(&file_name, &metadata, size), &frames, options);
+    sheet.save(output_image).map_err(|e| format!("Failed to save {}: {}", output_image, e))?;
+    println!("Contact sheet saved to {}", output_image);
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use lib_ffmpeg_utils::edit::parse_timestamp;
+use lib_ffmpeg_utils::encode::parse_size;
+use lib_ffmpeg_utils::probe::ProbeResult;
+
+/// A query saved under a name, shown in the sidebar
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SmartCollection {
+    pub name: String,
+    pub query: String,
+}
+
+/// What a query looks at for one video
+pub struct MediaFields<'a> {
+    pub file_name: &'a str,
+    pub metadata: &'a ProbeResult,
+    pub size: u64,
+    /// Modification time, in seconds since the epoch
+    pub modified: u64,
+    pub tags: &'a [String],
+    pub rating: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(&self, value: T, wanted: T) -> bool {
+        match self {
+            Op::Less => value < wanted,
+            Op::LessOrEqual => value <= wanted,
+            Op::Equal => value == wanted,
+            Op::GreaterOrEqual => value >= wanted,
+            Op::Greater => value > wanted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Seconds
+    Duration(Op, f64),
+    Width(Op, u32),
+    Height(Op, u32),
+    Codec(String),
+    Date(Op, NaiveDate),
+    /// Bytes
+    Size(Op, u64),
+    Tag(String),
+    Rating(Op, u8),
+    /// Part of the file name
+    Name(String),
+}
+
+/// Conditions that must all match, like `duration>5m res>=1080 codec:hevc tag:trip holiday`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    pub conditions: Vec<Condition>,
+}
+
+impl Query {
+    /// Parses the words of a query, anything that is not `field<op>value` searches the file name
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let conditions = query.split_whitespace().map(parse_condition).collect::<Result<_, _>>()?;
+        Ok(Query { conditions })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    pub fn matches(&self, fields: &MediaFields) -> bool {
+        self.conditions.iter().all(|condition| condition_matches(condition, fields))
+    }
+}
+
+fn condition_matches(condition: &Condition, fields: &MediaFields) -> bool {
+    let video = fields.metadata.video_stream();
+    match condition {
+        Condition::Duration(op, seconds) => {
+            fields.metadata.duration().is_some_and(|duration| op.compare(duration.as_secs_f64(), *seconds))
+        }
+        Condition::Width(op, width) => video.and_then(|s| s.resolution()).is_some_and(|(w, _)| op.compare(w, *width)),
+        Condition::Height(op, height) => video.and_then(|s| s.resolution()).is_some_and(|(_, h)| op.compare(h, *height)),
+        Condition::Codec(codec) => fields
+            .metadata
+            .streams
+            .iter()
+            .any(|stream| stream.codec_name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(codec))),
+        Condition::Date(op, date) => DateTime::from_timestamp(fields.modified as i64, 0)
+            .is_some_and(|time| op.compare(time.with_timezone(&Local).date_naive(), *date)),
+        Condition::Size(op, size) => op.compare(fields.size, *size),
+        Condition::Tag(tag) => fields.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+        Condition::Rating(op, rating) => op.compare(fields.rating, *rating),
+        Condition::Name(text) => fields.file_name.to_lowercase().contains(text),
+    }
+}
+
+fn parse_condition(word: &str) -> Result<Condition, String> {
+    let Some(split) = word.find(['<', '>', '=', ':']) else {
+        return Ok(Condition::Name(word.to_lowercase()));
+    };
+    let field = word[..split].to_ascii_lowercase();
+    let rest = &word[split..];
+    let (op, value) = [(">=", Op::GreaterOrEqual), ("<=", Op::LessOrEqual), (">", Op::Greater), ("<", Op::Less), ("=", Op::Equal), (":", Op::Equal)]
+        .iter()
+        .find(|(symbol, _)| rest.starts_with(symbol))
+        .map(|(symbol, op)| (*op, &rest[symbol.len()..]))
+        .unwrap();
+    let invalid = || format!("Invalid value {} for {}", value, field);
+
+    Ok(match field.as_str() {
+        "duration" | "length" => Condition::Duration(op, parse_duration(value).ok_or_else(invalid)?),
+        "width" => Condition::Width(op, value.parse().map_err(|_| invalid())?),
+        "height" | "res" => Condition::Height(op, value.trim_end_matches('p').parse().map_err(|_| invalid())?),
+        "codec" => Condition::Codec(value.to_string()),
+        "date" => Condition::Date(op, NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| invalid())?),
+        "size" => Condition::Size(op, parse_size(value).ok_or_else(invalid)?),
+        "tag" => Condition::Tag(value.to_string()),
+        "rating" | "stars" => Condition::Rating(op, value.parse().ok().filter(|r| *r <= 5).ok_or_else(invalid)?),
+        _ => return Err(format!("Unknown field {}, use duration, width, res, codec, date, size, tag or rating", field)),
+    })
+}
+
+/// Seconds from "90", "1:30", "90s", "5m" or "2h"
+fn parse_duration(value: &str) -> Option<f64> {
+    let multiplier = match value.chars().last()? {
+        's' => 1.0,
+        'm' => 60.0,
+        'h' => 3600.0,
+        _ => return parse_timestamp(value),
+    };
+    Some(parse_timestamp(&value[..value.len() - 1])? * multiplier)
+}
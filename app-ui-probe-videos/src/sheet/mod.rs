@@ -0,0 +1,149 @@
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgb, RgbImage};
+use lib_ffmpeg_utils::edit::format_timestamp;
+use lib_ffmpeg_utils::probe::ProbeResult;
+
+/// Space around the sheet and between the frames, in pixels
+const MARGIN: u32 = 16;
+const GAP: u32 = 8;
+const TITLE_SIZE: f32 = 26.0;
+const INFO_SIZE: f32 = 18.0;
+const TIME_SIZE: f32 = 15.0;
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 24]);
+const TEXT: Rgb<u8> = Rgb([235, 235, 235]);
+
+/// Layout of a contact sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SheetOptions {
+    pub columns: u32,
+    pub rows: u32,
+    /// Width of each frame, in pixels
+    pub frame_width: u32,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self { columns: 4, rows: 4, frame_width: 320 }
+    }
+}
+
+impl SheetOptions {
+    pub fn frame_count(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+}
+
+/// File name, then duration, resolution, codecs and size on one line
+pub fn header_lines(file_name: &str, metadata: &ProbeResult, size: u64) -> Vec<String> {
+    let mut details = Vec::new();
+    if let Some(duration) = metadata.duration() {
+        details.push(format_timestamp(duration.as_secs_f64().round()));
+    }
+    let video = metadata.video_stream();
+    if let Some((width, height)) = video.and_then(|stream| stream.resolution()) {
+        details.push(format!("{}x{}", width, height));
+    }
+    let codecs: Vec<&str> = video
+        .into_iter()
+        .chain(metadata.audio_streams().take(1))
+        .filter_map(|stream| stream.codec_name.as_deref())
+        .collect();
+    if !codecs.is_empty() {
+        details.push(codecs.join(" / "));
+    }
+    details.push(format_size(size));
+    vec![file_name.to_string(), details.join("  ·  ")]
+}
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.2} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b => format!("{} KB", b.div_ceil(1024)),
+    }
+}
+
+/// Draws the header and the frames, each with its time in the bottom right corner
+///
+/// Frames are scaled to the width of the options, with the aspect ratio of the first one.
+pub fn render_sheet(header: &[String], frames: &[(DynamicImage, f64)], options: &SheetOptions) -> RgbImage {
+    let font = default_font();
+    let columns = options.columns.max(1);
+    let frame_width = options.frame_width.max(16);
+    let frame_height = frames
+        .first()
+        .map(|(frame, _)| (frame_width as f64 * frame.height() as f64 / frame.width().max(1) as f64).round() as u32)
+        .unwrap_or(frame_width * 9 / 16)
+        .max(1);
+    let rows = (frames.len() as u32).div_ceil(columns);
+
+    let header_height = MARGIN + (TITLE_SIZE * 1.3) as u32 + (header.len().saturating_sub(1) as f32 * INFO_SIZE * 1.4) as u32;
+    let width = 2 * MARGIN + columns * frame_width + (columns - 1) * GAP;
+    let height = header_height + MARGIN + rows * frame_height + rows.saturating_sub(1) * GAP + MARGIN;
+    let mut sheet = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    let mut y = MARGIN as f32;
+    for (index, line) in header.iter().enumerate() {
+        let size = if index == 0 { TITLE_SIZE } else { INFO_SIZE };
+        draw_text(&mut sheet, &font, line, MARGIN as f32, y, size, TEXT);
+        y += size * if index == 0 { 1.3 } else { 1.4 };
+    }
+
+    for (index, (frame, time)) in frames.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        let x = MARGIN + column * (frame_width + GAP);
+        let y = header_height + MARGIN + row * (frame_height + GAP);
+        let tile = frame.resize_exact(frame_width, frame_height, FilterType::Triangle).into_rgb8();
+        imageops::replace(&mut sheet, &tile, x as i64, y as i64);
+
+        let label = format_timestamp(time.floor());
+        let label_width = text_width(&font, &label, TIME_SIZE).ceil() as u32 + 8;
+        let label_height = (TIME_SIZE * 1.3) as u32;
+        if label_width < frame_width && label_height < frame_height {
+            let (label_x, label_y) = (x + frame_width - label_width, y + frame_height - label_height);
+            let shade = RgbImage::from_pixel(label_width, label_height, Rgb([0, 0, 0]));
+            imageops::replace(&mut sheet, &shade, label_x as i64, label_y as i64);
+            draw_text(&mut sheet, &font, &label, (label_x + 4) as f32, label_y as f32 + 1.0, TIME_SIZE, TEXT);
+        }
+    }
+    sheet
+}
+
+/// The proportional font egui ships with, so no font file has to be found on the machine
+fn default_font() -> FontArc {
+    let data = egui::FontDefinitions::default().font_data.remove("Ubuntu-Light").expect("egui has no default font");
+    FontArc::try_from_vec(data.font.into_owned()).expect("Invalid default font")
+}
+
+pub fn text_width(font: &FontArc, text: &str, size: f32) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    text.chars().map(|c| scaled.h_advance(scaled.glyph_id(c))).sum()
+}
+
+/// Blends `text` over the image, `y` is the top of the line
+fn draw_text(image: &mut RgbImage, font: &FontArc, text: &str, x: f32, y: f32, size: f32, color: Rgb<u8>) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut caret = point(x, y + scaled.ascent());
+    for c in text.chars() {
+        let glyph = scaled.scaled_glyph(c);
+        let advance = scaled.h_advance(glyph.id);
+        let Some(outline) = font.outline_glyph(ab_glyph::Glyph { position: caret, ..glyph }) else {
+            caret.x += advance;
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let (px, py) = (bounds.min.x as i64 + gx as i64, bounds.min.y as i64 + gy as i64);
+            if px < 0 || py < 0 || px >= image.width() as i64 || py >= image.height() as i64 {
+                return;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            for channel in 0..3 {
+                let blended = pixel[channel] as f32 * (1.0 - coverage) + color[channel] as f32 * coverage;
+                pixel[channel] = blended.round() as u8;
+            }
+        });
+        caret.x += advance;
+    }
+}
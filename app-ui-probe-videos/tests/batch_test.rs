@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use app_ui_probe_videos::batch::{apply_renames, render_name, rename_plan, transfer, Transfer};
+    use app_ui_probe_videos::catalog::{Catalog, CatalogEntry};
+
+    fn entry(path: &str) -> CatalogEntry {
+        CatalogEntry {
+            path: path.to_string(),
+            // 2024-06-15 12:00 UTC
+            modified: 1_718_452_800,
+            probe: serde_json::json!({"streams": [{"index": 0, "codec_type": "video", "width": 1920, "height": 1080}]}),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn names_from_template() {
+        let video = entry("/videos/Beach Day.MOV");
+        assert_eq!(render_name("{n} - {name} {resolution}", &video, 3), "3 - Beach Day 1080p.MOV");
+        let date = chrono::DateTime::from_timestamp(1_718_452_800, 0).unwrap().with_timezone(&chrono::Local);
+        assert_eq!(render_name("{date} {name}", &video, 1), format!("{} Beach Day.MOV", date.format("%Y-%m-%d")));
+
+        // missing values and separators do not leak into the name
+        let unknown = CatalogEntry { probe: serde_json::Value::Null, ..entry("/videos/clip.mp4") };
+        assert_eq!(render_name("{name} {resolution} a/b", &unknown, 1), "clip a-b.mp4");
+    }
+
+    #[test]
+    fn renames_without_clobbering() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        for name in ["a.mp4", "b.mp4", "taken.mp4"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let a = entry(&dir.join("a.mp4").to_string_lossy());
+        let b = entry(&dir.join("b.mp4").to_string_lossy());
+
+        assert_eq!(rename_plan(&[a.clone(), b.clone()], "{resolution}").unwrap_err(), "Several videos would be named 1080p.mp4");
+        assert!(rename_plan(std::slice::from_ref(&a), "taken").unwrap_err().ends_with("taken.mp4 already exists"));
+
+        // the two videos swap their names
+        let plan = rename_plan(&[a, b], "{n}").unwrap();
+        let swap = vec![(plan[0].0.clone(), dir.join("b.mp4")), (plan[1].0.clone(), dir.join("a.mp4"))];
+        apply_renames(&swap).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.mp4")).unwrap(), "b.mp4");
+        assert_eq!(fs::read_to_string(dir.join("b.mp4")).unwrap(), "a.mp4");
+
+        // a rename that fails undoes the ones already done
+        let broken = vec![(dir.join("a.mp4"), dir.join("b.mp4")), (dir.join("b.mp4"), dir.join("missing").join("a.mp4"))];
+        assert!(apply_renames(&broken).is_err());
+        assert_eq!(fs::read_to_string(dir.join("a.mp4")).unwrap(), "b.mp4");
+        assert_eq!(fs::read_to_string(dir.join("b.mp4")).unwrap(), "a.mp4");
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn transfers_keep_existing_files_and_the_catalog_follows() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let (root, outside) = (dir.join("root"), dir.join("outside"));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("a.mp4"), "a").unwrap();
+
+        let copy = transfer(&root.join("a.mp4"), &outside, Transfer::Copy).unwrap();
+        assert_eq!(copy, outside.join("a.mp4"));
+        assert!(root.join("a.mp4").exists());
+        assert!(transfer(&root.join("a.mp4"), &outside, Transfer::Move).is_err());
+
+        let moved = transfer(&root.join("a.mp4"), &root.join("sub"), Transfer::Move).unwrap();
+        assert!(!root.join("a.mp4").exists());
+
+        let mut catalog = Catalog::new(&dir.join("catalog.json"));
+        catalog.add_root(&root.to_string_lossy());
+        let original = root.join("a.mp4").to_string_lossy().to_string();
+        catalog.insert(CatalogEntry { rating: 4, ..entry(&original) });
+        assert!(catalog.move_entry(&original, &moved));
+        assert_eq!(catalog.get(&moved.to_string_lossy()).unwrap().rating, 4);
+
+        assert!(catalog.move_entry(&moved.to_string_lossy(), &copy));
+        assert!(catalog.is_empty());
+    }
+}
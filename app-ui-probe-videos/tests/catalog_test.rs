@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use app_ui_probe_videos::catalog::{file_stamp, quick_hash, scan_root, Catalog, CatalogEntry};
+
+    fn entry(root: &Path, path: &Path) -> CatalogEntry {
+        let (size, modified) = file_stamp(path).unwrap();
+        CatalogEntry {
+            path: path.to_string_lossy().to_string(),
+            root: root.to_string_lossy().to_string(),
+            size,
+            modified,
+            hash: quick_hash(path).unwrap(),
+            probe: serde_json::json!({"format": {"duration": "12.5"}}),
+            thumbnail: None,
+            filmstrip: Some(Vec::new()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn scan_skips_hidden_and_other_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("trip/.thumbnails")).unwrap();
+        for file in ["a.mp4", "trip/b.MOV", "trip/.thumbnails/a.mp4", ".c.mp4", "notes.txt"] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        let mut found = scan_root(root);
+        found.sort();
+        assert_eq!(found, vec![root.join("a.mp4"), root.join("trip/b.MOV")]);
+    }
+
+    #[test]
+    fn changes_since_the_last_scan() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let (kept, edited, deleted) = (root.join("kept.mp4"), root.join("edited.mp4"), root.join("deleted.mp4"));
+        for file in [&kept, &edited, &deleted] {
+            fs::write(file, "video").unwrap();
+        }
+
+        let mut catalog = Catalog::new(&root.join("catalog.json"));
+        assert!(catalog.add_root(&root.to_string_lossy()));
+        assert!(!catalog.add_root(&root.to_string_lossy()));
+        for file in [&kept, &edited, &deleted] {
+            catalog.insert(entry(root, file));
+        }
+        assert!(catalog.changes().is_empty());
+        // entries indexed before filmstrips existed get one on the next scan
+        catalog.insert(CatalogEntry { filmstrip: None, ..entry(root, &kept) });
+        assert_eq!(catalog.changes().changed, vec![(root.to_string_lossy().to_string(), kept.clone())]);
+        catalog.insert(entry(root, &kept));
+
+        fs::write(&edited, "a longer video").unwrap();
+        fs::remove_file(&deleted).unwrap();
+        fs::write(root.join("new.mp4"), "video").unwrap();
+        let changes = catalog.changes();
+        assert_eq!(changes.added, vec![(root.to_string_lossy().to_string(), root.join("new.mp4"))]);
+        assert_eq!(changes.changed, vec![(root.to_string_lossy().to_string(), edited.clone())]);
+        assert_eq!(changes.removed, vec![deleted.to_string_lossy().to_string()]);
+        // same content, so a move of deleted.mp4 to new.mp4 is recognized
+        assert_eq!(catalog.get(&changes.removed[0]).unwrap().hash, quick_hash(&root.join("new.mp4")).unwrap());
+    }
+
+    #[test]
+    fn catalog_round_trip_and_missing_roots() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let video = root.join("a.mp4");
+        fs::write(&video, "video").unwrap();
+        let path = root.join("catalog.json");
+
+        let mut catalog = Catalog::new(&path);
+        catalog.add_root(&root.to_string_lossy());
+        catalog.add_root("/unplugged/drive");
+        catalog.insert(entry(root, &video));
+        catalog.insert(CatalogEntry { path: "/unplugged/drive/b.mp4".to_string(), root: "/unplugged/drive".to_string(), ..entry(root, &video) });
+        catalog.save().unwrap();
+
+        let mut loaded = Catalog::load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        let metadata = loaded.get(&video.to_string_lossy()).unwrap().metadata();
+        assert_eq!(metadata.duration().unwrap().as_secs_f64(), 12.5);
+        // videos of a root that cannot be read are kept
+        assert!(loaded.changes().is_empty());
+
+        loaded.remove_root("/unplugged/drive");
+        assert_eq!((loaded.roots().len(), loaded.len()), (1, 1));
+    }
+
+    #[test]
+    fn tags_ratings_and_collections() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let video = root.join("a.mp4");
+        fs::write(&video, "video").unwrap();
+        let key = video.to_string_lossy().to_string();
+
+        let mut catalog = Catalog::new(&root.join("catalog.json"));
+        catalog.insert(entry(root, &video));
+        assert!(catalog.set_tags(&key, &[" trip".to_string(), "family".to_string(), "trip".to_string(), "".to_string()]));
+        assert!(catalog.set_rating(&key, 9));
+        assert!(!catalog.set_rating("/missing.mp4", 3));
+        assert_eq!(catalog.tags(), vec!["family", "trip"]);
+
+        // indexing the file again keeps what the user entered
+        catalog.insert_indexed(entry(root, &video));
+        assert_eq!(catalog.get(&key).unwrap().rating, 5);
+        assert_eq!(catalog.get(&key).unwrap().tags, vec!["family", "trip"]);
+
+        // hashes worked out in the background do not undo what changed meanwhile
+        assert!(catalog.fill_frame_hashes(&key, &[1, 2]));
+        assert!(!catalog.fill_frame_hashes(&key, &[3]));
+        assert_eq!((catalog.get(&key).unwrap().frame_hashes.clone(), catalog.get(&key).unwrap().rating), (vec![1, 2], 5));
+
+        catalog.save_collection("Long", "duration>10m");
+        catalog.save_collection("Long", "duration>20m");
+        catalog.save_collection("Favorites", "rating>=4");
+        catalog.save().unwrap();
+        let mut loaded = Catalog::load(&root.join("catalog.json")).unwrap();
+        assert_eq!(loaded.collections().len(), 2);
+        assert_eq!(loaded.collections()[0].query, "duration>20m");
+        loaded.remove_collection("Long");
+        assert_eq!(loaded.collections()[0].name, "Favorites");
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use app_ui_probe_videos::catalog::CatalogEntry;
+    use app_ui_probe_videos::duplicates::{dhash, find_duplicates, frames_distance, DuplicateKind};
+    use image::{DynamicImage, GrayImage, Luma};
+
+    fn entry(path: &str, size: u64, hash: &str, duration: f64, frame_hashes: Vec<u64>) -> CatalogEntry {
+        CatalogEntry {
+            path: path.to_string(),
+            root: "/videos".to_string(),
+            size,
+            modified: 0,
+            hash: hash.to_string(),
+            probe: serde_json::json!({"format": {"duration": duration.to_string()}}),
+            thumbnail: None,
+            filmstrip: Some(Vec::new()),
+            frame_hashes,
+            ..Default::default()
+        }
+    }
+
+    fn gradient(width: u32, height: u32, invert: bool) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, _| {
+            let value = (x * 255 / width) as u8;
+            Luma([if invert { 255 - value } else { value }])
+        }))
+    }
+
+    #[test]
+    fn perceptual_hash_survives_resizing() {
+        let original = dhash(&gradient(320, 180, false));
+        assert!((original ^ dhash(&gradient(160, 90, false))).count_ones() <= 4);
+        assert!((original ^ dhash(&gradient(320, 180, true))).count_ones() >= 60);
+        assert_eq!(frames_distance(&[1, 2], &[]), f64::MAX);
+        // frames are matched with the closest one, whatever their order
+        assert_eq!(frames_distance(&[0b1111, 0], &[0, 0b0111]), 0.5);
+    }
+
+    #[test]
+    fn exact_copies_are_confirmed_with_the_full_hash() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        for (name, content) in [("a.mp4", "same"), ("copy of a.mp4", "same"), ("b.mp4", "diff")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        // b.mp4 shares the quick hash, as if only its middle was different
+        let entries = vec![
+            entry(&path("a.mp4"), 4, "q1", 10.0, vec![]),
+            entry(&path("copy of a.mp4"), 4, "q1", 10.0, vec![]),
+            entry(&path("b.mp4"), 4, "q1", 10.0, vec![]),
+        ];
+        let groups = find_duplicates(&entries);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::Exact);
+        assert_eq!(groups[0].paths, vec![path("a.mp4"), path("copy of a.mp4")]);
+    }
+
+    #[test]
+    fn similar_videos_need_close_durations_and_frames() {
+        let frames = vec![0xF0F0_F0F0_F0F0_F0F0, 0x0FF0_0FF0_0FF0_0FF0];
+        let reencoded = vec![0xF0F0_F0F0_F0F0_F0F1, 0x0FF0_0FF0_0FF0_0FF0];
+        let entries = vec![
+            entry("/videos/small.mp4", 100, "h1", 600.0, reencoded.clone()),
+            entry("/videos/original.mov", 900, "h2", 603.0, frames.clone()),
+            entry("/videos/other.mov", 900, "h3", 600.0, vec![0, 0]),
+            entry("/videos/longer.mov", 900, "h4", 700.0, frames),
+        ];
+        let groups = find_duplicates(&entries);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, DuplicateKind::Similar);
+        // the biggest file is kept
+        assert_eq!(groups[0].paths, vec!["/videos/original.mov", "/videos/small.mp4"]);
+        assert_eq!(groups[0].extras(), &["/videos/small.mp4".to_string()]);
+    }
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use app_ui_probe_videos::filmstrip::{mean_luma, parse_scene_times, pick_frame_times, scrub_index};
+
+    #[test]
+    fn scene_times_from_showinfo() {
+        let stderr = "\
+[Parsed_showinfo_2 @ 0x6000] n:   0 pts:  90000 pts_time:3.75    duration:512 fmt:yuv420p
+[Parsed_showinfo_2 @ 0x6000] n:   1 pts: 421888 pts_time:16.48   duration:512 fmt:yuv420p
+frame=    2 fps=0.0 q=-0.0 Lsize=N/A time=00:00:16.48
+";
+        assert_eq!(parse_scene_times(stderr), vec![3.75, 16.48]);
+        assert!(parse_scene_times("nothing").is_empty());
+    }
+
+    #[test]
+    fn frames_snap_to_scene_changes() {
+        // slots of 10s, scene changes in the first and third ones
+        assert_eq!(pick_frame_times(40.0, 4, &[3.75, 26.0, 27.0]), vec![3.75, 15.0, 26.0, 35.0]);
+        assert!(pick_frame_times(0.0, 4, &[]).is_empty());
+    }
+
+    #[test]
+    fn luma_of_dark_and_light_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let (dark, light) = (dir.path().join("dark.png"), dir.path().join("light.png"));
+        image::GrayImage::from_pixel(4, 4, image::Luma([10])).save(&dark).unwrap();
+        image::GrayImage::from_pixel(4, 4, image::Luma([255])).save(&light).unwrap();
+        assert!(mean_luma(&dark).unwrap() < 0.05);
+        assert_eq!(mean_luma(&light), Some(1.0));
+        assert_eq!(mean_luma(&dir.path().join("missing.png")), None);
+    }
+
+    #[test]
+    fn scrubbing() {
+        assert_eq!(scrub_index(0.0, 8), 0);
+        assert_eq!(scrub_index(0.5, 8), 4);
+        assert_eq!(scrub_index(1.0, 8), 7);
+        assert_eq!(scrub_index(0.3, 0), 0);
+    }
+}
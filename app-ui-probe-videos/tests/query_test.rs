@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use app_ui_probe_videos::query::{Condition, MediaFields, Op, Query};
+    use lib_ffmpeg_utils::probe::{parse_probe_output, ProbeResult};
+
+    fn metadata() -> ProbeResult {
+        parse_probe_output(
+            r#"{"format": {"duration": "372.5"},
+                "streams": [{"index": 0, "codec_type": "video", "codec_name": "hevc", "width": 1920, "height": 1080},
+                            {"index": 1, "codec_type": "audio", "codec_name": "aac"}]}"#,
+        )
+        .unwrap()
+    }
+
+    fn matches(query: &str) -> bool {
+        let metadata = metadata();
+        let tags = vec!["Trip".to_string()];
+        let fields = MediaFields {
+            file_name: "Beach Day.mov",
+            metadata: &metadata,
+            size: 1_500_000_000,
+            // 2024-06-15 12:00 UTC
+            modified: 1_718_452_800,
+            tags: &tags,
+            rating: 4,
+        };
+        Query::parse(query).unwrap().matches(&fields)
+    }
+
+    #[test]
+    fn parse_conditions() {
+        let query = Query::parse("duration>=5m res:1080p beach").unwrap();
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition::Duration(Op::GreaterOrEqual, 300.0),
+                Condition::Height(Op::Equal, 1080),
+                Condition::Name("beach".to_string()),
+            ]
+        );
+        assert!(Query::parse("  ").unwrap().is_empty());
+        assert_eq!(Query::parse("colour:red").unwrap_err(), "Unknown field colour, use duration, width, res, codec, date, size, tag or rating");
+        assert!(Query::parse("rating>9").is_err());
+        assert!(Query::parse("date>yesterday").is_err());
+    }
+
+    #[test]
+    fn metadata_conditions() {
+        assert!(matches("duration>6m duration<1:00:00 width>=1920 res>720 codec:HEVC codec:aac"));
+        assert!(!matches("duration<90s"));
+        assert!(!matches("codec:h264"));
+        assert!(matches("size>1GB size<2g"));
+        assert!(matches("date>=2024-06-01 date<2024-07-01"));
+        assert!(!matches("date>2024-06-20"));
+    }
+
+    #[test]
+    fn user_conditions_and_names() {
+        assert!(matches("tag:trip rating>=4 beach day"));
+        assert!(!matches("tag:work"));
+        assert!(!matches("rating=5"));
+        assert!(!matches("mountain"));
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use app_ui_probe_videos::sheet::{format_size, header_lines, render_sheet, SheetOptions};
+    use image::{DynamicImage, Rgb, RgbImage};
+    use lib_ffmpeg_utils::probe::parse_probe_output;
+
+    #[test]
+    fn header_shows_the_details() {
+        let metadata = parse_probe_output(
+            r#"{"format": {"duration": "3725.4"},
+                "streams": [{"index": 0, "codec_type": "video", "codec_name": "hevc", "width": 3840, "height": 2160},
+                            {"index": 1, "codec_type": "audio", "codec_name": "aac"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            header_lines("trip.mov", &metadata, 1_610_612_736),
+            vec!["trip.mov".to_string(), "1:02:05  ·  3840x2160  ·  hevc / aac  ·  1.50 GB".to_string()]
+        );
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(100), "1 KB");
+    }
+
+    #[test]
+    fn frames_fill_a_grid_under_the_header() {
+        let frame = DynamicImage::ImageRgb8(RgbImage::from_pixel(160, 90, Rgb([200, 0, 0])));
+        let frames: Vec<_> = (0..5).map(|i| (frame.clone(), i as f64 * 10.0)).collect();
+        let options = SheetOptions { columns: 3, rows: 2, frame_width: 320 };
+        let sheet = render_sheet(&["trip.mov".to_string(), "0:50".to_string()], &frames, &options);
+
+        // 3 columns of 320 pixels, 2 rows of 180 pixels
+        assert_eq!(sheet.width(), 16 * 2 + 3 * 320 + 2 * 8);
+        let bottom = sheet.height() - 16 - 1;
+        assert_eq!(sheet.get_pixel(16 + 10, bottom)[0], 200);
+        assert_eq!(sheet.get_pixel(16 + 10, bottom - 180 - 4)[0], 24, "gap between the rows");
+        // the sixth cell stays empty
+        assert_eq!(sheet.get_pixel(sheet.width() - 16 - 10, bottom), &Rgb([24, 24, 24]));
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::process::Command;
+use crate::presets::EncodePreset;
+use crate::utils::path_for;
+
+/// Encoders and muxers the installed ffmpeg was built with
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    pub video_encoders: HashSet<String>,
+    pub audio_encoders: HashSet<String>,
+    pub muxers: HashSet<String>,
+}
+
+impl Capabilities {
+    /// Asks the installed ffmpeg for its encoders and muxers
+    pub fn detect() -> Result<Self, Box<dyn Error>> {
+        let encoders = run_ffmpeg_list("-encoders")?;
+        let muxers = run_ffmpeg_list("-muxers")?;
+        Ok(Self::parse(&encoders, &muxers))
+    }
+
+    /// Builds the capabilities from the output of `ffmpeg -encoders` and `ffmpeg -muxers`
+    pub fn parse(encoders: &str, muxers: &str) -> Self {
+        let mut capabilities = Capabilities::default();
+
+        for (flags, name) in list_entries(encoders, "------") {
+            match flags.chars().next() {
+                Some('V') => capabilities.video_encoders.insert(name),
+                Some('A') => capabilities.audio_encoders.insert(name),
+                _ => false,
+            };
+        }
+        for (flags, names) in list_entries(muxers, "--") {
+            if flags.contains('E') {
+                capabilities.muxers.extend(names.split(',').map(String::from));
+            }
+        }
+        capabilities
+    }
+
+    /// Everything in `preset` this ffmpeg cannot do, empty when the preset is usable
+    pub fn check_preset(&self, preset: &EncodePreset) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(codec) = &preset.video_codec {
+            if !self.video_encoders.contains(codec) {
+                problems.push(format!("ffmpeg has no {} video encoder", codec));
+            }
+        }
+        if let Some(codec) = &preset.audio_codec {
+            if !self.audio_encoders.contains(codec) {
+                problems.push(format!("ffmpeg has no {} audio encoder", codec));
+            }
+        }
+        let muxer = muxer_for_container(&preset.container);
+        if !self.muxers.contains(muxer) {
+            problems.push(format!("ffmpeg cannot write {} files", preset.container));
+        }
+        problems
+    }
+}
+
+/// Name of the ffmpeg muxer used for a file extension
+pub fn muxer_for_container(container: &str) -> &str {
+    match container {
+        "mkv" => "matroska",
+        "m4a" | "m4v" => "ipod",
+        "mpeg" | "mpg" => "mpeg",
+        "ts" => "mpegts",
+        "aac" => "adts",
+        "opus" => "opus",
+        "ogg" | "oga" => "ogg",
+        other => other,
+    }
+}
+
+fn run_ffmpeg_list(flag: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(path_for("ffmpeg")).args(["-hide_banner", flag]).output()?;
+    if !output.status.success() {
+        return Err(format!("ffmpeg {} failed ({})", flag, output.status).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Flags and name of each entry printed after the `separator` line
+fn list_entries(output: &str, separator: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .skip_while(|line| line.trim() != separator)
+        .skip(1)
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            Some((words.next()?.to_string(), words.next()?.to_string()))
+        })
+        .collect()
+}
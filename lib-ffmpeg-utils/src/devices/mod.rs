@@ -1,4 +1,6 @@
+#[cfg(target_os = "macos")]
 use std::process::Command;
+#[cfg(target_os = "macos")]
 use crate::utils::ffmpeg_binary;
 
 pub struct DeviceLister {
@@ -82,6 +84,27 @@ impl DeviceLister {
 }
 
 // TODO: impl for windows and linux
+#[cfg(not(target_os = "macos"))]
+impl DeviceLister {
+    /// No device listing yet on this platform, returns empty lists
+    pub fn new() -> Self {
+        Self {
+            audio_devices: Vec::new(),
+            video_devices: Vec::new(),
+        }
+    }
+
+    /// Returns the list of audio devices
+    pub fn get_audio_devices(&self) -> Vec<String> {
+        self.audio_devices.clone()
+    }
+
+    /// Returns the list of video devices
+    pub fn get_video_devices(&self) -> Vec<String> {
+        self.video_devices.clone()
+    }
+}
+
 // Retrieves the list of available audio inputs via FFmpeg.
 // pub fn get_audio_inputs(&self) -> io::Result<Vec<String>> {
 //     // Execute FFmpeg to list devices
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::presets::EncodePreset;
+use crate::probe::probe;
+use crate::subtitles::{burn_filter, sidecar_language, sidecar_subtitles, subtitle_codec};
+use crate::utils::path_for;
+
+/// Points of the video sampled by [`detect_crop`], as a share of the duration
+const CROPDETECT_SAMPLES: [f64; 5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+/// Frames analysed at each sample point
+const CROPDETECT_FRAMES: u32 = 30;
+
+/// Quick fixes applied to a video while it is encoded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct EditInstructions {
+    /// Seconds cut from the beginning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<f64>,
+    /// Time where the video stops, in seconds of the untrimmed video
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<f64>,
+    #[serde(default, skip_serializing_if = "Rotation::is_none")]
+    pub rotate: Rotation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop: Option<Crop>,
+    /// Clips appended after the video, they must share its resolution and codecs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub append: Vec<String>,
+    /// Adds the `.srt` files named after the video as subtitle tracks
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub sidecar_subtitles: bool,
+    /// Subtitle track drawn into the picture, counted among the subtitle streams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_subtitles: Option<usize>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise,
+    UpsideDown,
+    CounterClockwise,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::None, Rotation::Clockwise, Rotation::UpsideDown, Rotation::CounterClockwise];
+
+    pub fn is_none(&self) -> bool {
+        *self == Rotation::None
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Rotation::None => "None",
+            Rotation::Clockwise => "90° clockwise",
+            Rotation::UpsideDown => "180°",
+            Rotation::CounterClockwise => "90° counter-clockwise",
+        }
+    }
+
+    fn filter(&self) -> Option<&'static str> {
+        match self {
+            Rotation::None => None,
+            Rotation::Clockwise => Some("transpose=clock"),
+            Rotation::UpsideDown => Some("hflip,vflip"),
+            Rotation::CounterClockwise => Some("transpose=cclock"),
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    /// Accepts "cw", "ccw", "180" and the angles 90, 270, -90
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "none" | "0" => Ok(Rotation::None),
+            "cw" | "90" | "clockwise" => Ok(Rotation::Clockwise),
+            "180" | "upside_down" => Ok(Rotation::UpsideDown),
+            "ccw" | "270" | "-90" | "counter_clockwise" => Ok(Rotation::CounterClockwise),
+            other => Err(format!("Unknown rotation {}, use cw, ccw or 180", other)),
+        }
+    }
+}
+
+/// Area kept by the crop filter, in pixels of the source
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Crop {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl fmt::Display for Crop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    /// Parses "width:height:x:y", the format printed by cropdetect
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let numbers: Vec<u32> = value
+            .trim()
+            .trim_start_matches("crop=")
+            .split(':')
+            .map(|n| n.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid crop {}, expected width:height:x:y", value))?;
+        match numbers[..] {
+            [width, height, x, y] if width > 0 && height > 0 => Ok(Crop { width, height, x, y }),
+            _ => Err(format!("Invalid crop {}, expected width:height:x:y", value)),
+        }
+    }
+}
+
+impl EditInstructions {
+    pub fn is_empty(&self) -> bool {
+        *self == EditInstructions::default()
+    }
+
+    /// One line description, like "trim 0:05-1:10, rotate 90° clockwise"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.start.is_some() || self.end.is_some() {
+            let start = format_timestamp(self.start.unwrap_or(0.0));
+            let end = self.end.map(format_timestamp).unwrap_or_default();
+            parts.push(format!("trim {}-{}", start, end));
+        }
+        if let Some(crop) = &self.crop {
+            parts.push(format!("crop {}", crop));
+        }
+        if !self.rotate.is_none() {
+            parts.push(format!("rotate {}", self.rotate.label()));
+        }
+        if !self.append.is_empty() {
+            parts.push(format!("+{} clips", self.append.len()));
+        }
+        if self.sidecar_subtitles {
+            parts.push("subtitles from .srt files".to_string());
+        }
+        if let Some(track) = self.burn_subtitles {
+            parts.push(format!("burn subtitles #{}", track));
+        }
+        parts.join(", ")
+    }
+
+    /// The video followed by the appended clips
+    pub fn clips(&self, input: &Path) -> Vec<PathBuf> {
+        let mut clips = vec![input.to_path_buf()];
+        clips.extend(self.append.iter().map(PathBuf::from));
+        clips
+    }
+
+    /// Crop then rotate, the crop is given in the orientation of the source
+    pub fn filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if let Some(crop) = &self.crop {
+            filters.push(format!("crop={}", crop));
+        }
+        if let Some(filter) = self.rotate.filter() {
+            filters.push(filter.to_string());
+        }
+        filters
+    }
+
+    /// [`Self::filters`] followed by the burned subtitles, drawn upright on the final picture
+    pub fn video_filters(&self, input: &Path) -> Vec<String> {
+        let mut filters = self.filters();
+        if let Some(track) = self.burn_subtitles {
+            filters.push(burn_filter(input, track, self.start));
+        }
+        filters
+    }
+
+    /// Input side arguments, from the seek to the last `-i`
+    ///
+    /// Appended clips are read with the concat demuxer, its list is written in `work_dir`.
+    /// Sidecar subtitles come as extra inputs, seeked like the video.
+    pub fn input_args(&self, input: &Path, work_dir: &Path) -> io::Result<Vec<String>> {
+        let mut args = Vec::new();
+        if let Some(start) = self.start {
+            args.extend(["-ss".to_string(), start.to_string()]);
+        }
+        if let Some(end) = self.end {
+            args.extend(["-t".to_string(), (end - self.start.unwrap_or(0.0)).max(0.0).to_string()]);
+        }
+
+        if self.append.is_empty() {
+            args.extend(["-i".to_string(), input.to_string_lossy().to_string()]);
+        } else {
+            let list = work_dir.join("concat.txt");
+            fs::write(&list, concat_list(&self.clips(input))?)?;
+            args.extend(["-f", "concat", "-safe", "0", "-i"].map(String::from));
+            args.push(list.to_string_lossy().to_string());
+        }
+
+        if self.sidecar_subtitles {
+            for sidecar in sidecar_subtitles(input) {
+                if let Some(start) = self.start {
+                    args.extend(["-ss".to_string(), start.to_string()]);
+                }
+                args.extend(["-i".to_string(), sidecar.to_string_lossy().to_string()]);
+            }
+        }
+        Ok(args)
+    }
+
+    /// Output side arguments mapping the sidecar subtitles into a `container` file
+    ///
+    /// The first video and audio streams are kept like ffmpeg does without a `-map`, the subtitles
+    /// of the source are replaced by the sidecar files.
+    pub fn output_args(&self, input: &Path, container: &str) -> Vec<String> {
+        let sidecars = if self.sidecar_subtitles { sidecar_subtitles(input) } else { Vec::new() };
+        if sidecars.is_empty() {
+            return Vec::new();
+        }
+        let mut args: Vec<String> = ["-map", "0:v:0?", "-map", "0:a:0?"].map(String::from).to_vec();
+        for (track, _) in sidecars.iter().enumerate() {
+            args.extend(["-map".to_string(), format!("{}:0", track + 1)]);
+        }
+        args.extend(["-c:s".to_string(), subtitle_codec(container).to_string()]);
+        for (track, sidecar) in sidecars.iter().enumerate() {
+            if let Some(language) = sidecar_language(input, sidecar).filter(|language| !language.is_empty()) {
+                args.extend([format!("-metadata:s:s:{}", track), format!("language={}", language)]);
+            }
+        }
+        args
+    }
+
+    /// Duration left once trimmed, `source` being the duration of all the clips
+    pub fn edited_duration(&self, source: Duration) -> Duration {
+        let end = self.end.map(Duration::from_secs_f64).unwrap_or(source).min(source);
+        let start = self.start.map(Duration::from_secs_f64).unwrap_or_default();
+        end.saturating_sub(start)
+    }
+
+    /// Probed duration of the video and its appended clips
+    pub fn source_duration(&self, input: &Path) -> Result<Duration, Box<dyn Error>> {
+        let mut total = Duration::ZERO;
+        for clip in self.clips(input) {
+            total += probe(&clip)?
+                .duration()
+                .ok_or_else(|| format!("Cannot find the duration of {}", clip.display()))?;
+        }
+        Ok(total)
+    }
+}
+
+/// Concat demuxer list, absolute paths with quotes escaped
+fn concat_list(clips: &[PathBuf]) -> io::Result<String> {
+    let mut list = String::new();
+    for clip in clips {
+        let path = fs::canonicalize(clip)?;
+        list.push_str(&format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''")));
+    }
+    Ok(list)
+}
+
+/// All ffmpeg arguments encoding `input` with `preset` and the edits applied
+pub fn edit_args(
+    preset: &EncodePreset,
+    edits: &EditInstructions,
+    input: &Path,
+    output: &Path,
+    work_dir: &Path,
+) -> io::Result<Vec<String>> {
+    if edits.is_empty() {
+        return Ok(preset.args(input, output));
+    }
+    let mut args: Vec<String> = Vec::new();
+    if preset.has_video() {
+        args.extend(["-hwaccel".to_string(), "auto".to_string()]);
+    }
+    args.extend(edits.input_args(input, work_dir)?);
+    args.extend(preset.output_args_with(&edits.video_filters(input)));
+    args.extend(edits.output_args(input, &preset.container));
+    args.push(output.to_string_lossy().to_string());
+    Ok(args)
+}
+
+/// Parses "83.5", "1:23.5" or "00:01:23.500" into seconds
+pub fn parse_timestamp(value: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in value.trim().split(':') {
+        let number: f64 = part.trim().parse().ok()?;
+        if !number.is_finite() || number < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + number;
+    }
+    Some(seconds)
+}
+
+/// "1:23.5" style timestamp, hours only when needed
+pub fn format_timestamp(seconds: f64) -> String {
+    let whole = seconds.max(0.0) as u64;
+    let fraction = seconds - whole as f64;
+    let secs = format!("{:02}", whole % 60);
+    let secs = if fraction >= 0.05 { format!("{}.{}", secs, ((fraction * 10.0).round() as u64).min(9)) } else { secs };
+    if whole >= 3600 {
+        format!("{}:{:02}:{}", whole / 3600, whole / 60 % 60, secs)
+    } else {
+        format!("{}:{}", whole / 60, secs)
+    }
+}
+
+/// Most frequent `crop=` suggestion in the output of the cropdetect filter
+pub fn parse_cropdetect(stderr: &str) -> Option<Crop> {
+    let re = Regex::new(r"crop=(\d+:\d+:\d+:\d+)").unwrap();
+    let mut counts: HashMap<Crop, usize> = HashMap::new();
+    for caps in re.captures_iter(stderr) {
+        if let Ok(crop) = caps[1].parse::<Crop>() {
+            *counts.entry(crop).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(crop, count)| (*count, crop.width as u64 * crop.height as u64))
+        .map(|(crop, _)| crop)
+}
+
+/// Detects black bars with cropdetect on a few points of the video
+///
+/// Returns `None` when there is nothing to crop.
+pub fn detect_crop(input: &Path) -> Result<Option<Crop>, Box<dyn Error>> {
+    let probe = probe(input)?;
+    let duration = probe.duration().unwrap_or_default().as_secs_f64();
+    let resolution = probe.video_stream().and_then(|s| s.resolution()).ok_or("No video stream to crop")?;
+
+    let mut stderr = String::new();
+    for sample in CROPDETECT_SAMPLES {
+        let output = Command::new(path_for("ffmpeg"))
+            .args(["-hide_banner", "-ss", &(duration * sample).to_string(), "-i"])
+            .arg(input)
+            .args(["-frames:v", &CROPDETECT_FRAMES.to_string(), "-vf", "cropdetect", "-an", "-f", "null", "-"])
+            .output()?;
+        stderr.push_str(&String::from_utf8_lossy(&output.stderr));
+    }
+
+    Ok(parse_cropdetect(&stderr).filter(|crop| (crop.width, crop.height) != resolution))
+}
@@ -0,0 +1,205 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use crate::edit::EditInstructions;
+use crate::log::append_to_home_log;
+use crate::presets::EncodePreset;
+use crate::utils::path_for;
+
+/// Share of the target kept for the container overhead
+const MUX_OVERHEAD: f64 = 0.03;
+/// Audio bitrate used when the preset does not set one
+const DEFAULT_AUDIO_BITRATE: u64 = 128_000;
+/// Below this the picture is unwatchable, better to fail early
+const MIN_VIDEO_BITRATE: u64 = 50_000;
+/// How many times the encode is redone when the output is still too big
+const MAX_ATTEMPTS: u32 = 2;
+
+#[cfg(target_os = "windows")]
+const NULL_OUTPUT: &str = "NUL";
+#[cfg(not(target_os = "windows"))]
+const NULL_OUTPUT: &str = "/dev/null";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetSizeResult {
+    pub target_size: u64,
+    pub output_size: u64,
+    pub video_bitrate: u64,
+    pub attempts: u32,
+}
+
+/// Parses sizes like "25MB", "25M", "1.5G", "800k" or a plain byte count
+///
+/// Units are decimal, so "25MB" is 25 000 000 bytes and stays under chat upload limits.
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    parse_with_units(value.trim_end_matches('b'), 1_000.0)
+}
+
+/// Parses bitrates like "128k", "2.5M" or "96000" into bits per second
+pub fn parse_bitrate(value: &str) -> Option<u64> {
+    parse_with_units(&value.trim().to_ascii_lowercase(), 1_000.0)
+}
+
+fn parse_with_units(value: &str, base: f64) -> Option<u64> {
+    let (number, multiplier) = match value.chars().last()? {
+        'k' => (&value[..value.len() - 1], base),
+        'm' => (&value[..value.len() - 1], base * base),
+        'g' => (&value[..value.len() - 1], base * base * base),
+        _ => (value, 1.0),
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    if number.is_finite() && number > 0.0 {
+        Some((number * multiplier) as u64)
+    } else {
+        None
+    }
+}
+
+/// Video bitrate (bits/s) that fits `duration` into `target_bytes` once the audio is reserved
+pub fn target_video_bitrate(duration: Duration, target_bytes: u64, audio_bitrate: u64) -> Option<u64> {
+    let seconds = duration.as_secs_f64();
+    if seconds <= 0.0 {
+        return None;
+    }
+    let total_bitrate = target_bytes as f64 * 8.0 * (1.0 - MUX_OVERHEAD) / seconds;
+    let video_bitrate = total_bitrate - audio_bitrate as f64;
+    if video_bitrate < MIN_VIDEO_BITRATE as f64 {
+        None
+    } else {
+        Some(video_bitrate as u64)
+    }
+}
+
+fn audio_bitrate(preset: &EncodePreset) -> u64 {
+    match &preset.audio_codec {
+        Some(_) => preset.audio_bitrate.as_deref().and_then(parse_bitrate).unwrap_or(DEFAULT_AUDIO_BITRATE),
+        None => 0,
+    }
+}
+
+/// Arguments for one pass of a two-pass libx264/libx265 encode, `passlog` is the stats file prefix
+///
+/// `input_args` are the arguments up to and including `-i`, `filters` go before the preset scale.
+/// `output_args` end with the output file, they only go to the second pass since the first one
+/// writes no file.
+pub fn two_pass_args(
+    preset: &EncodePreset,
+    input_args: &[String],
+    filters: &[String],
+    output_args: &[String],
+    video_bitrate: u64,
+    pass: u8,
+    passlog: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let codec = preset.video_codec.as_deref().ok_or_else(|| format!("{} has no video to size", preset.name))?;
+    let pass_args = match codec {
+        "libx264" => vec![
+            "-pass".to_string(),
+            pass.to_string(),
+            "-passlogfile".to_string(),
+            passlog.to_string_lossy().to_string(),
+        ],
+        "libx265" => vec![
+            "-x265-params".to_string(),
+            format!("pass={}:stats={}", pass, escape_param(&format!("{}.log", passlog.to_string_lossy()))),
+        ],
+        other => return Err(format!("Two-pass encoding needs libx264 or libx265, not {}", other).into()),
+    };
+
+    let mut args = vec!["-y".to_string()];
+    args.extend(input_args.iter().cloned());
+    args.extend(["-c:v".to_string(), codec.to_string(), "-b:v".to_string(), video_bitrate.to_string()]);
+    args.extend(preset.video_settings_args_with(filters));
+    args.extend(pass_args);
+
+    if pass == 1 {
+        args.extend(["-an", "-sn", "-f", "null", NULL_OUTPUT].map(String::from));
+    } else {
+        args.extend(preset.audio_args());
+        args.extend(preset.extra_args.iter().cloned());
+        args.extend(output_args.iter().cloned());
+    }
+    Ok(args)
+}
+
+/// Escapes a value of a `key=value:key=value` option, like the drive colon and backslashes of a Windows path
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ':' | '=' | '\'') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Runs ffmpeg with `args` and waits for it, failing on a non zero exit status
+pub fn run_ffmpeg(args: &[String]) -> Result<(), Box<dyn Error>> {
+    append_to_home_log(format!("{} {}", path_for("ffmpeg"), args.join(" ")));
+    let output = Command::new(path_for("ffmpeg")).args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last_line = stderr.lines().last().unwrap_or_default();
+        Err(format!("ffmpeg exited with {}: {}", output.status, last_line).into())
+    }
+}
+
+/// Two-pass encode of `input` sized to fit `target_bytes`, using the preset codec and settings
+///
+/// The duration is probed first, the audio bitrate is reserved, and the output size is checked
+/// afterwards. When the output still overshoots, the encode is redone with a scaled down bitrate.
+pub fn encode_to_target_size(
+    input: &Path,
+    output: &Path,
+    preset: &EncodePreset,
+    target_bytes: u64,
+) -> Result<TargetSizeResult, Box<dyn Error>> {
+    encode_to_target_size_with(input, output, preset, &EditInstructions::default(), target_bytes, run_ffmpeg)
+}
+
+/// Same as [`encode_to_target_size`], with `edits` applied and each ffmpeg pass handed to `run`
+pub fn encode_to_target_size_with(
+    input: &Path,
+    output: &Path,
+    preset: &EncodePreset,
+    edits: &EditInstructions,
+    target_bytes: u64,
+    mut run: impl FnMut(&[String]) -> Result<(), Box<dyn Error>>,
+) -> Result<TargetSizeResult, Box<dyn Error>> {
+    let duration = edits.edited_duration(edits.source_duration(input)?);
+    let audio_bitrate = audio_bitrate(preset);
+    let mut video_bitrate = target_video_bitrate(duration, target_bytes, audio_bitrate)
+        .ok_or_else(|| format!("{} bytes is too small for {:.0}s of video", target_bytes, duration.as_secs_f64()))?;
+
+    let work_dir = tempfile::tempdir()?;
+    let passlog = work_dir.path().join("passlog");
+    let input_args = edits.input_args(input, work_dir.path())?;
+    let filters = edits.video_filters(input);
+    let mut output_args = edits.output_args(input, &preset.container);
+    output_args.push(output.to_string_lossy().to_string());
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        run(&two_pass_args(preset, &input_args, &filters, &output_args, video_bitrate, 1, &passlog)?)?;
+        run(&two_pass_args(preset, &input_args, &filters, &output_args, video_bitrate, 2, &passlog)?)?;
+
+        let output_size = fs::metadata(output)?.len();
+        if output_size <= target_bytes {
+            return Ok(TargetSizeResult { target_size: target_bytes, output_size, video_bitrate, attempts: attempt });
+        }
+
+        append_to_home_log(format!("{} is {} bytes, over the {} target", output.display(), output_size, target_bytes));
+        let ratio = target_bytes as f64 / output_size as f64;
+        video_bitrate = ((video_bitrate as f64) * ratio * (1.0 - MUX_OVERHEAD)) as u64;
+        if video_bitrate < MIN_VIDEO_BITRATE {
+            break;
+        }
+    }
+
+    Err(format!("Could not fit {} into {} bytes", input.display(), target_bytes).into())
+}
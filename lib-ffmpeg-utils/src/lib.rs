@@ -1,5 +1,13 @@
 pub mod log;
 pub mod devices;
 pub mod utils;
+pub mod probe;
+pub mod presets;
+pub mod encode;
+pub mod edit;
+pub mod subtitles;
+pub mod loudness;
+pub mod verify;
+pub mod capabilities;
 
 pub mod recorder;
\ No newline at end of file
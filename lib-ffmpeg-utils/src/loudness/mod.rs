@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use crate::probe::probe;
+use crate::utils::path_for;
+
+/// Level below which audio counts as silence when trimming
+const SILENCE_THRESHOLD: &str = "-50dB";
+/// Sample rate used when the source does not tell, loudnorm works at 192 kHz and needs a rate to go back to
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+/// Loudness the normalization aims at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// LUFS, EBU R128 asks for -23
+    pub integrated: f64,
+    /// dBTP
+    pub true_peak: f64,
+    /// Loudness range, in LU
+    pub range: f64,
+}
+
+impl Default for LoudnessTarget {
+    fn default() -> Self {
+        LoudnessTarget { integrated: -23.0, true_peak: -1.0, range: 11.0 }
+    }
+}
+
+impl LoudnessTarget {
+    fn loudnorm(&self) -> String {
+        format!("loudnorm=I={}:TP={}:LRA={}", self.integrated, self.true_peak, self.range)
+    }
+}
+
+/// What the measuring pass of loudnorm found in the source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness, in LUFS
+    pub integrated: f64,
+    /// True peak, in dBTP
+    pub true_peak: f64,
+    /// Loudness range, in LU
+    pub range: f64,
+    pub threshold: f64,
+    /// Gain left for the second pass to apply
+    pub offset: f64,
+}
+
+impl fmt::Display for Loudness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} LUFS, {:.1} dBTP", self.integrated, self.true_peak)
+    }
+}
+
+/// The json block loudnorm prints at the end of a pass with `print_format=json`
+pub fn parse_loudnorm(stderr: &str) -> Option<Loudness> {
+    let report = &stderr[stderr.rfind("Parsed_loudnorm")?..];
+    let start = report.find('{')?;
+    let json = &report[start..=start + report[start..].find('}')?];
+    let values: HashMap<String, String> = serde_json::from_str(json).ok()?;
+    let value = |key: &str| values.get(key)?.trim().parse::<f64>().ok();
+    Some(Loudness {
+        integrated: value("input_i")?,
+        true_peak: value("input_tp")?,
+        range: value("input_lra")?,
+        threshold: value("input_thresh")?,
+        offset: value("target_offset")?,
+    })
+}
+
+/// Optional clean up of an audio track before it is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AudioFilters {
+    /// Two pass EBU R128 normalization
+    pub normalize: Option<LoudnessTarget>,
+    /// Removes the silence at the start and at the end
+    pub trim_silence: bool,
+    /// Downmixes to a single channel
+    pub mono: bool,
+}
+
+impl AudioFilters {
+    pub fn is_empty(&self) -> bool {
+        self.normalize.is_none() && !self.trim_silence && !self.mono
+    }
+
+    /// Filters applied before the loudness is measured, so the measure matches the output
+    ///
+    /// The end is trimmed by reversing the audio, which keeps the whole track in memory.
+    fn cleanup(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if self.mono {
+            filters.push("aformat=channel_layouts=mono".to_string());
+        }
+        if self.trim_silence {
+            let trim = format!("silenceremove=start_periods=1:start_threshold={}", SILENCE_THRESHOLD);
+            filters.extend([trim.clone(), "areverse".to_string(), trim, "areverse".to_string()]);
+        }
+        filters
+    }
+
+    /// `-af` value of the measuring pass
+    pub fn measure_filter(&self) -> String {
+        let mut filters = self.cleanup();
+        filters.push(format!("{}:print_format=json", self.normalize.unwrap_or_default().loudnorm()));
+        filters.join(",")
+    }
+
+    /// `-af` value of the encode, `None` when there is nothing to do
+    ///
+    /// Normalizing needs the `measured` loudness of the first pass, without it the track is only cleaned up.
+    pub fn filter(&self, measured: Option<&Loudness>, sample_rate: Option<u32>) -> Option<String> {
+        let mut filters = self.cleanup();
+        if let (Some(target), Some(measured)) = (self.normalize, measured) {
+            filters.push(format!(
+                "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+                target.loudnorm(),
+                measured.integrated,
+                measured.true_peak,
+                measured.range,
+                measured.threshold,
+                measured.offset
+            ));
+            filters.push(format!("aresample={}", sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE)));
+        }
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+}
+
+/// Runs the measuring pass over the audio picked by `input_args`, like `-i concert.mkv -map 0:a:1`
+pub fn measure_loudness(input_args: &[String], filters: &AudioFilters) -> Result<Loudness, String> {
+    let output = Command::new(path_for("ffmpeg"))
+        .args(["-hide_banner", "-nostats"])
+        .args(input_args)
+        .args(["-af", &filters.measure_filter(), "-f", "null", "-"])
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("ffmpeg error: {}", stderr.lines().last().unwrap_or_default()));
+    }
+    parse_loudnorm(&stderr).ok_or_else(|| "ffmpeg did not report the loudness".to_string())
+}
+
+/// Runs the filters over the first audio track of a file and replaces it, video is copied as is
+///
+/// `encode_args` set the audio encoder, since the filtered audio has to be encoded again.
+pub fn filter_in_place(path: &Path, filters: &AudioFilters, encode_args: &[&str]) -> Result<Option<Loudness>, String> {
+    let sample_rate = probe(path)
+        .map_err(|e| format!("Failed to probe {}: {}", path.display(), e))?
+        .audio_streams()
+        .next()
+        .and_then(|stream| stream.sample_rate);
+    let input_args = vec!["-i".to_string(), path.to_string_lossy().to_string(), "-map".to_string(), "0:a:0".to_string()];
+    let measured = match filters.normalize {
+        Some(_) => Some(measure_loudness(&input_args, filters)?),
+        None => None,
+    };
+    let Some(filter) = filters.filter(measured.as_ref(), sample_rate) else {
+        return Ok(None);
+    };
+
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".filtering.");
+    name.push(path.extension().unwrap_or_default());
+    let temporary = path.with_file_name(name);
+    let output = Command::new(path_for("ffmpeg"))
+        .args(["-hide_banner", "-y", "-i"])
+        .arg(path)
+        .args(["-map", "0:v?", "-map", "0:a:0", "-c:v", "copy", "-af", &filter])
+        .args(encode_args)
+        .arg(&temporary)
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&temporary);
+        return Err(format!("ffmpeg error: {}", String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default()));
+    }
+    fs::rename(&temporary, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+    Ok(measured)
+}
@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::log::append_to_home_log;
+use crate::utils::path_for;
+
+const BUILTIN_PRESETS: &str = include_str!("presets.yaml");
+
+/// A named set of encoding settings shared by the video apps
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct EncodePreset {
+    pub name: String,
+    /// Output container, also used as the file extension
+    pub container: String,
+    /// No video codec means the video is dropped
+    #[serde(default)]
+    pub video_codec: Option<String>,
+    /// No audio codec means the audio is dropped
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    #[serde(default)]
+    pub quality: Quality,
+    /// Encoder speed preset (ultrafast .. veryslow)
+    #[serde(default)]
+    pub speed: Option<String>,
+    #[serde(default)]
+    pub tune: Option<String>,
+    /// Scale filter size, "-2:720" keeps the aspect ratio
+    #[serde(default)]
+    pub scale: Option<String>,
+    #[serde(default)]
+    pub fps: Option<f32>,
+    #[serde(default)]
+    pub audio_bitrate: Option<String>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Quality {
+    /// Let the encoder decide
+    #[default]
+    Default,
+    /// Constant rate factor, lower is better
+    Crf { value: u8 },
+    /// Average video bitrate, like "2500k"
+    Bitrate { value: String },
+}
+
+impl EncodePreset {
+    /// Ad-hoc preset for a bare codec pair, like the old `encoding` settings
+    pub fn from_codecs(video_codec: &str, audio_codec: &str, container: &str) -> Self {
+        Self {
+            name: format!("{} / {}", video_codec, audio_codec),
+            container: container.to_string(),
+            video_codec: Some(video_codec.to_string()),
+            audio_codec: Some(audio_codec.to_string()),
+            speed: Some("fast".to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.video_codec.is_some()
+    }
+
+    /// Speed, tune, scale and frame rate arguments of the video encoder
+    pub fn video_settings_args(&self) -> Vec<String> {
+        self.video_settings_args_with(&[])
+    }
+
+    /// Same as [`Self::video_settings_args`], with `filters` run before the scale
+    pub fn video_settings_args_with(&self, filters: &[String]) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        if let Some(speed) = &self.speed {
+            args.extend(["-preset".to_string(), speed.clone()]);
+        }
+        if let Some(tune) = &self.tune {
+            args.extend(["-tune".to_string(), tune.clone()]);
+        }
+        let mut filters = filters.to_vec();
+        if let Some(scale) = &self.scale {
+            filters.push(format!("scale={}", scale));
+        }
+        if !filters.is_empty() {
+            args.extend(["-vf".to_string(), filters.join(",")]);
+        }
+        if let Some(fps) = self.fps {
+            args.extend(["-r".to_string(), fps.to_string()]);
+        }
+        args
+    }
+
+    /// Audio codec and bitrate arguments, or `-an` when the preset has no audio
+    pub fn audio_args(&self) -> Vec<String> {
+        match &self.audio_codec {
+            Some(codec) => {
+                let mut args = vec!["-c:a".to_string(), codec.clone()];
+                if let Some(bitrate) = &self.audio_bitrate {
+                    args.extend(["-b:a".to_string(), bitrate.clone()]);
+                }
+                args
+            }
+            None => vec!["-an".to_string()],
+        }
+    }
+
+    /// Output side ffmpeg arguments, everything between the input and the output file
+    pub fn output_args(&self) -> Vec<String> {
+        self.output_args_with(&[])
+    }
+
+    /// Same as [`Self::output_args`], with `filters` added to the video filter chain
+    pub fn output_args_with(&self, filters: &[String]) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+
+        match &self.video_codec {
+            Some(codec) => {
+                args.extend(["-c:v".to_string(), codec.clone()]);
+                match &self.quality {
+                    Quality::Default => {}
+                    Quality::Crf { value } => args.extend(["-crf".to_string(), value.to_string()]),
+                    Quality::Bitrate { value } => args.extend(["-b:v".to_string(), value.clone()]),
+                }
+                args.extend(self.video_settings_args_with(filters));
+            }
+            None => args.push("-vn".to_string()),
+        }
+
+        args.extend(self.audio_args());
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+
+    /// All ffmpeg arguments encoding `input` into `output` with this preset
+    pub fn args(&self, input: &Path, output: &Path) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        if self.has_video() {
+            args.extend(["-hwaccel".to_string(), "auto".to_string()]);
+        }
+        args.extend(["-i".to_string(), input.to_string_lossy().to_string()]);
+        args.extend(self.output_args());
+        args.push(output.to_string_lossy().to_string());
+        args
+    }
+
+    /// Full ffmpeg command encoding `input` into `output` with this preset
+    pub fn command(&self, input: &Path, output: &Path) -> Command {
+        let mut cmd = Command::new(path_for("ffmpeg"));
+        cmd.args(self.args(input, output));
+        cmd
+    }
+
+    /// Output file for `input` in `output_folder`, using the preset container as extension
+    pub fn output_path(&self, input: &Path, output_folder: &Path) -> PathBuf {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        output_folder.join(format!("{}.{}", stem, self.container))
+    }
+}
+
+pub fn builtin_presets() -> Vec<EncodePreset> {
+    serde_yaml::from_str(BUILTIN_PRESETS).expect("Invalid built-in presets")
+}
+
+/// Where users can add their own presets
+pub fn user_presets_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rusty-toolbox")
+        .join("presets.yaml")
+}
+
+pub fn load_presets_from(path: &Path) -> Result<Vec<EncodePreset>, Box<dyn Error>> {
+    let yaml = fs::read_to_string(path)?;
+    Ok(serde_yaml::from_str(&yaml)?)
+}
+
+pub fn save_presets_to(path: &Path, presets: &[EncodePreset]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_yaml::to_string(presets)?)?;
+    Ok(())
+}
+
+/// Built-in presets followed by the user ones, a user preset replaces a built-in with the same name
+pub fn merge_presets(builtin: Vec<EncodePreset>, user: Vec<EncodePreset>) -> Vec<EncodePreset> {
+    let mut presets = builtin;
+    for preset in user {
+        match presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+    presets
+}
+
+/// All presets available to the apps
+pub fn all_presets() -> Vec<EncodePreset> {
+    let path = user_presets_path();
+    let user = if path.exists() {
+        load_presets_from(&path).unwrap_or_else(|e| {
+            append_to_home_log(format!("Cannot load presets from {}: {}", path.display(), e));
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    merge_presets(builtin_presets(), user)
+}
+
+pub fn find_preset(name: &str) -> Option<EncodePreset> {
+    all_presets().into_iter().find(|p| p.name == name)
+}
@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use serde::{Deserialize, Deserializer};
+use crate::utils::path_for;
+
+/// Everything ffprobe tells us about a media file
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ProbeResult {
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub streams: Vec<Stream>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Format {
+    #[serde(default)]
+    pub filename: String,
+    #[serde(default)]
+    pub nb_streams: u32,
+    #[serde(default)]
+    pub format_name: String,
+    #[serde(default)]
+    pub format_long_name: Option<String>,
+    #[serde(default, deserialize_with = "de_duration")]
+    pub start_time: Option<Duration>,
+    #[serde(default, deserialize_with = "de_duration")]
+    pub duration: Option<Duration>,
+    #[serde(default, deserialize_with = "de_number")]
+    pub size: Option<u64>,
+    #[serde(default, deserialize_with = "de_number")]
+    pub bit_rate: Option<u64>,
+    #[serde(default)]
+    pub probe_score: Option<u32>,
+    #[serde(default)]
+    pub tags: Tags,
+}
+
+/// Container level tags, all optional since every muxer writes its own set
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Tags {
+    pub major_brand: Option<String>,
+    pub minor_version: Option<String>,
+    pub compatible_brands: Option<String>,
+    #[serde(alias = "ENCODER")]
+    pub encoder: Option<String>,
+    #[serde(alias = "TITLE")]
+    pub title: Option<String>,
+    #[serde(alias = "ARTIST")]
+    pub artist: Option<String>,
+    #[serde(alias = "ALBUM")]
+    pub album: Option<String>,
+    #[serde(alias = "DATE")]
+    pub date: Option<String>,
+    #[serde(alias = "COMMENT")]
+    pub comment: Option<String>,
+    pub creation_time: Option<String>,
+    #[serde(rename = "com.apple.quicktime.location.ISO6709")]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecType {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Stream {
+    pub index: u32,
+    #[serde(default)]
+    pub codec_type: CodecType,
+    pub codec_name: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub profile: Option<String>,
+    // video
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pix_fmt: Option<String>,
+    #[serde(default, deserialize_with = "de_rational")]
+    pub r_frame_rate: Option<f64>,
+    #[serde(default, deserialize_with = "de_rational")]
+    pub avg_frame_rate: Option<f64>,
+    // audio
+    #[serde(default, deserialize_with = "de_number")]
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    // common
+    #[serde(default, deserialize_with = "de_number")]
+    pub bit_rate: Option<u64>,
+    #[serde(default, deserialize_with = "de_duration")]
+    pub duration: Option<Duration>,
+    #[serde(default, deserialize_with = "de_number")]
+    pub nb_frames: Option<u64>,
+    #[serde(default)]
+    pub disposition: Disposition,
+    #[serde(default)]
+    pub tags: StreamTags,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct StreamTags {
+    #[serde(alias = "LANGUAGE")]
+    pub language: Option<String>,
+    #[serde(alias = "TITLE")]
+    pub title: Option<String>,
+    pub handler_name: Option<String>,
+    pub rotate: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Disposition {
+    #[serde(default, deserialize_with = "de_flag")]
+    pub default: bool,
+    #[serde(default, deserialize_with = "de_flag")]
+    pub forced: bool,
+    #[serde(default, deserialize_with = "de_flag")]
+    pub attached_pic: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Chapter {
+    pub id: i64,
+    #[serde(default, deserialize_with = "de_duration")]
+    pub start_time: Option<Duration>,
+    #[serde(default, deserialize_with = "de_duration")]
+    pub end_time: Option<Duration>,
+    #[serde(default)]
+    pub tags: ChapterTags,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChapterTags {
+    #[serde(alias = "TITLE")]
+    pub title: Option<String>,
+}
+
+impl ProbeResult {
+    /// Container duration, falling back to the longest stream
+    pub fn duration(&self) -> Option<Duration> {
+        self.format.duration.or_else(|| self.streams.iter().filter_map(|s| s.duration).max())
+    }
+
+    /// First real video stream, cover art pictures are skipped
+    pub fn video_stream(&self) -> Option<&Stream> {
+        self.video_streams().next()
+    }
+
+    pub fn video_streams(&self) -> impl Iterator<Item = &Stream> {
+        self.streams.iter().filter(|s| s.codec_type == CodecType::Video && !s.disposition.attached_pic)
+    }
+
+    pub fn audio_streams(&self) -> impl Iterator<Item = &Stream> {
+        self.streams_of(CodecType::Audio)
+    }
+
+    pub fn subtitle_streams(&self) -> impl Iterator<Item = &Stream> {
+        self.streams_of(CodecType::Subtitle)
+    }
+
+    pub fn streams_of(&self, codec_type: CodecType) -> impl Iterator<Item = &Stream> {
+        self.streams.iter().filter(move |s| s.codec_type == codec_type)
+    }
+}
+
+impl Stream {
+    /// Frames per second, the average rate is preferred over the container rate
+    pub fn fps(&self) -> Option<f64> {
+        self.avg_frame_rate.or(self.r_frame_rate)
+    }
+
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        Some((self.width?, self.height?))
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.tags.language.as_deref()
+    }
+}
+
+/// Runs ffprobe on the given file and parses format, streams and chapters
+pub fn probe(path: impl AsRef<Path>) -> Result<ProbeResult, Box<dyn Error>> {
+    parse_probe_output(&probe_json(path)?)
+}
+
+/// The json ffprobe prints for the given file, for callers keeping it around
+pub fn probe_json(path: impl AsRef<Path>) -> Result<String, Box<dyn Error>> {
+    let path = path.as_ref();
+    let output = Command::new(path_for("ffprobe"))
+        .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams", "-show_chapters"])
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!("ffprobe failed on {} ({})", path.display(), output.status).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parses the json printed by `ffprobe -print_format json`
+pub fn parse_probe_output(json: &str) -> Result<ProbeResult, Box<dyn Error>> {
+    Ok(serde_json::from_str(json)?)
+}
+
+// ffprobe prints most numbers as strings, and "N/A" when it does not know
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Text(String),
+    Number(f64),
+}
+
+impl RawValue {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            RawValue::Text(s) => s.trim().parse().ok(),
+            RawValue::Number(n) => Some(*n),
+        }
+    }
+}
+
+fn de_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let raw: Option<RawValue> = Option::deserialize(deserializer)?;
+    Ok(raw
+        .and_then(|v| v.as_f64())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64))
+}
+
+fn de_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<u64>,
+{
+    let raw: Option<RawValue> = Option::deserialize(deserializer)?;
+    Ok(raw
+        .and_then(|v| v.as_f64())
+        .filter(|n| n.is_finite() && *n >= 0.0)
+        .and_then(|n| T::try_from(n as u64).ok()))
+}
+
+/// Frame rates come as "30000/1001", "0/0" means unknown
+fn de_rational<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    let raw: Option<String> = Option::deserialize(deserializer)?;
+    Ok(raw.and_then(|s| parse_rational(&s)))
+}
+
+pub fn parse_rational(value: &str) -> Option<f64> {
+    let (num, den) = match value.split_once('/') {
+        Some((num, den)) => (num.trim().parse::<f64>().ok()?, den.trim().parse::<f64>().ok()?),
+        None => (value.trim().parse::<f64>().ok()?, 1.0),
+    };
+    if den == 0.0 || num == 0.0 {
+        None
+    } else {
+        Some(num / den)
+    }
+}
+
+fn de_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let raw: Option<RawValue> = Option::deserialize(deserializer)?;
+    Ok(raw.and_then(|v| v.as_f64()).is_some_and(|n| n != 0.0))
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use crate::probe::ProbeResult;
+use crate::utils::path_for;
+
+/// Subtitle codecs made of pictures, they cannot be turned into text or drawn by libass
+const BITMAP_CODECS: [&str; 4] = ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+/// Text formats subtitles are extracted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubtitleFormat {
+    #[default]
+    Srt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub const ALL: [SubtitleFormat; 2] = [SubtitleFormat::Srt, SubtitleFormat::Ass];
+
+    /// File extension, also the name of the ffmpeg encoder
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+}
+
+impl fmt::Display for SubtitleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.extension().to_uppercase())
+    }
+}
+
+impl FromStr for SubtitleFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SubtitleFormat::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| format!("Unknown subtitle format {}, use srt or ass", value))
+    }
+}
+
+/// A subtitle stream of a video
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SubtitleTrack {
+    /// Position among the subtitle streams, as in `-map 0:s:N`
+    pub track: usize,
+    /// Index among all the streams
+    pub index: u32,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+impl SubtitleTrack {
+    /// False for the picture based codecs of DVDs and Blu-rays
+    pub fn is_text(&self) -> bool {
+        !BITMAP_CODECS.contains(&self.codec.as_str())
+    }
+
+    /// One line description, like "#1 eng, Commentary (subrip) default"
+    pub fn label(&self) -> String {
+        let names: Vec<&str> = [self.language.as_deref(), self.title.as_deref()].into_iter().flatten().collect();
+        let mut label = format!("#{}", self.track);
+        if !names.is_empty() {
+            label.push_str(&format!(" {}", names.join(", ")));
+        }
+        label.push_str(&format!(" ({})", self.codec));
+        if self.default {
+            label.push_str(" default");
+        }
+        if self.forced {
+            label.push_str(" forced");
+        }
+        label
+    }
+}
+
+/// Subtitle streams of a probed file, in the order ffmpeg numbers them
+pub fn subtitle_tracks(metadata: &ProbeResult) -> Vec<SubtitleTrack> {
+    metadata
+        .subtitle_streams()
+        .enumerate()
+        .map(|(track, stream)| SubtitleTrack {
+            track,
+            index: stream.index,
+            codec: stream.codec_name.clone().unwrap_or_default(),
+            language: stream.language().filter(|language| *language != "und").map(String::from),
+            title: stream.tags.title.clone(),
+            default: stream.disposition.default,
+            forced: stream.disposition.forced,
+        })
+        .collect()
+}
+
+/// Text file for a track next to the video, like `trip.eng.srt`, or `trip.2.srt` without a language
+pub fn subtitle_output_path(video: &Path, track: &SubtitleTrack, format: SubtitleFormat) -> PathBuf {
+    let stem = video.file_stem().unwrap_or_default().to_string_lossy();
+    let suffix = track.language.clone().unwrap_or_else(|| track.track.to_string());
+    video.with_file_name(format!("{}.{}.{}", stem, suffix, format.extension()))
+}
+
+/// Arguments of the ffmpeg command writing one subtitle track of `input` to a text file
+pub fn extract_subtitle_args(input: &Path, track: usize, format: SubtitleFormat, output: &Path) -> Vec<String> {
+    let mut args = vec!["-y".to_string(), "-i".to_string(), input.to_string_lossy().to_string()];
+    args.extend(["-map".to_string(), format!("0:s:{}", track), "-c:s".to_string(), format.extension().to_string()]);
+    args.push(output.to_string_lossy().to_string());
+    args
+}
+
+pub fn extract_subtitle(input: &Path, track: &SubtitleTrack, format: SubtitleFormat, output: &Path) -> Result<(), String> {
+    if !track.is_text() {
+        return Err(format!("Track {} is made of pictures ({}), it cannot be saved as text", track.track, track.codec));
+    }
+    let result = Command::new(path_for("ffmpeg"))
+        .args(extract_subtitle_args(input, track.track, format, output))
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(format!("ffmpeg error: {}", String::from_utf8_lossy(&result.stderr).lines().last().unwrap_or_default()))
+    }
+}
+
+/// `.srt` files next to a video that share its name, like `trip.srt` or `trip.en.srt`
+pub fn sidecar_subtitles(video: &Path) -> Vec<PathBuf> {
+    let Some(folder) = video.parent().map(|parent| if parent.as_os_str().is_empty() { Path::new(".") } else { parent }) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut sidecars: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| video.with_file_name(entry.file_name()))
+        .filter(|path| sidecar_language(video, path).is_some())
+        .collect();
+    sidecars.sort();
+    sidecars
+}
+
+/// Language part of a sidecar name, empty for `trip.srt`, `None` when the file belongs to another video
+pub fn sidecar_language(video: &Path, sidecar: &Path) -> Option<String> {
+    let stem = video.file_stem()?.to_str()?;
+    let name = sidecar.file_name()?.to_str()?;
+    if !sidecar.extension()?.eq_ignore_ascii_case("srt") {
+        return None;
+    }
+    let middle = name.strip_prefix(stem)?;
+    let middle = middle.get(..middle.len().checked_sub(4)?)?;
+    match middle {
+        "" => Some(String::new()),
+        language if language.starts_with('.') && language.len() > 1 && !language[1..].contains('.') => Some(language[1..].to_string()),
+        _ => None,
+    }
+}
+
+/// Subtitle encoder that `container` can hold
+pub fn subtitle_codec(container: &str) -> &'static str {
+    match container {
+        "mkv" | "matroska" => "srt",
+        "webm" => "webvtt",
+        _ => "mov_text",
+    }
+}
+
+/// Filter drawing the subtitle track of `input` into the picture
+///
+/// `start` is the trimmed beginning, the frames are put back on the timeline of the subtitles for
+/// the filter and then moved back to zero.
+pub fn burn_filter(input: &Path, track: usize, start: Option<f64>) -> String {
+    let filter = format!("subtitles=filename={}:si={}", escape_filter_value(&input.to_string_lossy()), track);
+    match start.filter(|start| *start > 0.0) {
+        Some(start) => format!("setpts=PTS+{}/TB,{},setpts=PTS-STARTPTS", start, filter),
+        None => filter,
+    }
+}
+
+/// Escapes a filter option value, once for the option and once more for the filter graph
+pub fn escape_filter_value(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        value.chars().fold(String::new(), |mut escaped, c| {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+    };
+    escape(&escape(value, &['\\', '\'', ':']), &['\\', '\'', '[', ']', ',', ';'])
+}
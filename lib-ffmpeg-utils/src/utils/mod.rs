@@ -23,7 +23,7 @@ fn find_ff_binary(binary: &str) -> String {
 
 #[cfg(not(target_os = "macos"))]
 fn find_ff_binary(binary: &str) -> String {
-    let bin = format!("{:}", binary);
+    format!("{:}", binary)
 }
 
 pub fn ffmpeg_binary() -> String {
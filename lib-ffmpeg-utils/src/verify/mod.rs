@@ -0,0 +1,94 @@
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+use crate::edit::EditInstructions;
+use crate::presets::EncodePreset;
+use crate::probe::{probe, ProbeResult};
+
+/// Smallest duration difference accepted between the source and the output
+const MIN_DURATION_TOLERANCE: Duration = Duration::from_secs(1);
+/// Share of the source duration accepted as difference, containers round differently
+const DURATION_TOLERANCE_RATIO: f64 = 0.01;
+
+/// Number of video and audio streams in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamCounts {
+    pub video: usize,
+    pub audio: usize,
+}
+
+impl StreamCounts {
+    pub fn of(probe: &ProbeResult) -> Self {
+        Self { video: probe.video_streams().count(), audio: probe.audio_streams().count() }
+    }
+
+    /// Streams an encode of `source` with `preset` should contain
+    ///
+    /// Without a `-map`, ffmpeg keeps the best video and the best audio stream only.
+    pub fn expected(source: &ProbeResult, preset: &EncodePreset) -> Self {
+        let source = Self::of(source);
+        Self {
+            video: if preset.video_codec.is_some() { source.video.min(1) } else { 0 },
+            audio: if preset.audio_codec.is_some() { source.audio.min(1) } else { 0 },
+        }
+    }
+}
+
+/// Largest accepted difference between the source and the output duration
+pub fn duration_tolerance(source_duration: Duration) -> Duration {
+    source_duration.mul_f64(DURATION_TOLERANCE_RATIO).max(MIN_DURATION_TOLERANCE)
+}
+
+/// Compares the probe of an encoded file with the probe of its source
+///
+/// The output should last `expected_duration`, or as long as the source when `None`.
+pub fn check_output(
+    source: &ProbeResult,
+    output: &ProbeResult,
+    preset: &EncodePreset,
+    expected_duration: Option<Duration>,
+) -> Result<(), String> {
+    let expected = StreamCounts::expected(source, preset);
+    let actual = StreamCounts::of(output);
+    if actual != expected {
+        return Err(format!(
+            "output has {} video and {} audio streams, expected {} and {}",
+            actual.video, actual.audio, expected.video, expected.audio
+        ));
+    }
+
+    let source_duration = match expected_duration {
+        Some(duration) => duration,
+        None => source.duration().ok_or("cannot find the source duration")?,
+    };
+    let output_duration = output.duration().ok_or("cannot find the output duration")?;
+    if output_duration.abs_diff(source_duration) > duration_tolerance(source_duration) {
+        return Err(format!(
+            "output lasts {:.1}s, expected {:.1}s",
+            output_duration.as_secs_f64(),
+            source_duration.as_secs_f64()
+        ));
+    }
+    Ok(())
+}
+
+/// Probes `source` and `output` and checks the output is a complete encode of the source
+///
+/// Trims and appended clips of `edits` are taken into account. Returns the probe of the output.
+pub fn verify_output(
+    source: &Path,
+    output: &Path,
+    preset: &EncodePreset,
+    edits: &EditInstructions,
+) -> Result<ProbeResult, Box<dyn Error>> {
+    let source_probe = probe(source)?;
+    let output_probe = probe(output)?;
+    let expected_duration = if edits.is_empty() {
+        None
+    } else {
+        Some(edits.edited_duration(edits.source_duration(source)?))
+    };
+    check_output(&source_probe, &output_probe, preset, expected_duration)
+        .map_err(|e| format!("{} failed verification: {}", output.display(), e))?;
+    Ok(output_probe)
+}
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use lib_ffmpeg_utils::capabilities::{muxer_for_container, Capabilities};
+    use lib_ffmpeg_utils::presets::{builtin_presets, EncodePreset};
+
+    fn capabilities() -> Capabilities {
+        Capabilities::parse(include_str!("fixtures/ffmpeg_encoders.txt"), include_str!("fixtures/ffmpeg_muxers.txt"))
+    }
+
+    #[test]
+    fn parse_encoders_and_muxers() {
+        let capabilities = capabilities();
+        assert!(capabilities.video_encoders.contains("libx265"));
+        assert!(capabilities.video_encoders.contains("h264_videotoolbox"));
+        assert!(!capabilities.video_encoders.contains("aac"));
+        assert!(capabilities.audio_encoders.contains("libopus"));
+        // the legend is not an encoder
+        assert!(!capabilities.video_encoders.contains("="));
+        assert!(!capabilities.audio_encoders.contains("srt"));
+        assert!(capabilities.muxers.contains("matroska"));
+        assert_eq!(capabilities.muxers.len(), 10);
+    }
+
+    #[test]
+    fn builtin_presets_are_supported() {
+        let capabilities = capabilities();
+        for preset in builtin_presets() {
+            assert_eq!(capabilities.check_preset(&preset), Vec::<String>::new(), "{}", preset.name);
+        }
+    }
+
+    #[test]
+    fn unsupported_settings() {
+        let preset = EncodePreset::from_codecs("libsvtav1", "mp3", "avi");
+        assert_eq!(
+            capabilities().check_preset(&preset),
+            vec![
+                "ffmpeg has no libsvtav1 video encoder".to_string(),
+                "ffmpeg has no mp3 audio encoder".to_string(),
+                "ffmpeg cannot write avi files".to_string(),
+            ]
+        );
+        assert_eq!(muxer_for_container("mkv"), "matroska");
+        assert_eq!(muxer_for_container("mp4"), "mp4");
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+    use lib_ffmpeg_utils::edit::{
+        edit_args, format_timestamp, parse_cropdetect, parse_timestamp, Crop, EditInstructions, Rotation,
+    };
+    use lib_ffmpeg_utils::presets::EncodePreset;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("83.5"), Some(83.5));
+        assert_eq!(parse_timestamp("1:23.5"), Some(83.5));
+        assert_eq!(parse_timestamp("01:00:05"), Some(3605.0));
+        assert_eq!(parse_timestamp("1:x"), None);
+        assert_eq!(parse_timestamp("-3"), None);
+        assert_eq!(format_timestamp(83.5), "1:23.5");
+        assert_eq!(format_timestamp(3605.0), "1:00:05");
+    }
+
+    #[test]
+    fn trim_rotate_and_crop_args() {
+        let preset = EncodePreset { scale: Some("-2:720".to_string()), ..EncodePreset::from_codecs("libx264", "aac", "mp4") };
+        let edits = EditInstructions {
+            start: Some(5.0),
+            end: Some(65.0),
+            rotate: "cw".parse().unwrap(),
+            crop: Some("crop=1920:800:0:140".parse().unwrap()),
+            ..Default::default()
+        };
+        let args = edit_args(&preset, &edits, Path::new("in.mov"), Path::new("out.mp4"), Path::new("/tmp")).unwrap();
+        assert_eq!(&args[..8], &["-hwaccel", "auto", "-ss", "5", "-t", "60", "-i", "in.mov"]);
+        assert!(args.contains(&"crop=1920:800:0:140,transpose=clock,scale=-2:720".to_string()));
+        assert_eq!(edits.summary(), "trim 0:05-1:05, crop 1920:800:0:140, rotate 90° clockwise");
+
+        // no edits gives the plain preset command
+        let plain = edit_args(&preset, &EditInstructions::default(), Path::new("in.mov"), Path::new("out.mp4"), Path::new("/tmp"));
+        assert_eq!(plain.unwrap(), preset.args(Path::new("in.mov"), Path::new("out.mp4")));
+        assert!("sideways".parse::<Rotation>().is_err());
+        assert!("1920:800".parse::<Crop>().is_err());
+    }
+
+    #[test]
+    fn concat_list_and_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second) = (dir.path().join("a.mp4"), dir.path().join("it's.mp4"));
+        std::fs::write(&first, "a").unwrap();
+        std::fs::write(&second, "b").unwrap();
+
+        let edits = EditInstructions { append: vec![second.to_string_lossy().to_string()], end: Some(100.0), ..Default::default() };
+        let args = edits.input_args(&first, dir.path()).unwrap();
+        assert_eq!(&args[2..7], &["-f", "concat", "-safe", "0", "-i"]);
+        let list = std::fs::read_to_string(&args[7]).unwrap();
+        assert_eq!(list.lines().count(), 2);
+        assert!(list.ends_with("it'\\''s.mp4'\n"));
+
+        assert_eq!(edits.edited_duration(Duration::from_secs(30)), Duration::from_secs(30));
+        let trimmed = EditInstructions { start: Some(10.0), end: Some(20.0), ..Default::default() };
+        assert_eq!(trimmed.edited_duration(Duration::from_secs(30)), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn cropdetect_output() {
+        let stderr = "\
+[Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:138 y2:941 w:1920 h:800 x:0 y:140 pts:1 t:0.04 crop=1920:800:0:140
+[Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:0 y2:1079 w:1920 h:1072 x:0 y:4 pts:2 t:0.08 crop=1920:1072:0:4
+[Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:138 y2:941 w:1920 h:800 x:0 y:140 pts:3 t:0.12 crop=1920:800:0:140
+";
+        assert_eq!(parse_cropdetect(stderr), Some(Crop { width: 1920, height: 800, x: 0, y: 140 }));
+        assert_eq!(parse_cropdetect("no crop here"), None);
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+    use lib_ffmpeg_utils::encode::{parse_bitrate, parse_size, target_video_bitrate, two_pass_args};
+    use lib_ffmpeg_utils::presets::EncodePreset;
+
+    fn input_args() -> Vec<String> {
+        vec!["-i".to_string(), "in.mov".to_string()]
+    }
+
+    fn output(file: &str) -> Vec<String> {
+        vec![file.to_string()]
+    }
+
+    #[test]
+    fn sizes_and_bitrates() {
+        assert_eq!(parse_size("25MB"), Some(25_000_000));
+        assert_eq!(parse_size("25m"), Some(25_000_000));
+        assert_eq!(parse_size("1.5G"), Some(1_500_000_000));
+        assert_eq!(parse_size("800k"), Some(800_000));
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("MB"), None);
+        assert_eq!(parse_size("-3M"), None);
+
+        assert_eq!(parse_bitrate("128k"), Some(128_000));
+        assert_eq!(parse_bitrate("2.5M"), Some(2_500_000));
+        assert_eq!(parse_bitrate("fast"), None);
+    }
+
+    #[test]
+    fn bitrate_for_target_size() {
+        // 25MB over 100s is 2Mb/s in total, minus 3% overhead and 128k of audio
+        let bitrate = target_video_bitrate(Duration::from_secs(100), 25_000_000, 128_000).unwrap();
+        assert_eq!(bitrate, 1_812_000);
+
+        // an hour does not fit in 1MB
+        assert_eq!(target_video_bitrate(Duration::from_secs(3600), 1_000_000, 128_000), None);
+        assert_eq!(target_video_bitrate(Duration::ZERO, 1_000_000, 0), None);
+    }
+
+    #[test]
+    fn x264_passes() {
+        let preset = EncodePreset::from_codecs("libx264", "aac", "mp4");
+        let passlog = Path::new("/tmp/x/passlog");
+        let first = two_pass_args(&preset, &input_args(), &[], &output("out.mp4"), 1_000_000, 1, passlog).unwrap();
+        assert_eq!(
+            first,
+            vec![
+                "-y", "-i", "in.mov", "-c:v", "libx264", "-b:v", "1000000", "-preset", "fast",
+                "-pass", "1", "-passlogfile", "/tmp/x/passlog", "-an", "-sn", "-f", "null", "/dev/null",
+            ]
+        );
+
+        let second = two_pass_args(&preset, &input_args(), &[], &output("out.mp4"), 1_000_000, 2, passlog).unwrap();
+        assert_eq!(&second[9..], &["-pass", "2", "-passlogfile", "/tmp/x/passlog", "-c:a", "aac", "out.mp4"]);
+    }
+
+    #[test]
+    fn x265_passes_and_other_codecs() {
+        let preset = EncodePreset::from_codecs("libx265", "aac", "mp4");
+        let passlog = Path::new("/tmp/x/passlog");
+        let args = two_pass_args(&preset, &input_args(), &[], &output("out.mp4"), 800_000, 2, passlog).unwrap();
+        assert!(args.contains(&"pass=2:stats=/tmp/x/passlog.log".to_string()));
+        let args = two_pass_args(&preset, &input_args(), &[], &output("out.mp4"), 800_000, 2, Path::new(r"C:\tmp\passlog")).unwrap();
+        assert!(args.contains(&r"pass=2:stats=C\:\\tmp\\passlog.log".to_string()));
+
+        let vp9 = EncodePreset::from_codecs("libvpx-vp9", "libopus", "webm");
+        assert!(two_pass_args(&vp9, &input_args(), &[], &output("out.webm"), 800_000, 1, passlog).is_err());
+        let audio = EncodePreset { video_codec: None, ..EncodePreset::from_codecs("", "libmp3lame", "mp3") };
+        assert!(two_pass_args(&audio, &input_args(), &[], &output("out.mp3"), 800_000, 1, passlog).is_err());
+    }
+}
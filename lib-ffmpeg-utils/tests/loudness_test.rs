@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use lib_ffmpeg_utils::loudness::{parse_loudnorm, AudioFilters, Loudness, LoudnessTarget};
+
+    #[test]
+    fn parses_the_measure() {
+        let loudness = parse_loudnorm(include_str!("fixtures/loudnorm_stderr.txt")).unwrap();
+        assert_eq!(
+            loudness,
+            Loudness { integrated: -27.61, true_peak: -4.47, range: 18.06, threshold: -39.2, offset: 0.58 }
+        );
+        assert_eq!(loudness.to_string(), "-27.6 LUFS, -4.5 dBTP");
+        assert_eq!(parse_loudnorm("Stream mapping:\n"), None);
+    }
+
+    #[test]
+    fn builds_both_passes() {
+        assert_eq!(AudioFilters::default().filter(None, None), None);
+
+        let filters = AudioFilters { normalize: Some(LoudnessTarget::default()), trim_silence: true, mono: true };
+        let trim = "silenceremove=start_periods=1:start_threshold=-50dB";
+        assert_eq!(
+            filters.measure_filter(),
+            format!("aformat=channel_layouts=mono,{0},areverse,{0},areverse,loudnorm=I=-23:TP=-1:LRA=11:print_format=json", trim)
+        );
+
+        let measured = Loudness { integrated: -27.61, true_peak: -4.47, range: 18.06, threshold: -39.2, offset: 0.58 };
+        let mono = AudioFilters { mono: true, ..Default::default() };
+        let normalize = AudioFilters { normalize: filters.normalize, ..Default::default() };
+        assert_eq!(mono.filter(None, None).as_deref(), Some("aformat=channel_layouts=mono"));
+        assert_eq!(
+            normalize.filter(Some(&measured), Some(44100)).as_deref(),
+            Some("loudnorm=I=-23:TP=-1:LRA=11:measured_I=-27.61:measured_TP=-4.47:measured_LRA=18.06:measured_thresh=-39.2:offset=0.58:linear=true,aresample=44100")
+        );
+    }
+}
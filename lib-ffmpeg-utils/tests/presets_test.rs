@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use lib_ffmpeg_utils::presets::{builtin_presets, load_presets_from, merge_presets, save_presets_to, EncodePreset, Quality};
+
+    fn builtin(name: &str) -> EncodePreset {
+        builtin_presets().into_iter().find(|p| p.name == name).unwrap()
+    }
+
+    #[test]
+    fn builtin_presets_parse() {
+        let presets = builtin_presets();
+        assert!(presets.len() >= 3);
+        let archive = builtin("archive H.265 CRF 22");
+        assert_eq!(archive.quality, Quality::Crf { value: 22 });
+        assert_eq!(archive.container, "mp4");
+    }
+
+    #[test]
+    fn video_preset_arguments() {
+        let args = builtin("web H.264 720p").output_args();
+        assert_eq!(
+            args,
+            vec![
+                "-c:v", "libx264", "-crf", "23", "-preset", "fast", "-vf", "scale=-2:720",
+                "-c:a", "aac", "-b:a", "128k", "-movflags", "+faststart", "-pix_fmt", "yuv420p",
+            ]
+        );
+    }
+
+    #[test]
+    fn audio_only_preset_drops_video() {
+        let opus = builtin("audio-only opus");
+        assert!(!opus.has_video());
+        assert_eq!(opus.output_args(), vec!["-vn", "-c:a", "libopus", "-b:a", "96k"]);
+        assert_eq!(
+            opus.output_path(Path::new("/in/family.trip.mov"), Path::new("/out")),
+            Path::new("/out/family.trip.opus")
+        );
+    }
+
+    #[test]
+    fn bitrate_quality() {
+        let preset = EncodePreset {
+            quality: Quality::Bitrate { value: "2500k".to_string() },
+            ..EncodePreset::from_codecs("libx264", "aac", "mp4")
+        };
+        let args = preset.output_args();
+        assert_eq!(&args[..6], &["-c:v", "libx264", "-b:v", "2500k", "-preset", "fast"]);
+    }
+
+    #[test]
+    fn user_presets_override_and_extend() {
+        let yaml = r#"
+- name: web H.264 720p
+  container: mp4
+  video_codec: libx264
+  audio_codec: aac
+  quality: { mode: crf, value: 28 }
+- name: tiny
+  container: webm
+  video_codec: libvpx-vp9
+  audio_codec: libopus
+  fps: 24
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("presets.yaml");
+        std::fs::write(&path, yaml).unwrap();
+
+        let user = load_presets_from(&path).unwrap();
+        let builtin = builtin_presets();
+        let merged = merge_presets(builtin.clone(), user.clone());
+
+        assert_eq!(merged.len(), builtin.len() + 1);
+        let web = merged.iter().find(|p| p.name == "web H.264 720p").unwrap();
+        assert_eq!(web.quality, Quality::Crf { value: 28 });
+        assert_eq!(merged.last().unwrap().fps, Some(24.0));
+
+        // round trip
+        save_presets_to(&path, &user).unwrap();
+        assert_eq!(load_presets_from(&path).unwrap(), user);
+    }
+}
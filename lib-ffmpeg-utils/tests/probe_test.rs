@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use lib_ffmpeg_utils::probe::{parse_probe_output, parse_rational, CodecType};
+
+    #[test]
+    fn iphone_movie() {
+        let probe = parse_probe_output(include_str!("fixtures/iphone_mov.json")).unwrap();
+
+        assert_eq!(probe.format.filename, "IMG_4211.MOV");
+        assert_eq!(probe.format.size, Some(16_122_450));
+        assert_eq!(probe.format.bit_rate, Some(10_722_838));
+        assert_eq!(probe.duration(), Some(Duration::from_secs_f64(12.028571)));
+        assert_eq!(probe.format.tags.major_brand.as_deref(), Some("qt  "));
+        assert_eq!(probe.format.tags.location.as_deref(), Some("+35.6595+139.7005+040.000/"));
+        assert!(probe.chapters.is_empty());
+
+        let video = probe.video_stream().unwrap();
+        assert_eq!(video.codec_name.as_deref(), Some("hevc"));
+        assert_eq!(video.resolution(), Some((1920, 1080)));
+        assert_eq!(video.pix_fmt.as_deref(), Some("yuv420p10le"));
+        assert!((video.fps().unwrap() - 29.95).abs() < 0.01);
+        assert_eq!(video.nb_frames, Some(360));
+
+        let audio: Vec<_> = probe.audio_streams().collect();
+        assert_eq!(audio.len(), 1);
+        assert_eq!(audio[0].sample_rate, Some(44100));
+        assert_eq!(audio[0].channels, Some(2));
+        assert_eq!(audio[0].fps(), None);
+
+        assert_eq!(probe.streams[2].codec_type, CodecType::Data);
+        assert_eq!(probe.streams[2].codec_name, None);
+    }
+
+    #[test]
+    fn matroska_without_brand() {
+        let probe = parse_probe_output(include_str!("fixtures/concert_mkv.json")).unwrap();
+
+        assert_eq!(probe.format.tags.major_brand, None);
+        assert_eq!(probe.format.tags.title.as_deref(), Some("Summer Concert"));
+        assert_eq!(probe.format.tags.encoder.as_deref(), Some("Lavf60.16.100"));
+        // negative start times can't be represented
+        assert_eq!(probe.format.start_time, None);
+
+        let video = probe.video_stream().unwrap();
+        assert!((video.fps().unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(video.duration, None);
+
+        let languages: Vec<_> = probe.audio_streams().map(|s| s.language()).collect();
+        assert_eq!(languages, vec![Some("jpn"), Some("eng")]);
+        assert_eq!(probe.streams[2].tags.title.as_deref(), Some("Commentary"));
+        assert_eq!(probe.streams[1].channel_layout.as_deref(), Some("5.1"));
+
+        let subtitles: Vec<_> = probe.subtitle_streams().collect();
+        assert_eq!(subtitles.len(), 1);
+        assert!(subtitles[0].disposition.forced);
+        assert_eq!(subtitles[0].duration, None);
+
+        assert_eq!(probe.chapters.len(), 2);
+        assert_eq!(probe.chapters[0].tags.title.as_deref(), Some("Opening"));
+        assert_eq!(probe.chapters[1].tags.title.as_deref(), Some("Encore"));
+        assert_eq!(probe.chapters[1].start_time, Some(Duration::from_secs(95)));
+        assert_eq!(probe.chapters[1].end_time, Some(Duration::from_secs_f64(300.033)));
+    }
+
+    #[test]
+    fn audio_with_cover_art() {
+        let probe = parse_probe_output(include_str!("fixtures/podcast_mp3.json")).unwrap();
+
+        assert!(probe.video_stream().is_none());
+        assert_eq!(probe.streams_of(CodecType::Video).count(), 1);
+        assert_eq!(probe.format.tags.artist.as_deref(), Some("Rusty Radio"));
+        assert_eq!(probe.format.tags.date.as_deref(), Some("2024"));
+        assert_eq!(probe.audio_streams().next().unwrap().channel_layout.as_deref(), Some("mono"));
+    }
+
+    #[test]
+    fn empty_and_invalid_output() {
+        let probe = parse_probe_output("{}").unwrap();
+        assert!(probe.streams.is_empty());
+        assert_eq!(probe.duration(), None);
+
+        assert!(parse_probe_output("").is_err());
+        assert!(parse_probe_output("{\"format\": 12}").is_err());
+    }
+
+    #[test]
+    fn rationals() {
+        assert_eq!(parse_rational("25/1"), Some(25.0));
+        assert_eq!(parse_rational("0/0"), None);
+        assert_eq!(parse_rational("24"), Some(24.0));
+        assert_eq!(parse_rational("abc"), None);
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use lib_ffmpeg_utils::edit::{edit_args, EditInstructions};
+    use lib_ffmpeg_utils::presets::EncodePreset;
+    use lib_ffmpeg_utils::probe::parse_probe_output;
+    use lib_ffmpeg_utils::subtitles::{
+        burn_filter, escape_filter_value, extract_subtitle_args, sidecar_language, sidecar_subtitles, subtitle_output_path,
+        subtitle_tracks, SubtitleFormat, SubtitleTrack,
+    };
+
+    #[test]
+    fn lists_and_extracts_tracks() {
+        let probe = parse_probe_output(include_str!("fixtures/concert_mkv.json")).unwrap();
+        let tracks = subtitle_tracks(&probe);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].index, 3);
+        assert!(tracks[0].is_text());
+        assert_eq!(tracks[0].label(), "#0 fre (subrip) forced");
+
+        let video = Path::new("/videos/concert.mkv");
+        let output = subtitle_output_path(video, &tracks[0], SubtitleFormat::Ass);
+        assert_eq!(output, Path::new("/videos/concert.fre.ass"));
+        assert_eq!(
+            extract_subtitle_args(video, 0, SubtitleFormat::Ass, &output).join(" "),
+            "-y -i /videos/concert.mkv -map 0:s:0 -c:s ass /videos/concert.fre.ass"
+        );
+        let pgs = SubtitleTrack { codec: "hdmv_pgs_subtitle".to_string(), ..Default::default() };
+        assert!(!pgs.is_text());
+        assert_eq!("SRT".parse::<SubtitleFormat>(), Ok(SubtitleFormat::Srt));
+    }
+
+    #[test]
+    fn finds_sidecar_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["trip.mp4", "trip.srt", "trip.en.srt", "trip.2.en.srt", "trip 2.srt", "trip.en.ass"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        let video = dir.path().join("trip.mp4");
+        assert_eq!(sidecar_subtitles(&video), vec![dir.path().join("trip.en.srt"), dir.path().join("trip.srt")]);
+        assert_eq!(sidecar_language(&video, &dir.path().join("trip.en.srt")), Some("en".to_string()));
+        assert_eq!(sidecar_language(&video, &dir.path().join("trip.srt")), Some(String::new()));
+
+        let preset = EncodePreset::from_codecs("libx264", "aac", "mp4");
+        let edits = EditInstructions { sidecar_subtitles: true, start: Some(3.0), ..Default::default() };
+        let args = edit_args(&preset, &edits, &video, Path::new("out.mp4"), dir.path()).unwrap().join(" ");
+        let sidecar = dir.path().join("trip.en.srt");
+        assert!(args.contains(&format!("-ss 3 -i {}", sidecar.display())));
+        assert!(args.ends_with("-map 0:v:0? -map 0:a:0? -map 1:0 -map 2:0 -c:s mov_text -metadata:s:s:0 language=en out.mp4"));
+        assert_eq!(edits.summary(), "trim 0:03-, subtitles from .srt files");
+    }
+
+    #[test]
+    fn burns_a_track() {
+        assert_eq!(escape_filter_value("/a/it's:b.mkv"), r"/a/it\\\'s\\:b.mkv");
+        assert_eq!(burn_filter(Path::new("in.mkv"), 1, None), "subtitles=filename=in.mkv:si=1");
+        assert_eq!(
+            burn_filter(Path::new("in.mkv"), 0, Some(5.0)),
+            "setpts=PTS+5/TB,subtitles=filename=in.mkv:si=0,setpts=PTS-STARTPTS"
+        );
+
+        let edits = EditInstructions { burn_subtitles: Some(0), rotate: "cw".parse().unwrap(), ..Default::default() };
+        assert_eq!(edits.video_filters(Path::new("in.mkv")), vec!["transpose=clock", "subtitles=filename=in.mkv:si=0"]);
+    }
+}
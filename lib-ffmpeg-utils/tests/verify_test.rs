@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use lib_ffmpeg_utils::presets::EncodePreset;
+    use lib_ffmpeg_utils::probe::{parse_probe_output, ProbeResult};
+    use lib_ffmpeg_utils::verify::{check_output, duration_tolerance, StreamCounts};
+
+    fn source() -> ProbeResult {
+        parse_probe_output(include_str!("fixtures/iphone_mov.json")).unwrap()
+    }
+
+    fn output(duration: &str, codec_types: &[&str]) -> ProbeResult {
+        let streams: Vec<String> = codec_types
+            .iter()
+            .enumerate()
+            .map(|(index, codec_type)| format!("{{\"index\": {}, \"codec_type\": \"{}\"}}", index, codec_type))
+            .collect();
+        let json = format!("{{\"format\": {{\"duration\": \"{}\"}}, \"streams\": [{}]}}", duration, streams.join(","));
+        parse_probe_output(&json).unwrap()
+    }
+
+    #[test]
+    fn complete_encode_passes() {
+        let preset = EncodePreset::from_codecs("libx265", "aac", "mp4");
+        // the data stream of the source is not copied by ffmpeg
+        assert_eq!(StreamCounts::expected(&source(), &preset), StreamCounts { video: 1, audio: 1 });
+        assert!(check_output(&source(), &output("12.05", &["video", "audio"]), &preset, None).is_ok());
+    }
+
+    #[test]
+    fn truncated_encode_fails() {
+        let preset = EncodePreset::from_codecs("libx265", "aac", "mp4");
+        let error = check_output(&source(), &output("4.2", &["video", "audio"]), &preset, None).unwrap_err();
+        assert_eq!(error, "output lasts 4.2s, expected 12.0s");
+        assert!(check_output(&source(), &output("N/A", &["video", "audio"]), &preset, None).is_err());
+    }
+
+    #[test]
+    fn missing_streams_fail() {
+        let preset = EncodePreset::from_codecs("libx265", "aac", "mp4");
+        let error = check_output(&source(), &output("12.0", &["video"]), &preset, None).unwrap_err();
+        assert_eq!(error, "output has 1 video and 0 audio streams, expected 1 and 1");
+
+        let audio_only = EncodePreset { video_codec: None, ..preset };
+        assert!(check_output(&source(), &output("12.0", &["audio"]), &audio_only, None).is_ok());
+    }
+
+    #[test]
+    fn tolerance_grows_with_duration() {
+        assert_eq!(duration_tolerance(Duration::from_secs(10)), Duration::from_secs(1));
+        assert_eq!(duration_tolerance(Duration::from_secs(3600)), Duration::from_secs(36));
+    }
+}
@@ -1,53 +1,95 @@
-use std::process::Command;
-use std::io;
-use reqwest;
-use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use clap::{Arg, ArgAction, Command};
+use ui_download_audio::download::{default_output_folder, download, fetch_info, DownloadEvent, DownloadOptions, DEFAULT_NAME_TEMPLATE};
+use ui_download_audio::tags::PendingTags;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Ask the user for the YouTube video URL
-    println!("Enter the YouTube video URL:");
-    let mut video_url = String::new();
-    io::stdin().read_line(&mut video_url).expect("Failed to read input");
-    let video_url = video_url.trim(); // Remove any trailing newline or spaces
+fn main() {
+    let matches = Command::new("Download")
+        .version("1.0")
+        .about("Downloads videos and playlists with yt-dlp")
+        .arg(Arg::new("url").value_name("URL").help("Video or playlist, asked for when missing"))
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FOLDER")
+                .help("Folder to save to [default: the downloads folder]"),
+        )
+        .arg(
+            Arg::new("audio")
+                .short('x')
+                .long("audio")
+                .value_name("FORMAT")
+                .help("Keeps only the audio, in this format")
+                .num_args(0..=1)
+                .default_missing_value("mp3"),
+        )
+        .arg(Arg::new("no-tags").long("no-tags").help("Leaves extracted audio untagged and without cover").action(ArgAction::SetTrue))
+        .arg(Arg::new("info").long("info").help("Lists what would be downloaded").action(ArgAction::SetTrue))
+        .get_matches();
 
-    // Fetch the HTML content of the page
-    let html_content = reqwest::blocking::get(video_url)?.text()?;
-
-    // Parse the HTML to extract the `meta` tag content
-    let document = Html::parse_document(&html_content);
-    let selector = Selector::parse(r#"meta[name="twitter:title"]"#).unwrap();
-
-    let title = if let Some(element) = document.select(&selector).next() {
-        element.value().attr("content").unwrap_or("video")
-    } else {
-        "video"
+    let url = match matches.get_one::<String>("url") {
+        Some(url) => url.clone(),
+        None => {
+            println!("Enter the YouTube video URL:");
+            let mut url = String::new();
+            io::stdin().read_line(&mut url).expect("Failed to read input");
+            url.trim().to_string()
+        }
     };
 
-    // Sanitize the title for file name
-    let sanitized_title = title
-        .replace(|c: char| !c.is_alphanumeric() && c != ' ', "_")
-        .trim()
-        .to_string();
-    let output_path = format!("{}.mp4", sanitized_title);
-
-    // Execute yt-dlp command
-    let status = Command::new("yt-dlp")
-        .arg("-o")
-        .arg(&output_path) // Specify the dynamic output file path
-        .arg(video_url)    // Pass the video URL
-        .status();
+    let videos = match fetch_info(&url) {
+        Ok(videos) => videos,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    if let Some(playlist) = videos.iter().find_map(|video| video.playlist_title.as_ref()) {
+        println!("{} ({} videos)", playlist, videos.len());
+    }
+    if matches.get_flag("info") {
+        for video in &videos {
+            println!("{}  {}  {}", video.id, video.title, video.author().unwrap_or_default());
+        }
+        return;
+    }
 
-    match status {
-        Ok(exit_status) if exit_status.success() => {
-            println!("Video downloaded successfully to {}", output_path);
+    let options = DownloadOptions {
+        output_folder: matches.get_one::<String>("output").map(PathBuf::from).unwrap_or_else(default_output_folder),
+        name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+        audio_format: matches.get_one::<String>("audio").cloned(),
+    };
+    let tag = options.audio_format.is_some() && !matches.get_flag("no-tags");
+    let mut infos: HashMap<String, _> = videos.into_iter().map(|video| (video.id.clone(), video)).collect();
+    let mut saved = Vec::new();
+    let result = download(&url, &options, |event| match event {
+        DownloadEvent::Progress(progress) => {
+            let percent = progress.fraction().map(|fraction| format!("{:5.1}%", fraction * 100.0)).unwrap_or_default();
+            print!("\r{} {} {}", progress.id, progress.status, percent);
+            let _ = io::stdout().flush();
         }
-        Ok(exit_status) => {
-            eprintln!("yt-dlp exited with status: {}", exit_status);
+        DownloadEvent::Metadata(info) => {
+            infos.insert(info.id.clone(), info);
         }
-        Err(e) => {
-            eprintln!("Failed to execute yt-dlp: {}", e);
+        DownloadEvent::Finished { id, path } => {
+            println!("\rSaved {}", path.display());
+            saved.push((id, path));
         }
+        DownloadEvent::Failed { message, .. } => eprintln!("\r{}", message),
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(1);
     }
 
-    Ok(())
+    for (id, path) in saved.iter().filter(|_| tag) {
+        let Some(info) = infos.get(id) else { continue };
+        if let Err(e) = PendingTags::new(path, info).save() {
+            eprintln!("Failed to tag {}: {}", path.display(), e);
+        }
+    }
 }
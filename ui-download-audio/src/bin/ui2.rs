@@ -1,35 +1,46 @@
-use std::env::home_dir;
 use eframe::egui;
-use std::process::{Command, Stdio};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::io::{BufRead, BufReader};
 use lib_egui_utils::my_default_options;
-use lib_ffmpeg_utils::utils::path_for;
+use lib_ffmpeg_utils::loudness::{AudioFilters, LoudnessTarget};
+use rfd::FileDialog;
+use ui_download_audio::download::{DownloadOptions, ItemStatus};
+use ui_download_audio::history::{default_history_file, parse_urls, DownloadHistory};
+use ui_download_audio::queue::{run_queue, DownloadQueue, Notify, QueueStatus};
+use ui_download_audio::tags::{supports_cover, PendingTags};
 
 fn main() -> Result<(), eframe::Error> {
     let options =
-        my_default_options(500.0, 300.0, include_bytes!("../../icon.png"));
+        my_default_options(600.0, 600.0, include_bytes!("../../icon.png"));
 
     eframe::run_native("YouTube Downloader", options, Box::new(|_cc| Ok(Box::new(MyApp::default()))))
 }
 
 struct MyApp {
-    youtube_url: String,
-    is_downloading: Arc<Mutex<bool>>,
-    downloaded_file: Arc<Mutex<Option<String>>>,
-    download_status: Arc<Mutex<Option<String>>>,
+    urls: String,
+    options: DownloadOptions,
     extract_audio: bool,
+    filters: AudioFilters,
+    queue: Arc<Mutex<DownloadQueue>>,
+    history: Arc<Mutex<DownloadHistory>>,
+    status: Option<String>,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
+        let path = default_history_file();
+        let (history, status) = match DownloadHistory::load(&path) {
+            Ok(history) => (history, None),
+            Err(e) => (DownloadHistory::new(&path), Some(format!("Failed to read the history: {}", e))),
+        };
         Self {
-            youtube_url: String::new(),
-            is_downloading: Arc::new(Mutex::new(false)),
-            downloaded_file: Arc::new(Mutex::new(None)),
-            download_status: Arc::new(Mutex::new(None)),
+            urls: String::new(),
+            options: DownloadOptions::default(),
             extract_audio: false,
+            filters: AudioFilters::default(),
+            queue: Arc::new(Mutex::new(DownloadQueue::default())),
+            history: Arc::new(Mutex::new(history)),
+            status,
         }
     }
 }
@@ -39,105 +50,259 @@ impl eframe::App for MyApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("YouTube Downloader");
 
-            // Text input for YouTube URL
-            ui.label("Enter YouTube URL:");
-            ui.text_edit_singleline(&mut self.youtube_url);
+            ui.label("YouTube URLs, one per line:");
+            ui.add(egui::TextEdit::multiline(&mut self.urls).desired_rows(3).desired_width(f32::INFINITY));
 
+            ui.horizontal(|ui| {
+                ui.label(format!("Save to: {}", self.options.output_folder.display()));
+                if ui.button("Change...").clicked() {
+                    if let Some(folder) = FileDialog::new().set_directory(&self.options.output_folder).pick_folder() {
+                        self.options.output_folder = folder;
+                    }
+                }
+            });
 
-            // Checkbox for extracting audio
-            ui.checkbox(&mut self.extract_audio, "Extract audio");
-
-
-            let is_downloading = self.is_downloading.clone();
-            let download_status = self.download_status.clone();
-            let extract_audio = self.extract_audio;
-
-            // Download button
-            if ui.button("Download").clicked() && !*is_downloading.lock().unwrap() {
-                let youtube_url = self.youtube_url.clone();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.extract_audio, "Extract audio");
+                let mut queue = self.queue.lock().unwrap();
+                ui.add_enabled(self.extract_audio, egui::Checkbox::new(&mut queue.review_tags, "Review tags"))
+                    .on_hover_text("Lets you fix the tags of the audio before they are saved");
+                ui.label("Parallel downloads:");
+                ui.add(egui::DragValue::new(&mut queue.max_parallel).range(1..=4));
+            });
 
-                // Start the download process in a separate thread
-                *is_downloading.lock().unwrap() = true;
-                *download_status.lock().unwrap() = None;
-                let downloaded_file = self.downloaded_file.clone();
-                *downloaded_file.lock().unwrap() = None;
+            ui.horizontal(|ui| {
+                let mut normalize = self.filters.normalize.is_some();
+                if ui.checkbox(&mut normalize, "Normalize loudness").changed() {
+                    self.filters.normalize = normalize.then(LoudnessTarget::default);
+                }
+                if let Some(target) = &mut self.filters.normalize {
+                    ui.add(egui::DragValue::new(&mut target.integrated).range(-31.0..=-5.0).speed(0.5).suffix(" LUFS"));
+                }
+                ui.checkbox(&mut self.filters.trim_silence, "Trim silence");
+                ui.checkbox(&mut self.filters.mono, "Mono");
+            });
 
-                thread::spawn(move || {
-                    let (result, file_name) = download_youtube_video(&youtube_url, extract_audio);
-                    *is_downloading.lock().unwrap() = false;
-                    *download_status.lock().unwrap() = result;
-                    *downloaded_file.lock().unwrap() = file_name;
-                });
+            ui.horizontal(|ui| {
+                if ui.button("Add to queue").clicked() {
+                    self.add_urls(ctx);
+                }
+                if ui.button("Clear finished").clicked() {
+                    self.queue.lock().unwrap().clear_finished();
+                }
+            });
+            if let Some(status) = &self.status {
+                ui.label(status);
             }
 
-            // Display the download status
-            let is_downloading = self.is_downloading.clone();
-            if *is_downloading.lock().unwrap() {
-                ui.label("Downloading...");
+            ui.separator();
+            let mut retry = None;
+            let mut edit = None;
+            egui::ScrollArea::vertical().id_salt("queue").max_height(300.0).show(ui, |ui| {
+                for item in self.queue.lock().unwrap().items.iter() {
+                    ui.horizontal(|ui| {
+                        match &item.status {
+                            QueueStatus::Queued => {
+                                ui.weak(item.name());
+                            }
+                            QueueStatus::LookingUp => {
+                                ui.label(item.name());
+                                ui.spinner();
+                            }
+                            QueueStatus::Downloading | QueueStatus::Processing => {
+                                ui.label(item.name());
+                                if item.videos.len() > 1 {
+                                    ui.label(format!("{}/{}", item.done(), item.videos.len()));
+                                }
+                                ui.spinner();
+                            }
+                            QueueStatus::Done(paths) => {
+                                ui.label(format!("{}: {} files", item.name(), paths.len()));
+                            }
+                            QueueStatus::Failed(message) => {
+                                ui.colored_label(egui::Color32::LIGHT_RED, item.name()).on_hover_text(message);
+                                if ui.small_button("Retry").clicked() {
+                                    retry = Some(item.id);
+                                }
+                            }
+                            QueueStatus::AlreadyDownloaded(file) => {
+                                ui.label(format!("{}: already downloaded", item.name()));
+                                if ui.small_button("Open existing").on_hover_text(file.display().to_string()).clicked() {
+                                    open::that(file).unwrap_or_default();
+                                }
+                                if ui.small_button("Download again").clicked() {
+                                    retry = Some(item.id);
+                                }
+                            }
+                        }
+                    });
+                    if matches!(item.status, QueueStatus::Downloading | QueueStatus::Processing | QueueStatus::Done(_)) {
+                        ui.indent(item.id, |ui| {
+                            for video in &item.videos {
+                                ui.horizontal(|ui| {
+                                    let title = if video.info.title.is_empty() { &video.info.id } else { &video.info.title };
+                                    match &video.status {
+                                        ItemStatus::Waiting => {
+                                            ui.weak(title);
+                                        }
+                                        ItemStatus::Downloading(fraction) => {
+                                            ui.label(title);
+                                            let bar = egui::ProgressBar::new(fraction.unwrap_or(0.0)).desired_width(120.0);
+                                            ui.add(if fraction.is_some() { bar.show_percentage() } else { bar.animate(true) });
+                                        }
+                                        ItemStatus::Processing => {
+                                            ui.label(title);
+                                            ui.spinner();
+                                        }
+                                        ItemStatus::Done(path) => {
+                                            if ui.link(title).on_hover_text(path.display().to_string()).clicked() {
+                                                open::that(path).unwrap_or_default();
+                                            }
+                                            if item.options.audio_format.is_some() && ui.small_button("Tags").clicked() {
+                                                edit = Some(path.clone());
+                                            }
+                                        }
+                                        ItemStatus::Failed(message) => {
+                                            ui.colored_label(egui::Color32::LIGHT_RED, title).on_hover_text(message);
+                                        }
+                                    }
+                                });
+                            }
+                            for message in &item.messages {
+                                ui.small(message);
+                            }
+                        });
+                    }
+                }
+            });
+            if let Some(id) = retry {
+                self.queue.lock().unwrap().retry(id);
+                self.run(ctx);
             }
-
-            let download_status = self.download_status.clone();
-            if let Some(status) = &*download_status.lock().unwrap() {
-                ui.label(status);
+            if let Some(file) = edit {
+                self.edit_tags(&file);
             }
 
-            // Display the downloaded file name
-            let downloaded_file = self.downloaded_file.clone();
-            if let Some(file_name) = &*downloaded_file.lock().unwrap() {
-                if ui.label(format!("Downloaded File: {}", file_name)).clicked() {
-                    println!("Filename: {}", file_name.clone());
-                    open::that(file_name).unwrap_or_default();
+            ui.separator();
+            egui::CollapsingHeader::new("History").show(ui, |ui| {
+                let mut edit = None;
+                let mut history = self.history.lock().unwrap();
+                egui::ScrollArea::vertical().id_salt("history").show(ui, |ui| {
+                    for entry in history.entries().iter().rev() {
+                        ui.horizontal(|ui| {
+                            ui.weak(&entry.date);
+                            if entry.file.exists() {
+                                if ui.link(&entry.title).on_hover_text(entry.file.display().to_string()).clicked() {
+                                    open::that(&entry.file).unwrap_or_default();
+                                }
+                            } else {
+                                ui.label(&entry.title).on_hover_text("File is gone");
+                            }
+                            if ui.small_button("Page").on_hover_text(&entry.url).clicked() {
+                                open::that(&entry.url).unwrap_or_default();
+                            }
+                            if entry.file.exists() && ui.small_button("Tags").clicked() {
+                                edit = Some(entry.file.clone());
+                            }
+                        });
+                    }
+                });
+                if ui.button("Clear history").clicked() {
+                    history.clear();
+                    if let Err(e) = history.save() {
+                        self.status = Some(format!("Failed to save the history: {}", e));
+                    }
+                }
+                drop(history);
+                if let Some(file) = edit {
+                    self.edit_tags(&file);
                 }
-            };
+            });
         });
+
+        self.tag_editor(ctx);
     }
 }
-fn download_youtube_video(url: &str, extract_audio: bool) -> (Option<String>, Option<String>) {
-    if url.is_empty() {
-        return (Some("Please enter a valid URL.".to_string()), None);
-    }
-    let mut binding = Command::new(path_for("yt-dlp"));
-    let mut command = binding
-        .arg("--ffmpeg-location")
-        .arg(path_for("ffmpeg"))
-        .arg("-o")
-        //.arg("--force-overwrites")
-        .arg(format!("{:}/Desktop/%(title)s.%(ext)s", home_dir().unwrap().to_string_lossy().to_string())); // Dynamic filename based on video title
-
-    println!("{:?}", extract_audio);
-    if extract_audio {
-        command.arg("-x").arg("--audio-format").arg("mp3"); // Add the -x argument for audio extraction
-    }
 
-    command.arg(url).stdout(Stdio::piped());
+impl MyApp {
+    /// Queues the pasted urls and starts downloading them
+    fn add_urls(&mut self, ctx: &egui::Context) {
+        let urls = parse_urls(&self.urls);
+        if urls.is_empty() {
+            self.status = Some("Please enter a valid URL.".to_string());
+            return;
+        }
 
-    if let Ok(mut child) = command.spawn() {
-        let stdout = BufReader::new(child.stdout.take().unwrap());
-        let mut file_path = None;
+        let mut options = self.options.clone();
+        options.audio_format = self.extract_audio.then(|| "mp3".to_string());
+        let added = self.queue.lock().unwrap().add(&urls, &options, &self.filters, &self.history.lock().unwrap());
+        self.status = Some(format!("Added {} of {} URLs.", added, urls.len()));
+        self.urls.clear();
+        self.run(ctx);
+    }
 
-        // Read the yt-dlp output line by line
-        for line in stdout.lines() {
-            if let Ok(line) = line {
-                // Detect the line with "Destination: " to extract the file name
-                if let Some(path) = line.strip_prefix("[download] Destination: ") {
-                    file_path = Some(path.to_string());
-                }
-            }
+    /// Opens the tags of a saved file in the editor
+    fn edit_tags(&mut self, file: &Path) {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.pending_tags.iter().any(|pending| pending.file == file) {
+            return;
         }
+        match PendingTags::open(file) {
+            Ok(pending) => queue.pending_tags.push(pending),
+            Err(e) => self.status = Some(e),
+        }
+    }
 
-        // Wait for the process to finish
-        if let Ok(status) = child.wait() {
-            if status.success() {
-                if let Some(path) = file_path {
-                    return (Some("Download successful.".to_string()), Some(path));
-                } else {
-                    return (Some("Download successful, but file name is missing.".to_string()), None);
+    /// Window with the tags waiting to be saved
+    fn tag_editor(&mut self, ctx: &egui::Context) {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.pending_tags.is_empty() {
+            return;
+        }
+        let mut done = None;
+        egui::Window::new("Tags").collapsible(false).show(ctx, |ui| {
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for (index, pending) in queue.pending_tags.iter_mut().enumerate() {
+                    ui.strong(pending.file.file_name().unwrap_or_default().to_string_lossy());
+                    egui::Grid::new(index).num_columns(2).show(ui, |ui| {
+                        let tags = &mut pending.tags;
+                        for (label, value) in [("Title", &mut tags.title), ("Artist", &mut tags.artist), ("Album", &mut tags.album), ("Date", &mut tags.date), ("URL", &mut tags.url)] {
+                            ui.label(label);
+                            ui.text_edit_singleline(value);
+                            ui.end_row();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let can_embed = pending.cover.is_some() && supports_cover(&pending.file);
+                        ui.add_enabled(can_embed, egui::Checkbox::new(&mut pending.embed_cover, "Embed cover"));
+                        if ui.button("Save").clicked() {
+                            done = Some((index, true));
+                        }
+                        if ui.button("Skip").clicked() {
+                            done = Some((index, false));
+                        }
+                    });
+                    ui.separator();
                 }
+            });
+        });
+
+        if let Some((index, save)) = done {
+            let pending = queue.pending_tags.remove(index);
+            drop(queue);
+            if !save {
+                pending.discard();
+            } else if let Err(e) = pending.save() {
+                self.status = Some(format!("Failed to tag {}: {}", pending.file.display(), e));
             } else {
-                return (Some(format!("yt-dlp exited with status: {}", status)), None);
+                self.status = Some(format!("Tagged {}.", pending.file.display()));
             }
         }
     }
 
-    (Some("Failed to start yt-dlp.".to_string()), None)
-}
\ No newline at end of file
+    fn run(&self, ctx: &egui::Context) {
+        let ctx = ctx.clone();
+        let notify: Notify = Arc::new(move || ctx.request_repaint());
+        run_queue(&self.queue, &self.history, &notify);
+    }
+}
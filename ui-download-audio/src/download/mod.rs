@@ -0,0 +1,286 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use serde::Deserialize;
+use lib_ffmpeg_utils::utils::path_for;
+
+/// Progress lines, one field per value so they can be split on spaces, yt-dlp writes NA for the unknown ones
+const PROGRESS_TEMPLATE: &str = "download:[progress] %(info.id)s %(progress.status)s %(progress.downloaded_bytes)s \
+%(progress.total_bytes)s %(progress.total_bytes_estimate)s %(progress.speed)s %(progress.eta)s";
+/// Full metadata of each video, flat playlist entries lack the uploader and the date
+const METADATA_TEMPLATE: &str = "after_move:[metadata] %(.{id,title,uploader,channel,duration,webpage_url,upload_date,thumbnail,playlist_title,playlist_index})j";
+/// Printed once a file is in its final place, after the audio extraction and merges
+const FILE_TEMPLATE: &str = "after_move:[file] %(id)s %(filepath)s";
+/// Default name of the downloaded files, in the output folder
+pub const DEFAULT_NAME_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+/// What yt-dlp knows about a video before downloading it
+///
+/// Playlist entries are listed flat, they only have an id, a title, a url and sometimes a duration.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct VideoInfo {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    pub uploader: Option<String>,
+    pub channel: Option<String>,
+    /// Seconds
+    pub duration: Option<f64>,
+    pub webpage_url: Option<String>,
+    pub url: Option<String>,
+    /// YYYYMMDD
+    pub upload_date: Option<String>,
+    pub thumbnail: Option<String>,
+    pub playlist_title: Option<String>,
+    pub playlist_index: Option<u32>,
+}
+
+impl VideoInfo {
+    /// Page of the video, the url of flat playlist entries
+    pub fn link(&self) -> Option<&str> {
+        self.webpage_url.as_deref().or(self.url.as_deref())
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.uploader.as_deref().or(self.channel.as_deref())
+    }
+}
+
+/// Where and how videos are downloaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadOptions {
+    pub output_folder: PathBuf,
+    /// yt-dlp output template, relative to the folder
+    pub name_template: String,
+    /// Keeps only the audio, converted to this format, like `mp3`
+    pub audio_format: Option<String>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            output_folder: default_output_folder(),
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            audio_format: None,
+        }
+    }
+}
+
+/// The downloads folder, or the home folder on systems without one
+pub fn default_output_folder() -> PathBuf {
+    dirs::download_dir().or_else(dirs::home_dir).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// What a running download reports, items are told apart by their video id
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadEvent {
+    Progress(Progress),
+    /// Everything yt-dlp knows about a downloaded video
+    Metadata(VideoInfo),
+    /// The file is in its final place
+    Finished { id: String, path: PathBuf },
+    /// An item failed, the others of a playlist go on
+    Failed { id: Option<String>, message: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub id: String,
+    /// `downloading`, or `finished` once the data is there and before it is processed
+    pub status: String,
+    pub downloaded: u64,
+    /// Bytes, exact or estimated
+    pub total: Option<u64>,
+    /// Bytes per second
+    pub speed: Option<f64>,
+    /// Seconds
+    pub eta: Option<u64>,
+}
+
+impl Progress {
+    /// Between 0 and 1, when the size is known
+    pub fn fraction(&self) -> Option<f32> {
+        self.total.filter(|total| *total > 0).map(|total| (self.downloaded as f64 / total as f64).min(1.0) as f32)
+    }
+}
+
+/// Arguments of the metadata pass, one json line per video, playlists are not expanded
+pub fn info_args(url: &str) -> Vec<String> {
+    ["--dump-json", "--flat-playlist", "--no-warnings", url].map(String::from).to_vec()
+}
+
+/// Arguments of the download, progress and final paths are printed on stdout
+pub fn download_args(url: &str, options: &DownloadOptions) -> Vec<String> {
+    let output = options.output_folder.join(&options.name_template);
+    let mut args = vec!["--ffmpeg-location".to_string(), path_for("ffmpeg")];
+    args.extend(["--quiet", "--progress", "--newline", "--no-warnings", "--ignore-errors"].map(String::from));
+    args.extend(["--progress-template", PROGRESS_TEMPLATE, "--print", METADATA_TEMPLATE, "--print", FILE_TEMPLATE].map(String::from));
+    args.extend(["-o".to_string(), output.to_string_lossy().to_string()]);
+    if let Some(format) = &options.audio_format {
+        args.extend(["-x".to_string(), "--audio-format".to_string(), format.clone()]);
+        // saved next to the audio as a jpg, to be embedded as its cover
+        args.extend(["--write-thumbnail", "--convert-thumbnails", "jpg"].map(String::from));
+    }
+    args.push(url.to_string());
+    args
+}
+
+/// Reads the json lines of the metadata pass
+pub fn parse_info(stdout: &str) -> Result<Vec<VideoInfo>, String> {
+    stdout
+        .lines()
+        .filter(|line| line.trim_start().starts_with('{'))
+        .map(|line| serde_json::from_str(line).map_err(|e| format!("Unexpected yt-dlp output: {}", e)))
+        .collect()
+}
+
+/// Event of a line printed by yt-dlp, `None` for anything else
+pub fn parse_line(line: &str) -> Option<DownloadEvent> {
+    if let Some(rest) = line.strip_prefix("[progress] ") {
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let [id, status, downloaded, total, estimate, speed, eta] = fields[..] else {
+            return None;
+        };
+        let number = |value: &str| value.parse::<f64>().ok().filter(|number| number.is_finite());
+        return Some(DownloadEvent::Progress(Progress {
+            id: id.to_string(),
+            status: status.to_string(),
+            downloaded: number(downloaded).unwrap_or(0.0) as u64,
+            total: number(total).or_else(|| number(estimate)).map(|total| total as u64),
+            speed: number(speed),
+            eta: number(eta).map(|eta| eta as u64),
+        }));
+    }
+    if let Some(json) = line.strip_prefix("[metadata] ") {
+        return serde_json::from_str(json).ok().map(DownloadEvent::Metadata);
+    }
+    if let Some(rest) = line.strip_prefix("[file] ") {
+        let (id, path) = rest.split_once(' ')?;
+        return Some(DownloadEvent::Finished { id: id.to_string(), path: PathBuf::from(path) });
+    }
+    // ERROR: [youtube] dQw4w9WgXcQ: Video unavailable
+    let message = line.strip_prefix("ERROR: ")?;
+    let id = message
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .and_then(|(_, rest)| rest.split_once(": "))
+        .map(|(id, _)| id.to_string());
+    Some(DownloadEvent::Failed { id, message: message.to_string() })
+}
+
+/// Metadata of a video, or of every entry of a playlist, without downloading anything
+pub fn fetch_info(url: &str) -> Result<Vec<VideoInfo>, String> {
+    let output = Command::new(path_for("yt-dlp"))
+        .args(info_args(url))
+        .output()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("yt-dlp failed").to_string());
+    }
+    let videos = parse_info(&String::from_utf8_lossy(&output.stdout))?;
+    if videos.is_empty() {
+        return Err(format!("Nothing to download at {}", url));
+    }
+    Ok(videos)
+}
+
+/// Downloads a video or a playlist, calling `on_event` as yt-dlp goes, returns the saved files
+///
+/// Failed playlist items are reported as events, the call only fails when nothing was saved.
+pub fn download(url: &str, options: &DownloadOptions, mut on_event: impl FnMut(DownloadEvent)) -> Result<Vec<PathBuf>, String> {
+    let mut child = Command::new(path_for("yt-dlp"))
+        .args(download_args(url, options))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+
+    // progress comes on stdout and errors on stderr, both are read at once so neither pipe fills up
+    let (sender, receiver) = mpsc::channel();
+    forward_events(child.stdout.take().unwrap(), sender.clone());
+    forward_events(child.stderr.take().unwrap(), sender);
+
+    let mut paths = Vec::new();
+    let mut last_error = None;
+    for event in receiver {
+        match &event {
+            DownloadEvent::Finished { path, .. } => paths.push(path.clone()),
+            DownloadEvent::Failed { message, .. } => last_error = Some(message.clone()),
+            DownloadEvent::Progress(_) | DownloadEvent::Metadata(_) => {}
+        }
+        on_event(event);
+    }
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for yt-dlp: {}", e))?;
+    if paths.is_empty() {
+        return Err(last_error.unwrap_or_else(|| format!("yt-dlp exited with status: {}", status)));
+    }
+    Ok(paths)
+}
+
+fn forward_events(stream: impl Read + Send + 'static, sender: Sender<DownloadEvent>) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if let Some(event) = parse_line(&line) {
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Where one video of a download stands
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ItemStatus {
+    #[default]
+    Waiting,
+    /// Share downloaded, when the size is known
+    Downloading(Option<f32>),
+    /// Downloaded, being converted or merged
+    Processing,
+    Done(PathBuf),
+    Failed(String),
+}
+
+/// A video of a download, with its status
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadItem {
+    pub info: VideoInfo,
+    pub status: ItemStatus,
+}
+
+impl DownloadItem {
+    pub fn new(info: VideoInfo) -> Self {
+        DownloadItem { info, status: ItemStatus::Waiting }
+    }
+}
+
+/// Updates the status of the item an event is about
+pub fn update_items(items: &mut [DownloadItem], event: &DownloadEvent) {
+    if let DownloadEvent::Metadata(info) = event {
+        if let Some(item) = items.iter_mut().find(|item| item.info.id == info.id) {
+            item.info = info.clone();
+        }
+        return;
+    }
+    let id = match event {
+        DownloadEvent::Progress(progress) => Some(&progress.id),
+        DownloadEvent::Finished { id, .. } => Some(id),
+        DownloadEvent::Failed { id, .. } => id.as_ref(),
+        DownloadEvent::Metadata(_) => None,
+    };
+    let Some(item) = items.iter_mut().find(|item| Some(&item.info.id) == id) else {
+        return;
+    };
+    item.status = match event {
+        DownloadEvent::Progress(progress) if progress.status == "finished" => ItemStatus::Processing,
+        DownloadEvent::Progress(progress) => ItemStatus::Downloading(progress.fraction()),
+        DownloadEvent::Finished { path, .. } => ItemStatus::Done(path.clone()),
+        DownloadEvent::Failed { message, .. } => ItemStatus::Failed(message.clone()),
+        DownloadEvent::Metadata(_) => return,
+    };
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A video that was downloaded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub url: String,
+    /// Video id given by yt-dlp
+    pub id: String,
+    pub title: String,
+    pub file: PathBuf,
+    /// Local time, like 2024-08-11 09:12
+    pub date: String,
+}
+
+/// Every download, newest last, saved after each one
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct DownloadHistory {
+    entries: Vec<HistoryEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl DownloadHistory {
+    /// An empty history that will be saved to `path`
+    pub fn new(path: &Path) -> Self {
+        DownloadHistory { path: path.to_path_buf(), ..Default::default() }
+    }
+
+    /// Loads the history from `path`, a missing file gives an empty history
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::new(path));
+        }
+        let mut history: DownloadHistory = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        history.path = path.to_path_buf();
+        Ok(history)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let yaml = serde_yaml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, yaml)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Records a download, replacing an older one of the same video
+    pub fn record(&mut self, url: &str, id: &str, title: &str, file: &Path) {
+        self.entries.retain(|entry| entry.id != id);
+        self.entries.push(HistoryEntry {
+            url: url.to_string(),
+            id: id.to_string(),
+            title: title.to_string(),
+            file: file.to_path_buf(),
+            date: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        });
+    }
+
+    /// Latest download of a url or of a video id whose file is still there
+    pub fn find(&self, url_or_id: &str) -> Option<&HistoryEntry> {
+        let url_or_id = url_or_id.trim();
+        let id = video_id(url_or_id);
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.file.exists())
+            .find(|entry| entry.url == url_or_id || entry.id == url_or_id || id.as_deref() == Some(entry.id.as_str()))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// History file of the downloader
+pub fn default_history_file() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rusty-toolbox")
+        .join("download-audio")
+        .join("history.yaml")
+}
+
+/// Id of a YouTube video from the usual forms of its url, so the same video is found behind any of them
+pub fn video_id(url: &str) -> Option<String> {
+    let re = Regex::new(r"(?:youtube\.com/(?:watch\?(?:.*&)?v=|shorts/|embed/|live/)|youtu\.be/)([A-Za-z0-9_-]{11})").unwrap();
+    re.captures(url).map(|caps| caps[1].to_string())
+}
+
+/// The urls in pasted text, one per line or separated by spaces, each once
+pub fn parse_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let word = word.trim_matches(|c| c == '<' || c == '>' || c == '"' || c == ',');
+        if (word.starts_with("https://") || word.starts_with("http://")) && !urls.iter().any(|url| url == word) {
+            urls.push(word.to_string());
+        }
+    }
+    urls
+}
@@ -0,0 +1,4 @@
+pub mod download;
+pub mod history;
+pub mod queue;
+pub mod tags;
@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use lib_ffmpeg_utils::loudness::{filter_in_place, AudioFilters};
+use crate::download::{download, fetch_info, update_items, DownloadItem, DownloadOptions, ItemStatus};
+use crate::history::DownloadHistory;
+use crate::tags::PendingTags;
+
+/// Downloads running at the same time by default
+pub const DEFAULT_PARALLEL_DOWNLOADS: usize = 2;
+
+/// Called from the download threads whenever the queue changes, to refresh a window
+pub type Notify = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueStatus {
+    Queued,
+    /// Fetching the metadata
+    LookingUp,
+    Downloading,
+    /// Normalizing, trimming, downmixing or tagging the audio
+    Processing,
+    Done(Vec<PathBuf>),
+    Failed(String),
+    /// In the history with its file still there, not downloaded again unless asked
+    AlreadyDownloaded(PathBuf),
+}
+
+impl QueueStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self, QueueStatus::LookingUp | QueueStatus::Downloading | QueueStatus::Processing)
+    }
+}
+
+/// A pasted url, a single video or a whole playlist
+#[derive(Debug, Clone)]
+pub struct QueueItem {
+    /// Unique in the queue, items are found by id since finished ones can be cleared while others run
+    pub id: u64,
+    pub url: String,
+    pub options: DownloadOptions,
+    pub filters: AudioFilters,
+    /// Playlist or video title, once looked up
+    pub title: Option<String>,
+    pub videos: Vec<DownloadItem>,
+    pub status: QueueStatus,
+    /// Loudness, processing and tagging errors of the saved files
+    pub messages: Vec<String>,
+}
+
+impl QueueItem {
+    /// Title, or the url before it is known
+    pub fn name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.url)
+    }
+
+    /// Videos saved so far
+    pub fn done(&self) -> usize {
+        self.videos.iter().filter(|video| matches!(video.status, ItemStatus::Done(_))).count()
+    }
+}
+
+#[derive(Debug)]
+pub struct DownloadQueue {
+    pub items: Vec<QueueItem>,
+    pub max_parallel: usize,
+    /// Extracted audio waits in `pending_tags` for its tags to be checked instead of being tagged right away
+    pub review_tags: bool,
+    pub pending_tags: Vec<PendingTags>,
+    next_id: u64,
+}
+
+impl Default for DownloadQueue {
+    fn default() -> Self {
+        DownloadQueue {
+            items: Vec::new(),
+            max_parallel: DEFAULT_PARALLEL_DOWNLOADS,
+            review_tags: false,
+            pending_tags: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl DownloadQueue {
+    /// Queues the urls that are not in the queue yet, those in the history are only marked as such
+    ///
+    /// Returns the number of urls added.
+    pub fn add(&mut self, urls: &[String], options: &DownloadOptions, filters: &AudioFilters, history: &DownloadHistory) -> usize {
+        let mut added = 0;
+        for url in urls {
+            if self.items.iter().any(|item| &item.url == url && !matches!(item.status, QueueStatus::Failed(_))) {
+                continue;
+            }
+            let existing = history.find(url);
+            self.next_id += 1;
+            self.items.push(QueueItem {
+                id: self.next_id,
+                url: url.clone(),
+                options: options.clone(),
+                filters: *filters,
+                title: existing.map(|entry| entry.title.clone()),
+                videos: Vec::new(),
+                status: match existing {
+                    Some(entry) => QueueStatus::AlreadyDownloaded(entry.file.clone()),
+                    None => QueueStatus::Queued,
+                },
+                messages: Vec::new(),
+            });
+            added += 1;
+        }
+        added
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut QueueItem> {
+        self.items.iter_mut().find(|item| item.id == id)
+    }
+
+    pub fn running(&self) -> usize {
+        self.items.iter().filter(|item| item.status.is_running()).count()
+    }
+
+    /// Marks queued items as started, as many as the parallel downloads allow, and returns their ids
+    pub fn start_next(&mut self) -> Vec<u64> {
+        let free = self.max_parallel.max(1).saturating_sub(self.running());
+        let mut started = Vec::new();
+        for item in self.items.iter_mut().filter(|item| item.status == QueueStatus::Queued).take(free) {
+            item.status = QueueStatus::LookingUp;
+            started.push(item.id);
+        }
+        started
+    }
+
+    /// Queues an item again, to download a video found in the history anyway
+    pub fn retry(&mut self, id: u64) {
+        if let Some(item) = self.get_mut(id).filter(|item| !item.status.is_running()) {
+            item.status = QueueStatus::Queued;
+            item.videos.clear();
+            item.messages.clear();
+        }
+    }
+
+    /// Forgets the finished, failed and already downloaded items
+    pub fn clear_finished(&mut self) {
+        self.items.retain(|item| item.status.is_running() || item.status == QueueStatus::Queued);
+    }
+}
+
+/// Starts the queued downloads the parallel limit allows, each one starts the next when it ends
+pub fn run_queue(queue: &Arc<Mutex<DownloadQueue>>, history: &Arc<Mutex<DownloadHistory>>, notify: &Notify) {
+    let started = queue.lock().unwrap().start_next();
+    for id in started {
+        let (queue, history, notify) = (Arc::clone(queue), Arc::clone(history), Arc::clone(notify));
+        thread::spawn(move || {
+            let status = download_item(&queue, &history, &notify, id);
+            if let Some(item) = queue.lock().unwrap().get_mut(id) {
+                item.status = status;
+            }
+            notify();
+            run_queue(&queue, &history, &notify);
+        });
+    }
+}
+
+/// Looks an item up, downloads it, processes the audio and records the saved videos in the history
+fn download_item(queue: &Mutex<DownloadQueue>, history: &Mutex<DownloadHistory>, notify: &Notify, id: u64) -> QueueStatus {
+    let Some((url, options, filters)) = queue.lock().unwrap().get_mut(id).map(|item| (item.url.clone(), item.options.clone(), item.filters)) else {
+        return QueueStatus::Failed("Removed from the queue".to_string());
+    };
+
+    let videos = match fetch_info(&url) {
+        Ok(videos) => videos,
+        Err(e) => return QueueStatus::Failed(e),
+    };
+    // other urls of the same video, like a youtu.be link, are only known to be duplicates now
+    if let [video] = &videos[..] {
+        if let Some(entry) = history.lock().unwrap().find(&video.id) {
+            return QueueStatus::AlreadyDownloaded(entry.file.clone());
+        }
+    }
+    if let Some(item) = queue.lock().unwrap().get_mut(id) {
+        item.title = videos.iter().find_map(|video| video.playlist_title.clone()).or_else(|| videos.first().map(|video| video.title.clone()));
+        item.videos = videos.into_iter().map(DownloadItem::new).collect();
+        item.status = QueueStatus::Downloading;
+    }
+    notify();
+
+    let result = download(&url, &options, |event| {
+        if let Some(item) = queue.lock().unwrap().get_mut(id) {
+            update_items(&mut item.videos, &event);
+        }
+        notify();
+    });
+    let paths = match result {
+        Ok(paths) => paths,
+        Err(e) => return QueueStatus::Failed(e),
+    };
+
+    let audio_only = options.audio_format.is_some();
+    let mut messages = Vec::new();
+    if !filters.is_empty() || audio_only {
+        if let Some(item) = queue.lock().unwrap().get_mut(id) {
+            item.status = QueueStatus::Processing;
+        }
+        notify();
+    }
+    if !filters.is_empty() {
+        messages.extend(paths.iter().map(|path| process_audio(path, &filters)));
+    }
+
+    let saved: Vec<DownloadItem> = queue.lock().unwrap().get_mut(id).map(|item| item.videos.clone()).unwrap_or_default();
+    if audio_only {
+        // tagged after the filters, which encode the audio again and drop the cover
+        let review = queue.lock().unwrap().review_tags;
+        for video in &saved {
+            if let ItemStatus::Done(file) = &video.status {
+                let pending = PendingTags::new(file, &video.info);
+                if review {
+                    queue.lock().unwrap().pending_tags.push(pending);
+                } else if let Err(e) = pending.save() {
+                    messages.push(format!("{}: tagging failed: {}", video.info.title, e));
+                }
+            }
+        }
+    }
+    if let Some(item) = queue.lock().unwrap().get_mut(id) {
+        item.messages = messages;
+    }
+
+    let mut history = history.lock().unwrap();
+    for video in &saved {
+        if let ItemStatus::Done(file) = &video.status {
+            let link = if saved.len() == 1 { &url } else { video.info.link().unwrap_or(&url) };
+            history.record(link, &video.info.id, &video.info.title, file);
+        }
+    }
+    if let Err(e) = history.save() {
+        eprintln!("Failed to save the download history: {}", e);
+    }
+    QueueStatus::Done(paths)
+}
+
+/// Encoder for the filtered audio of a downloaded file, picked from its extension
+///
+/// Videos keep the default audio encoder of their container.
+pub fn encode_args(file: &Path) -> &'static [&'static str] {
+    let extension = file.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+    match extension.as_str() {
+        "mp3" => &["-c:a", "libmp3lame", "-b:a", "320k"],
+        "m4a" => &["-c:a", "aac", "-b:a", "256k"],
+        "aac" => &["-c:a", "aac", "-b:a", "256k", "-f", "adts"],
+        "opus" => &["-c:a", "libopus", "-b:a", "160k"],
+        "ogg" => &["-c:a", "libvorbis", "-q:a", "6"],
+        "flac" => &["-c:a", "flac"],
+        "wav" => &["-c:a", "pcm_s16le"],
+        _ => &["-b:a", "192k"],
+    }
+}
+
+/// Normalizes, trims or downmixes a downloaded file in place, the message tells the measured loudness
+pub fn process_audio(file: &Path, filters: &AudioFilters) -> String {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    match filter_in_place(file, filters, encode_args(file)) {
+        Ok(Some(loudness)) => format!("{}: audio was at {}", name, loudness),
+        Ok(None) => format!("{}: done", name),
+        Err(e) => format!("{}: processing the audio failed: {}", name, e),
+    }
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use lib_ffmpeg_utils::probe::probe;
+use lib_ffmpeg_utils::utils::path_for;
+use crate::download::VideoInfo;
+
+/// Formats whose container can hold a cover picture, ogg and opus files only get the tags
+const COVER_EXTENSIONS: [&str; 4] = ["mp3", "m4a", "mp4", "flac"];
+/// Thumbnails yt-dlp may leave next to a download
+const THUMBNAIL_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// Tags written to a downloaded file, ID3 for mp3 and Vorbis comments for ogg, opus and flac
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// Like 2024-08-11
+    pub date: String,
+    /// Page the file was downloaded from
+    pub url: String,
+}
+
+impl AudioTags {
+    /// Tags from the yt-dlp metadata, the uploader is the artist and the playlist the album
+    pub fn from_info(info: &VideoInfo) -> Self {
+        AudioTags {
+            title: info.title.clone(),
+            artist: info.author().unwrap_or_default().to_string(),
+            album: info.playlist_title.clone().unwrap_or_default(),
+            date: info.upload_date.as_deref().map(upload_date).unwrap_or_default(),
+            url: info.link().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Tags already in a file
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let metadata = probe(path).map_err(|e| format!("Failed to probe {}: {}", path.display(), e))?;
+        let tags = metadata.format.tags;
+        Ok(AudioTags {
+            title: tags.title.unwrap_or_default(),
+            artist: tags.artist.unwrap_or_default(),
+            album: tags.album.unwrap_or_default(),
+            date: tags.date.unwrap_or_default(),
+            url: tags.comment.unwrap_or_default(),
+        })
+    }
+
+    /// `-metadata` arguments of the tags that are set, the url goes in the comment like yt-dlp does
+    pub fn metadata_args(&self) -> Vec<String> {
+        let fields = [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("date", &self.date),
+            ("comment", &self.url),
+            ("purl", &self.url),
+        ];
+        fields
+            .iter()
+            .filter(|(_, value)| !value.trim().is_empty())
+            .flat_map(|(key, value)| ["-metadata".to_string(), format!("{}={}", key, value.trim())])
+            .collect()
+    }
+}
+
+/// YYYYMMDD from yt-dlp as YYYY-MM-DD
+pub fn upload_date(date: &str) -> String {
+    match (date.get(..4), date.get(4..6), date.get(6..8)) {
+        (Some(year), Some(month), Some(day)) if date.len() == 8 => format!("{}-{}-{}", year, month, day),
+        _ => date.to_string(),
+    }
+}
+
+/// Whether a cover picture can be embedded in this file
+pub fn supports_cover(path: &Path) -> bool {
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    COVER_EXTENSIONS.contains(&extension.as_str())
+}
+
+/// Thumbnail saved by yt-dlp next to a download, with the same name
+pub fn find_cover(path: &Path) -> Option<PathBuf> {
+    THUMBNAIL_EXTENSIONS.iter().map(|extension| path.with_extension(extension)).find(|cover| cover.exists())
+}
+
+/// Arguments copying `input` to `output` with new tags and, when the format allows it, a cover
+pub fn tag_args(input: &Path, tags: &AudioTags, cover: Option<&Path>, output: &Path) -> Vec<String> {
+    let cover = cover.filter(|_| supports_cover(input));
+    let mut args = vec!["-hide_banner".to_string(), "-y".to_string(), "-i".to_string(), input.to_string_lossy().to_string()];
+    if let Some(cover) = cover {
+        args.extend(["-i".to_string(), cover.to_string_lossy().to_string()]);
+    }
+    args.extend(["-map", "0:a", "-c", "copy"].map(String::from));
+    if let Some(cover) = cover {
+        let codec = if cover.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) { "png" } else { "mjpeg" };
+        args.extend(["-map", "1:v", "-c:v", codec, "-disposition:v", "attached_pic"].map(String::from));
+        args.extend(["-metadata:s:v", "title=Album cover", "-metadata:s:v", "comment=Cover (front)"].map(String::from));
+    } else {
+        // keeps the cover a file already has
+        args.extend(["-map", "0:v?"].map(String::from));
+    }
+    args.extend(tags.metadata_args());
+    if input.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("mp3")) {
+        // version 3 is the one most players read
+        args.extend(["-id3v2_version", "3"].map(String::from));
+    }
+    args.push(output.to_string_lossy().to_string());
+    args
+}
+
+/// Rewrites the tags of a file in place, without encoding the audio again
+pub fn write_tags(path: &Path, tags: &AudioTags, cover: Option<&Path>) -> Result<(), String> {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".tagging.");
+    name.push(path.extension().unwrap_or_default());
+    let temporary = path.with_file_name(name);
+    let output = Command::new(path_for("ffmpeg"))
+        .args(tag_args(path, tags, cover, &temporary))
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&temporary);
+        return Err(format!("ffmpeg error: {}", String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default()));
+    }
+    fs::rename(&temporary, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+/// A downloaded file with the tags it is about to get, so they can be fixed first
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingTags {
+    pub file: PathBuf,
+    pub tags: AudioTags,
+    /// Thumbnail of the video, removed once embedded
+    pub cover: Option<PathBuf>,
+    /// Unchecked to leave the cover out
+    pub embed_cover: bool,
+}
+
+impl PendingTags {
+    pub fn new(file: &Path, info: &VideoInfo) -> Self {
+        let cover = find_cover(file);
+        PendingTags { file: file.to_path_buf(), tags: AudioTags::from_info(info), embed_cover: cover.is_some(), cover }
+    }
+
+    /// A file saved earlier, with its current tags, to fix them
+    pub fn open(file: &Path) -> Result<Self, String> {
+        let cover = find_cover(file);
+        Ok(PendingTags { file: file.to_path_buf(), tags: AudioTags::read(file)?, embed_cover: cover.is_some(), cover })
+    }
+
+    /// Writes the tags then removes the thumbnail
+    pub fn save(&self) -> Result<(), String> {
+        let cover = self.cover.as_deref().filter(|_| self.embed_cover);
+        write_tags(&self.file, &self.tags, cover)?;
+        self.discard();
+        Ok(())
+    }
+
+    /// Leaves the file as it is and removes the thumbnail
+    pub fn discard(&self) {
+        if let Some(cover) = &self.cover {
+            let _ = fs::remove_file(cover);
+        }
+    }
+}
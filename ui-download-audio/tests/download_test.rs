@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use ui_download_audio::download::{
+        download_args, parse_info, parse_line, update_items, DownloadEvent, DownloadItem, DownloadOptions, ItemStatus, Progress,
+    };
+
+    #[test]
+    fn reads_playlist_info() {
+        let stdout = "{\"_type\": \"url\", \"id\": \"abc\", \"title\": \"First\", \"url\": \"https://www.youtube.com/watch?v=abc\", \
+                      \"duration\": 212.0, \"playlist_title\": \"Live\", \"playlist_index\": 1}\n\
+                      {\"id\": \"def\", \"title\": \"Second\", \"webpage_url\": \"https://www.youtube.com/watch?v=def\", \"uploader\": \"Band\"}\n";
+        let videos = parse_info(stdout).unwrap();
+        assert_eq!(videos.len(), 2);
+        assert_eq!(videos[0].link(), Some("https://www.youtube.com/watch?v=abc"));
+        assert_eq!(videos[0].playlist_index, Some(1));
+        assert_eq!(videos[1].author(), Some("Band"));
+        assert!(parse_info("{\"title\": \"no id\"}").is_err());
+    }
+
+    #[test]
+    fn parses_events() {
+        let progress = parse_line("[progress] abc downloading 1048576 NA 4194304.5 524288.0 6").unwrap();
+        let DownloadEvent::Progress(progress) = progress else { panic!("not a progress") };
+        assert_eq!(
+            progress,
+            Progress { id: "abc".into(), status: "downloading".into(), downloaded: 1048576, total: Some(4194304), speed: Some(524288.0), eta: Some(6) }
+        );
+        assert_eq!(progress.fraction(), Some(0.25));
+
+        assert_eq!(
+            parse_line("[file] abc /music/My song: live.mp3"),
+            Some(DownloadEvent::Finished { id: "abc".into(), path: PathBuf::from("/music/My song: live.mp3") })
+        );
+        assert_eq!(
+            parse_line("ERROR: [youtube] def: Video unavailable"),
+            Some(DownloadEvent::Failed { id: Some("def".into()), message: "[youtube] def: Video unavailable".into() })
+        );
+        assert_eq!(parse_line("[youtube] Extracting URL"), None);
+    }
+
+    #[test]
+    fn tracks_items() {
+        let options = DownloadOptions { output_folder: PathBuf::from("/music"), audio_format: Some("mp3".into()), ..Default::default() };
+        let args = download_args("https://youtu.be/abc", &options).join(" ");
+        assert!(args.contains("-o /music/%(title)s.%(ext)s -x --audio-format mp3 --write-thumbnail --convert-thumbnails jpg https://youtu.be/abc"));
+
+        let mut items: Vec<DownloadItem> = parse_info("{\"id\": \"abc\"}\n{\"id\": \"def\"}").unwrap().into_iter().map(DownloadItem::new).collect();
+        update_items(&mut items, &parse_line("[progress] abc finished 10 10 NA NA NA").unwrap());
+        assert_eq!(items[0].status, ItemStatus::Processing);
+        update_items(&mut items, &parse_line("[file] abc /music/a.mp3").unwrap());
+        update_items(&mut items, &parse_line("ERROR: [youtube] def: Private video").unwrap());
+        assert_eq!(items[0].status, ItemStatus::Done(Path::new("/music/a.mp3").to_path_buf()));
+        assert!(matches!(items[1].status, ItemStatus::Failed(_)));
+    }
+}
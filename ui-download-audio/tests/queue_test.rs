@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use lib_ffmpeg_utils::loudness::AudioFilters;
+    use ui_download_audio::download::DownloadOptions;
+    use ui_download_audio::history::{parse_urls, video_id, DownloadHistory};
+    use ui_download_audio::queue::{encode_args, DownloadQueue, QueueStatus};
+
+    #[test]
+    fn reads_pasted_urls() {
+        let text = "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL1\n  <https://youtu.be/dQw4w9WgXcQ>, not a url\n\
+                    https://www.youtube.com/shorts/abcdefghijk https://youtu.be/dQw4w9WgXcQ";
+        let urls = parse_urls(text);
+        assert_eq!(urls, vec![
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL1",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://www.youtube.com/shorts/abcdefghijk",
+        ]);
+        assert_eq!(video_id(&urls[0]).as_deref(), Some("dQw4w9WgXcQ"));
+        assert_eq!(video_id(&urls[1]).as_deref(), Some("dQw4w9WgXcQ"));
+        assert_eq!(video_id(&urls[2]).as_deref(), Some("abcdefghijk"));
+        assert_eq!(video_id("https://example.com/video"), None);
+    }
+
+    #[test]
+    fn finds_downloaded_videos() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("song.mp3");
+        fs::write(&file, "audio").unwrap();
+        let mut history = DownloadHistory::new(&dir.path().join("history.yaml"));
+        history.record("https://www.youtube.com/watch?v=dQw4w9WgXcQ", "dQw4w9WgXcQ", "Song", &file);
+        history.record("https://youtu.be/abcdefghijk", "abcdefghijk", "Gone", &dir.path().join("gone.mp3"));
+        history.save().unwrap();
+
+        let history = DownloadHistory::load(&dir.path().join("history.yaml")).unwrap();
+        assert_eq!(history.entries().len(), 2);
+        assert_eq!(history.find("https://youtu.be/dQw4w9WgXcQ").map(|entry| entry.title.as_str()), Some("Song"));
+        assert_eq!(history.find("dQw4w9WgXcQ").map(|entry| entry.file.clone()), Some(file));
+        assert!(history.find("https://youtu.be/abcdefghijk").is_none());
+    }
+
+    #[test]
+    fn starts_up_to_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("song.mp3");
+        fs::write(&file, "audio").unwrap();
+        let mut history = DownloadHistory::new(&dir.path().join("history.yaml"));
+        history.record("https://youtu.be/dQw4w9WgXcQ", "dQw4w9WgXcQ", "Song", &file);
+
+        let mut queue = DownloadQueue::default();
+        queue.max_parallel = 2;
+        let urls = parse_urls("https://youtu.be/aaaaaaaaaaa https://youtu.be/bbbbbbbbbbb https://youtu.be/ccccccccccc \
+                               https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        let options = DownloadOptions::default();
+        assert_eq!(queue.add(&urls, &options, &AudioFilters::default(), &history), 4);
+        assert_eq!(queue.add(&urls[..1], &options, &AudioFilters::default(), &history), 0);
+        assert_eq!(queue.items[3].status, QueueStatus::AlreadyDownloaded(file));
+
+        let started = queue.start_next();
+        assert_eq!(started, vec![queue.items[0].id, queue.items[1].id]);
+        assert!(queue.start_next().is_empty());
+        queue.get_mut(started[0]).unwrap().status = QueueStatus::Done(Vec::new());
+        assert_eq!(queue.start_next(), vec![queue.items[2].id]);
+
+        queue.retry(queue.items[3].id);
+        queue.clear_finished();
+        assert_eq!(queue.items.len(), 3);
+        assert_eq!(queue.items[2].status, QueueStatus::Queued);
+    }
+
+    #[test]
+    fn filtered_audio_keeps_its_format() {
+        assert_eq!(encode_args(Path::new("song.mp3"))[1], "libmp3lame");
+        assert_eq!(encode_args(Path::new("song.OPUS"))[1], "libopus");
+        assert_eq!(encode_args(Path::new("song.m4a"))[1], "aac");
+        // videos keep the encoder of their container
+        assert_eq!(encode_args(Path::new("clip.webm")), ["-b:a", "192k"]);
+    }
+}
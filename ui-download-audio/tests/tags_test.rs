@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use ui_download_audio::download::{parse_info, parse_line, update_items, DownloadEvent, DownloadItem};
+    use ui_download_audio::tags::{supports_cover, tag_args, upload_date, AudioTags};
+
+    #[test]
+    fn takes_tags_from_metadata() {
+        let line = "[metadata] {\"id\": \"abc\", \"title\": \"Encore\", \"uploader\": \"Band\", \"upload_date\": \"20240811\", \
+                    \"webpage_url\": \"https://www.youtube.com/watch?v=abc\", \"playlist_title\": \"Live at home\", \"playlist_index\": 2}";
+        let Some(DownloadEvent::Metadata(info)) = parse_line(line) else { panic!("not metadata") };
+
+        let mut items: Vec<DownloadItem> = parse_info("{\"id\": \"abc\", \"title\": \"Encore\"}").unwrap().into_iter().map(DownloadItem::new).collect();
+        update_items(&mut items, &DownloadEvent::Metadata(info.clone()));
+        assert_eq!(items[0].info, info);
+
+        let tags = AudioTags::from_info(&info);
+        assert_eq!(
+            tags,
+            AudioTags {
+                title: "Encore".into(),
+                artist: "Band".into(),
+                album: "Live at home".into(),
+                date: "2024-08-11".into(),
+                url: "https://www.youtube.com/watch?v=abc".into(),
+            }
+        );
+        assert_eq!(upload_date("2024"), "2024");
+    }
+
+    #[test]
+    fn embeds_covers_where_possible() {
+        let tags = AudioTags { title: "Encore".into(), artist: " ".into(), ..Default::default() };
+        let args = tag_args(Path::new("/music/Encore.mp3"), &tags, Some(Path::new("/music/Encore.jpg")), Path::new("/music/out.mp3")).join(" ");
+        assert_eq!(
+            args,
+            "-hide_banner -y -i /music/Encore.mp3 -i /music/Encore.jpg -map 0:a -c copy -map 1:v -c:v mjpeg -disposition:v attached_pic \
+             -metadata:s:v title=Album cover -metadata:s:v comment=Cover (front) -metadata title=Encore -id3v2_version 3 /music/out.mp3"
+        );
+
+        assert!(!supports_cover(Path::new("/music/Encore.opus")));
+        let args = tag_args(Path::new("/music/Encore.opus"), &tags, Some(Path::new("/music/Encore.jpg")), Path::new("/music/out.opus")).join(" ");
+        assert_eq!(args, "-hide_banner -y -i /music/Encore.opus -map 0:a -c copy -map 0:v? -metadata title=Encore /music/out.opus");
+    }
+}
@@ -0,0 +1,175 @@
+use clap::{value_parser, Arg, ArgAction, Command};
+use lib_ffmpeg_utils::edit::format_timestamp;
+use lib_ffmpeg_utils::loudness::{AudioFilters, LoudnessTarget};
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+use ui_extract_audio::extract::{collect_inputs, extract_audio, AudioFormat, AudioSource};
+use ui_extract_audio::split::{parse_cues, plan_segments, split_audio, SplitMode};
+
+fn main() {
+    let matches = Command::new("Extract Audio")
+        .version("1.0")
+        .about("Saves the audio tracks of videos, whole or split by chapters, silences or a cue list")
+        .arg(
+            Arg::new("inputs")
+                .value_name("INPUT")
+                .help("Files, or folders searched for media files")
+                .required(true)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(AudioFormat::ALL.map(|format| format.name()))
+                .default_value("mp3"),
+        )
+        .arg(
+            Arg::new("track")
+                .short('t')
+                .long("track")
+                .value_name("N")
+                .help("Audio track to save, can be repeated [default: the default track]")
+                .value_parser(value_parser!(usize))
+                .action(ArgAction::Append),
+        )
+        .arg(Arg::new("all-tracks").long("all-tracks").help("Saves every audio track").action(ArgAction::SetTrue))
+        .arg(Arg::new("list").long("list").help("Lists the audio tracks and chapters, saves nothing").action(ArgAction::SetTrue))
+        .arg(
+            Arg::new("normalize")
+                .long("normalize")
+                .value_name("LUFS")
+                .help("Two pass loudness normalization")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .num_args(0..=1)
+                .default_missing_value("-23"),
+        )
+        .arg(Arg::new("trim-silence").long("trim-silence").help("Removes the silence at the start and the end").action(ArgAction::SetTrue))
+        .arg(Arg::new("mono").long("mono").help("Downmixes to a single channel").action(ArgAction::SetTrue))
+        .arg(
+            Arg::new("split")
+                .long("split")
+                .value_name("MODE")
+                .help("Saves one file per chapter, per piece between silences or per cue")
+                .value_parser(["chapters", "silence", "cues"]),
+        )
+        .arg(
+            Arg::new("cues")
+                .long("cues")
+                .value_name("FILE")
+                .help("Cue sheet or list of \"timestamp title\" lines, for --split cues"),
+        )
+        .arg(
+            Arg::new("noise")
+                .long("noise")
+                .value_name("DB")
+                .help("Level below which it is silent, for --split silence")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .default_value("-40"),
+        )
+        .arg(
+            Arg::new("min-silence")
+                .long("min-silence")
+                .value_name("SECONDS")
+                .help("Shortest silence cutting the track, for --split silence")
+                .value_parser(value_parser!(f64))
+                .default_value("2"),
+        )
+        .get_matches();
+
+    let paths: Vec<PathBuf> = matches.get_many::<String>("inputs").unwrap().map(PathBuf::from).collect();
+    let format: AudioFormat = matches.get_one::<String>("format").unwrap().parse().unwrap();
+    let filters = AudioFilters {
+        normalize: matches.get_one::<f64>("normalize").map(|integrated| LoudnessTarget { integrated: *integrated, ..Default::default() }),
+        trim_silence: matches.get_flag("trim-silence"),
+        mono: matches.get_flag("mono"),
+    };
+    let split = match matches.get_one::<String>("split").map(String::as_str) {
+        Some("chapters") => Some(SplitMode::Chapters),
+        Some("silence") => Some(SplitMode::Silence {
+            noise: *matches.get_one::<f64>("noise").unwrap(),
+            min_duration: *matches.get_one::<f64>("min-silence").unwrap(),
+        }),
+        Some(_) => {
+            let Some(file) = matches.get_one::<String>("cues") else {
+                eprintln!("--split cues needs a --cues file");
+                exit(2);
+            };
+            match fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file, e)).and_then(|text| parse_cues(&text)) {
+                Ok(cues) => Some(SplitMode::Cues(cues)),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(2);
+                }
+            }
+        }
+        None => None,
+    };
+    if split.is_some() && !filters.is_empty() {
+        eprintln!("--normalize, --trim-silence and --mono only apply to whole tracks");
+        exit(2);
+    }
+
+    let inputs = collect_inputs(&paths);
+    if inputs.is_empty() {
+        eprintln!("No media files found");
+        exit(1);
+    }
+
+    let mut failed = 0;
+    for input in inputs {
+        let source = match AudioSource::open(&input) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}", e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        if matches.get_flag("list") {
+            println!("{}", input.display());
+            for track in &source.tracks {
+                println!("  {}", track.label());
+            }
+            for chapter in &source.chapters {
+                println!("  {} {}", format_timestamp(chapter.start), chapter.title.as_deref().unwrap_or_default());
+            }
+            continue;
+        }
+
+        let tracks: Vec<usize> = if matches.get_flag("all-tracks") {
+            source.tracks.iter().map(|track| track.track).collect()
+        } else if let Some(tracks) = matches.get_many::<usize>("track") {
+            tracks.copied().collect()
+        } else {
+            source.default_track().into_iter().collect()
+        };
+
+        for (track, output) in source.outputs(&tracks, format) {
+            let result = match &split {
+                Some(mode) => plan_segments(&source, track, mode)
+                    .and_then(|segments| split_audio(&source, track, format, &segments, tracks.len() > 1))
+                    .map(|outputs| outputs.iter().map(|output| output.display().to_string()).collect::<Vec<_>>().join("\n")),
+                None => extract_audio(&source, track, format, &filters, &output).map(|loudness| match loudness {
+                    Some(loudness) => format!("{} (was {})", output.display(), loudness),
+                    None => output.display().to_string(),
+                }),
+            };
+            match result {
+                Ok(saved) => println!("{}", saved),
+                Err(e) => {
+                    eprintln!("{}: {}", input.display(), e.lines().last().unwrap_or_default());
+                    failed += 1;
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        exit(1);
+    }
+}
@@ -1,23 +1,39 @@
 use eframe::egui;
 use lib_egui_utils::my_default_options;
-use lib_ffmpeg_utils::utils::{check_ffmpeg, path_for};
+use lib_ffmpeg_utils::loudness::{measure_loudness, AudioFilters, Loudness, LoudnessTarget};
+use lib_ffmpeg_utils::utils::check_ffmpeg;
 use native_dialog::FileDialog;
-use std::path::PathBuf;
-use std::process::{exit, Command};
+use ui_extract_audio::extract::{collect_inputs, extract_audio, input_args, AudioFormat, AudioSource};
+use ui_extract_audio::split::{parse_cues, plan_segments, split_audio, SplitMode};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
+/// A file to extract from and the tracks picked in it
+struct SourceRow {
+    source: AudioSource,
+    chosen: Vec<usize>,
+    /// Loudness of each track measured so far
+    loudness: BTreeMap<usize, Loudness>,
+}
+
 #[derive(Default)]
 struct MyApp {
     ffmpeg_version: Option<String>, // Store the detected ffmpeg version
     ffmpeg_not_found: bool,         // Flag to indicate ffmpeg is not found
     exit_time: Option<Instant>,     // Time to quit the application if ffmpeg is not found
-    selected_file: Option<PathBuf>,
+    sources: Arc<Mutex<Vec<SourceRow>>>, // Files probed so far, filled in the background
     processing: Arc<Mutex<bool>>,  // Shared flag for processing status
     current_tab: String,           // Current tab
-    selected_format: String,       // Selected audio format
+    selected_format: AudioFormat,  // Selected audio format
+    filters: AudioFilters,         // Normalization, silence trimming and downmix
+    split: Option<SplitMode>,      // One file per chapter, silence or cue instead of whole tracks
+    cue_text: String,              // Cue sheet or timestamp list typed for the cue split
     status_message: Arc<Mutex<Option<String>>>, // Shared status message
+    errors: Arc<Mutex<Vec<String>>>, // Files that could not be probed or extracted
 }
 
 fn main() -> Result<(), eframe::Error> {
@@ -29,11 +45,10 @@ fn main() -> Result<(), eframe::Error> {
     //         .with_icon(app_icon),
     //     ..Default::default()
     // };
-    let native_options = my_default_options(400.0, 300.0, include_bytes!("../../icon.png"));
+    let native_options = my_default_options(600.0, 450.0, include_bytes!("../../icon.png"));
 
     let mut app = MyApp {
         current_tab: "Extract Audio".to_string(),
-        selected_format: "mp3".to_string(), // Auto-select the first format
         ..Default::default()
     };
 
@@ -91,93 +106,290 @@ impl eframe::App for MyApp {
 
             // Show content for the selected tab
             if self.current_tab == "Extract Audio" {
-                ui.label("Select a video file:");
+                self.extract_tab(ui);
+            }
+        });
+
+        // Request a UI repaint while processing
+        if *processing.lock().unwrap() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+}
 
-                if ui.button("Browse").clicked() {
-                    if let Ok(file) = FileDialog::new().show_open_single_file() {
-                        self.selected_file = file;
+
+impl MyApp {
+    fn extract_tab(&mut self, ui: &mut egui::Ui) {
+        ui.label("Select video files or a folder:");
+
+        ui.horizontal(|ui| {
+            if ui.button("Add Files").clicked() {
+                if let Ok(files) = FileDialog::new().show_open_multiple_file() {
+                    self.add_sources(ui.ctx(), files);
+                }
+            }
+            if ui.button("Add Folder").clicked() {
+                if let Ok(Some(folder)) = FileDialog::new().show_open_single_dir() {
+                    self.add_sources(ui.ctx(), vec![folder]);
+                }
+            }
+            if ui.button("Clear").clicked() {
+                self.sources.lock().unwrap().clear();
+                self.errors.lock().unwrap().clear();
+            }
+        });
+
+        let dropped: Vec<PathBuf> = ui.ctx().input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect());
+        if !dropped.is_empty() {
+            self.add_sources(ui.ctx(), dropped);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Output format:");
+            egui::ComboBox::from_label("")
+                .selected_text(self.selected_format.to_string())
+                .show_ui(ui, |ui| {
+                    for format in AudioFormat::ALL {
+                        ui.selectable_value(&mut self.selected_format, format, format.to_string());
+                    }
+                });
+            if self.selected_format.is_lossless() {
+                ui.label("lossless");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Split:");
+            let selected = self.split.as_ref().map_or("Whole tracks".to_string(), |mode| mode.to_string());
+            egui::ComboBox::from_id_salt("split").selected_text(&selected).show_ui(ui, |ui| {
+                for mode in [None, Some(SplitMode::Chapters), Some(SplitMode::default()), Some(SplitMode::Cues(Vec::new()))] {
+                    let label = mode.as_ref().map_or("Whole tracks".to_string(), |mode| mode.to_string());
+                    if ui.selectable_label(selected == label, &label).clicked() {
+                        self.split = mode;
                     }
                 }
+            });
+            if let Some(SplitMode::Silence { noise, min_duration }) = &mut self.split {
+                ui.add(egui::DragValue::new(noise).range(-90.0..=-10.0).suffix(" dB"));
+                ui.add(egui::DragValue::new(min_duration).range(0.1..=30.0).speed(0.1).suffix(" s"));
+            }
+        });
+        if matches!(self.split, Some(SplitMode::Cues(_))) {
+            ui.add(egui::TextEdit::multiline(&mut self.cue_text).hint_text("0:00 Intro\n3:45 Second song").desired_rows(4));
+        }
 
-                if let Some(ref file) = self.selected_file {
-                    ui.label(format!("Selected file: {:?}", file.display()));
-                } else {
-                    ui.label("No file selected");
+        ui.add_enabled_ui(self.selected_format != AudioFormat::Copy && self.split.is_none(), |ui| {
+            ui.horizontal(|ui| {
+                let mut normalize = self.filters.normalize.is_some();
+                if ui.checkbox(&mut normalize, "Normalize loudness").changed() {
+                    self.filters.normalize = normalize.then(LoudnessTarget::default);
                 }
+                if let Some(target) = &mut self.filters.normalize {
+                    ui.add(egui::DragValue::new(&mut target.integrated).range(-31.0..=-5.0).speed(0.5).suffix(" LUFS"));
+                }
+                ui.checkbox(&mut self.filters.trim_silence, "Trim silence");
+                ui.checkbox(&mut self.filters.mono, "Mono");
+            });
+        });
 
-                ui.horizontal(|ui| {
-                    ui.label("Output format:");
-                    egui::ComboBox::from_label("")
-                        .selected_text(&self.selected_format)
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut self.selected_format, "mp3".to_string(), "MP3");
-                            ui.selectable_value(&mut self.selected_format, "aac".to_string(), "AAC");
-                            ui.selectable_value(&mut self.selected_format, "wav".to_string(), "WAV");
+        let mut sources = self.sources.lock().unwrap();
+        if sources.is_empty() {
+            ui.label("No file selected");
+        } else {
+            ui.horizontal(|ui| {
+                if ui.button("All tracks").clicked() {
+                    for row in sources.iter_mut() {
+                        row.chosen = row.source.tracks.iter().map(|track| track.track).collect();
+                    }
+                }
+                if ui.button("Default tracks").clicked() {
+                    for row in sources.iter_mut() {
+                        row.chosen = row.source.default_track().into_iter().collect();
+                    }
+                }
+            });
+        }
+
+        egui::ScrollArea::vertical().max_height(220.0).show(ui, |ui| {
+            for row in sources.iter_mut() {
+                let mut name = row.source.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                if !row.source.chapters.is_empty() {
+                    name.push_str(&format!("  ·  {} chapters", row.source.chapters.len()));
+                }
+                ui.label(name).on_hover_text(row.source.path.display().to_string());
+                ui.indent(row.source.path.display().to_string(), |ui| {
+                    for track in &row.source.tracks {
+                        ui.horizontal(|ui| {
+                            let mut chosen = row.chosen.contains(&track.track);
+                            if ui.checkbox(&mut chosen, track.label()).changed() {
+                                if chosen {
+                                    row.chosen.push(track.track);
+                                    row.chosen.sort();
+                                } else {
+                                    row.chosen.retain(|t| *t != track.track);
+                                }
+                            }
+                            if let Some(loudness) = row.loudness.get(&track.track) {
+                                ui.weak(loudness.to_string());
+                            }
                         });
+                    }
                 });
+            }
+        });
 
-                if ui.button("Go").clicked() {
-                    if let Some(ref file) = self.selected_file {
-                        let output_file = format!("{}.{}", file.display(), self.selected_format);
+        let count: usize = sources.iter().map(|row| row.chosen.len()).sum();
+        let processing = *self.processing.lock().unwrap();
+        let jobs: Vec<(AudioSource, Vec<usize>)> = sources.iter().map(|row| (row.source.clone(), row.chosen.clone())).collect();
+        drop(sources);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(count > 0 && !processing, egui::Button::new(format!("Go ({} tracks)", count))).clicked() {
+                match self.split_mode() {
+                    Ok(Some(mode)) => self.split(jobs.clone(), count, mode),
+                    Ok(None) => self.extract(jobs.clone(), count),
+                    Err(e) => *self.status_message.lock().unwrap() = Some(e),
+                }
+            }
+            if ui.add_enabled(count > 0 && !processing, egui::Button::new("Measure Loudness")).clicked() {
+                self.measure(jobs, count);
+            }
+        });
 
-                        // Set the processing flag to true
-                        let processing_clone = self.processing.clone();
-                        *processing_clone.lock().unwrap() = true;
+        // Check if the process is running and display a "Processing..." message
+        if processing {
+            ui.label("Processing...");
+        }
 
-                        let status_message_clone = self.status_message.clone();
+        if let Some(ref message) = *self.status_message.lock().unwrap() {
+            ui.label(message);
+        }
+        for error in self.errors.lock().unwrap().iter() {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
 
-                        // Run ffmpeg in a background thread
-                        let file_clone = file.clone();
-                        let format_clone = self.selected_format.clone();
-                        thread::spawn(move || {
-                            let result = run_ffmpeg(&file_clone, &output_file, &format_clone);
-                            *processing_clone.lock().unwrap() = false;
-                            *status_message_clone.lock().unwrap() = Some(result);
-                        });
+    /// Probes the files and folders in the background, the default track of each is picked
+    fn add_sources(&self, ctx: &egui::Context, paths: Vec<PathBuf>) {
+        let ctx = ctx.clone();
+        let sources = self.sources.clone();
+        let errors = self.errors.clone();
+        thread::spawn(move || {
+            for input in collect_inputs(&paths) {
+                if sources.lock().unwrap().iter().any(|row| row.source.path == input) {
+                    continue;
+                }
+                match AudioSource::open(&input) {
+                    Ok(source) => {
+                        let chosen = source.default_track().into_iter().collect();
+                        sources.lock().unwrap().push(SourceRow { source, chosen, loudness: BTreeMap::new() });
                     }
+                    Err(e) => errors.lock().unwrap().push(e),
                 }
+                ctx.request_repaint();
+            }
+        });
+    }
 
-                // Check if the process is running and display a "Processing..." message
-                if *processing.lock().unwrap() {
-                    ui.label("Processing...");
-                }
+    /// Runs ffmpeg for every chosen track in a background thread, one after the other
+    fn extract(&self, jobs: Vec<(AudioSource, Vec<usize>)>, count: usize) {
+        let processing = self.processing.clone();
+        let status_message = self.status_message.clone();
+        let errors = self.errors.clone();
+        let sources = self.sources.clone();
+        let format = self.selected_format;
+        let filters = if format == AudioFormat::Copy { AudioFilters::default() } else { self.filters };
+        *processing.lock().unwrap() = true;
+        errors.lock().unwrap().clear();
 
-                if let Some(ref message) = *self.status_message.lock().unwrap() {
-                    ui.label(message);
+        thread::spawn(move || {
+            let mut done = 0;
+            let mut failed = 0;
+            for (source, tracks) in &jobs {
+                for (track, output) in source.outputs(tracks, format) {
+                    done += 1;
+                    *status_message.lock().unwrap() = Some(format!("Extracting {}/{}: {}", done, count, output.display()));
+                    match extract_audio(source, track, format, &filters, &output) {
+                        Ok(Some(loudness)) => record_loudness(&sources, &source.path, track.track, loudness),
+                        Ok(None) => {}
+                        Err(e) => {
+                            errors.lock().unwrap().push(format!("{}: {}", output.display(), e.lines().last().unwrap_or_default()));
+                            failed += 1;
+                        }
+                    }
                 }
             }
+            *processing.lock().unwrap() = false;
+            *status_message.lock().unwrap() = Some(format!("Extracted {} tracks, {} failed", count - failed, failed));
         });
+    }
 
-        // Request a UI repaint while processing
-        if *processing.lock().unwrap() {
-            ctx.request_repaint_after(Duration::from_millis(100));
+    /// The split mode with the cue list parsed from the text box
+    fn split_mode(&self) -> Result<Option<SplitMode>, String> {
+        match &self.split {
+            Some(SplitMode::Cues(_)) => Ok(Some(SplitMode::Cues(parse_cues(&self.cue_text)?))),
+            mode => Ok(mode.clone()),
         }
     }
-}
 
+    /// Cuts every chosen track into numbered files in a background thread
+    fn split(&self, jobs: Vec<(AudioSource, Vec<usize>)>, count: usize, mode: SplitMode) {
+        let processing = self.processing.clone();
+        let status_message = self.status_message.clone();
+        let errors = self.errors.clone();
+        let format = self.selected_format;
+        *processing.lock().unwrap() = true;
+        errors.lock().unwrap().clear();
+
+        thread::spawn(move || {
+            let mut done = 0;
+            let mut files = 0;
+            for (source, tracks) in &jobs {
+                for track in source.tracks.iter().filter(|track| tracks.contains(&track.track)) {
+                    done += 1;
+                    *status_message.lock().unwrap() = Some(format!("Splitting {}/{}: {}", done, count, source.path.display()));
+                    let result = plan_segments(source, track, &mode)
+                        .and_then(|segments| split_audio(source, track, format, &segments, tracks.len() > 1));
+                    match result {
+                        Ok(outputs) => files += outputs.len(),
+                        Err(e) => errors.lock().unwrap().push(format!("{}: {}", source.path.display(), e)),
+                    }
+                }
+            }
+            *processing.lock().unwrap() = false;
+            *status_message.lock().unwrap() = Some(format!("Split {} tracks into {} files", count, files));
+        });
+    }
 
-fn run_ffmpeg(input_file: &PathBuf, output_file: &str, format: &str) -> String {
-    let input = input_file.to_str().unwrap();
-    let output = output_file;
+    /// Measures the integrated loudness and true peak of every chosen track, after trimming and downmixing
+    fn measure(&self, jobs: Vec<(AudioSource, Vec<usize>)>, count: usize) {
+        let processing = self.processing.clone();
+        let status_message = self.status_message.clone();
+        let errors = self.errors.clone();
+        let sources = self.sources.clone();
+        let filters = self.filters;
+        *processing.lock().unwrap() = true;
+        errors.lock().unwrap().clear();
 
-    let args = match format {
-        "mp3" => vec!["-i", input, "-vn", "-ac", "2", "-ar", "44100", "-ab", "320k", "-f", "mp3", output],
-        "aac" => vec!["-i", input, "-vn", "-ac", "2", "-ar", "44100", "-ab", "192k", "-f", "adts", output],
-        "wav" => vec!["-i", input, "-vn", "-ac", "2", "-ar", "44100", "-f", "wav", output],
-        _ => vec![], // Default empty args for unsupported formats
-    };
+        thread::spawn(move || {
+            let mut done = 0;
+            for (source, tracks) in &jobs {
+                for track in source.tracks.iter().filter(|track| tracks.contains(&track.track)) {
+                    done += 1;
+                    *status_message.lock().unwrap() = Some(format!("Measuring {}/{}: {}", done, count, source.path.display()));
+                    match measure_loudness(&input_args(source, track), &filters) {
+                        Ok(loudness) => record_loudness(&sources, &source.path, track.track, loudness),
+                        Err(e) => errors.lock().unwrap().push(format!("{}: {}", source.path.display(), e)),
+                    }
+                }
+            }
+            *processing.lock().unwrap() = false;
+            *status_message.lock().unwrap() = Some(format!("Measured {} tracks", count));
+        });
+    }
+}
 
-    let ffmpeg_command = Command::new(path_for("ffmpeg"))
-        .args(args)
-        .output()
-        .expect("Failed to execute ffmpeg command");
-
-    if ffmpeg_command.status.success() {
-        format!("Audio extracted successfully: {}", output)
-    } else {
-        format!(
-            "Error during extraction: {}",
-            String::from_utf8_lossy(&ffmpeg_command.stderr)
-        )
+fn record_loudness(sources: &Mutex<Vec<SourceRow>>, path: &Path, track: usize, loudness: Loudness) {
+    if let Some(row) = sources.lock().unwrap().iter_mut().find(|row| row.source.path == path) {
+        row.loudness.insert(track, loudness);
     }
 }
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use lib_ffmpeg_utils::loudness::{measure_loudness, AudioFilters, Loudness};
+use lib_ffmpeg_utils::probe::{probe, ProbeResult, Tags};
+use lib_ffmpeg_utils::utils::path_for;
+use crate::split::{chapter_segments, Segment};
+
+/// Files picked up when a folder is given, videos and audio files alike
+pub const MEDIA_EXTENSIONS: [&str; 17] = [
+    "mp4", "mov", "m4v", "mkv", "webm", "avi", "mpeg", "mpg", "ts", "flv", "mp3", "m4a", "aac", "wav", "flac", "ogg", "opus",
+];
+
+/// Audio formats the video soundtrack can be saved as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    Aac,
+    M4a,
+    Opus,
+    Flac,
+    Wav,
+    /// The stream as it is in the source, nothing is encoded again
+    Copy,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 7] = [
+        AudioFormat::Mp3,
+        AudioFormat::Aac,
+        AudioFormat::M4a,
+        AudioFormat::Opus,
+        AudioFormat::Flac,
+        AudioFormat::Wav,
+        AudioFormat::Copy,
+    ];
+
+    /// Name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Aac => "aac",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Copy => "copy",
+        }
+    }
+
+    /// Extension of the saved file, a copied stream goes in a container that fits its codec
+    pub fn extension(&self, codec: &str) -> &'static str {
+        match self {
+            AudioFormat::Copy => copy_extension(codec),
+            format => format.name(),
+        }
+    }
+
+    /// Flac, wav and copies keep every sample of the source
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav | AudioFormat::Copy)
+    }
+
+    /// Encoding settings, the sample rate and channels of the source are kept
+    fn codec_args(&self) -> Vec<&'static str> {
+        match self {
+            AudioFormat::Mp3 => vec!["-c:a", "libmp3lame", "-b:a", "320k"],
+            AudioFormat::Aac => vec!["-c:a", "aac", "-b:a", "192k", "-f", "adts"],
+            AudioFormat::M4a => vec!["-c:a", "aac", "-b:a", "192k"],
+            AudioFormat::Opus => vec!["-c:a", "libopus", "-b:a", "160k"],
+            AudioFormat::Flac => vec!["-c:a", "flac"],
+            AudioFormat::Wav => vec!["-c:a", "pcm_s16le"],
+            AudioFormat::Copy => vec!["-c:a", "copy"],
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioFormat::Copy => f.write_str("Copy as is"),
+            format => f.write_str(&format.name().to_uppercase()),
+        }
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AudioFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown audio format {}, use mp3, aac, m4a, opus, flac, wav or copy", s))
+    }
+}
+
+/// Container holding a stream copied without encoding, Matroska audio takes anything else
+fn copy_extension(codec: &str) -> &'static str {
+    match codec {
+        "aac" | "alac" => "m4a",
+        "mp3" => "mp3",
+        "opus" => "opus",
+        "vorbis" => "ogg",
+        "flac" => "flac",
+        "ac3" => "ac3",
+        "eac3" => "eac3",
+        codec if codec.starts_with("pcm_") => "wav",
+        _ => "mka",
+    }
+}
+
+/// An audio stream of a media file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioTrack {
+    /// Position among the audio streams, as in `-map 0:a:N`
+    pub track: usize,
+    /// Index among all the streams
+    pub index: u32,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub default: bool,
+}
+
+impl AudioTrack {
+    /// One line description, like "#1 eng, Commentary (ac3, 5.1(side), 48 kHz) default"
+    pub fn label(&self) -> String {
+        let names: Vec<&str> = [self.language.as_deref(), self.title.as_deref()].into_iter().flatten().collect();
+        let mut details: Vec<String> = Some(self.codec.clone()).filter(|codec| !codec.is_empty()).into_iter().collect();
+        match (&self.channel_layout, self.channels) {
+            (Some(layout), _) => details.push(layout.clone()),
+            (None, Some(channels)) => details.push(format!("{} ch", channels)),
+            (None, None) => {}
+        }
+        if let Some(rate) = self.sample_rate {
+            details.push(format!("{} kHz", rate as f64 / 1000.0));
+        }
+
+        let mut label = format!("#{}", self.track);
+        if !names.is_empty() {
+            label.push_str(&format!(" {}", names.join(", ")));
+        }
+        if !details.is_empty() {
+            label.push_str(&format!(" ({})", details.join(", ")));
+        }
+        if self.default {
+            label.push_str(" default");
+        }
+        label
+    }
+}
+
+/// Audio streams of a probed file, in the order ffmpeg numbers them
+pub fn audio_tracks(metadata: &ProbeResult) -> Vec<AudioTrack> {
+    metadata
+        .audio_streams()
+        .enumerate()
+        .map(|(track, stream)| AudioTrack {
+            track,
+            index: stream.index,
+            codec: stream.codec_name.clone().unwrap_or_default(),
+            language: stream.language().filter(|language| *language != "und").map(String::from),
+            title: stream.tags.title.clone(),
+            channels: stream.channels,
+            channel_layout: stream.channel_layout.clone(),
+            sample_rate: stream.sample_rate,
+            default: stream.disposition.default,
+        })
+        .collect()
+}
+
+/// A media file with the audio tracks and tags that can be extracted from it
+#[derive(Debug, Clone, Default)]
+pub struct AudioSource {
+    pub path: PathBuf,
+    pub tracks: Vec<AudioTrack>,
+    pub tags: Tags,
+    /// Seconds
+    pub duration: Option<f64>,
+    pub chapters: Vec<Segment>,
+}
+
+impl AudioSource {
+    /// Probes the file, an error when it cannot be read or has no audio
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let metadata = probe(path).map_err(|e| format!("Failed to probe {}: {}", path.display(), e))?;
+        let source = Self::from_probe(path, &metadata);
+        if source.tracks.is_empty() {
+            return Err(format!("{} has no audio", path.display()));
+        }
+        Ok(source)
+    }
+
+    pub fn from_probe(path: &Path, metadata: &ProbeResult) -> Self {
+        AudioSource {
+            path: path.to_path_buf(),
+            tracks: audio_tracks(metadata),
+            tags: metadata.format.tags.clone(),
+            duration: metadata.duration().map(|duration| duration.as_secs_f64()),
+            chapters: chapter_segments(metadata),
+        }
+    }
+
+    /// The track flagged as default, or the first one
+    pub fn default_track(&self) -> Option<usize> {
+        self.tracks.iter().find(|track| track.default).or(self.tracks.first()).map(|track| track.track)
+    }
+
+    /// Where each of the chosen tracks is saved, next to the source
+    ///
+    /// Names get the track number and language when several tracks of the file are extracted.
+    pub fn outputs(&self, tracks: &[usize], format: AudioFormat) -> Vec<(&AudioTrack, PathBuf)> {
+        self.tracks
+            .iter()
+            .filter(|track| tracks.contains(&track.track))
+            .map(|track| (track, output_path(&self.path, track, format, tracks.len() > 1)))
+            .collect()
+    }
+}
+
+pub fn is_media(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Media files given directly and those found in the given folders and their subfolders, sorted
+pub fn collect_inputs(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_folder(path, &mut inputs);
+        } else if path.is_file() {
+            inputs.push(path.clone());
+        }
+    }
+    inputs.sort();
+    inputs.dedup();
+    inputs
+}
+
+fn collect_folder(folder: &Path, inputs: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_folder(&path, inputs);
+        } else if is_media(&path) {
+            inputs.push(path);
+        }
+    }
+}
+
+/// Output next to the source, the format extension is appended to the whole file name
+///
+/// `numbered` adds the track and its language, like `concert.mkv.1.eng.mp3`.
+pub fn output_path(input: &Path, track: &AudioTrack, format: AudioFormat, numbered: bool) -> PathBuf {
+    let mut name = input.file_name().unwrap_or_default().to_os_string();
+    if numbered {
+        name.push(format!(".{}", track.track));
+        if let Some(language) = &track.language {
+            name.push(format!(".{}", language));
+        }
+    }
+    name.push(".");
+    name.push(format.extension(&track.codec));
+    input.with_file_name(name)
+}
+
+/// `-metadata` arguments carrying the title, artist, album and date of the source over
+///
+/// Phones only write a creation time, its day becomes the date.
+pub fn tag_args(tags: &Tags) -> Vec<String> {
+    let date = tags.date.clone().or_else(|| tags.creation_time.as_ref().map(|time| time.chars().take(10).collect()));
+    [("title", &tags.title), ("artist", &tags.artist), ("album", &tags.album), ("date", &date)]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+        .flat_map(|tag| ["-metadata".to_string(), tag])
+        .collect()
+}
+
+/// `-i` and `-map` arguments picking one audio track of `source`
+pub fn input_args(source: &AudioSource, track: &AudioTrack) -> Vec<String> {
+    vec!["-i".to_string(), source.path.to_string_lossy().to_string(), "-map".to_string(), format!("0:a:{}", track.track)]
+}
+
+/// Arguments of the ffmpeg command saving one audio track of `source` into `output`, replacing it
+///
+/// Normalizing needs the loudness `measured` by [measure_loudness].
+pub fn extract_args(
+    source: &AudioSource,
+    track: &AudioTrack,
+    format: AudioFormat,
+    filters: &AudioFilters,
+    measured: Option<&Loudness>,
+    output: &Path,
+) -> Vec<String> {
+    let mut args = vec!["-y".to_string()];
+    args.extend(input_args(source, track));
+    args.extend(tag_args(&source.tags));
+    if let Some(filter) = filters.filter(measured, track.sample_rate) {
+        args.extend(["-af".to_string(), filter]);
+    }
+    args.extend(encode_args(format, track, filters.mono));
+    args.push(output.to_string_lossy().to_string());
+    args
+}
+
+/// Codec settings of `format` for `track`, downmixed to stereo when the encoder needs it
+pub(crate) fn encode_args(format: AudioFormat, track: &AudioTrack, mono: bool) -> Vec<String> {
+    let mut args: Vec<String> = format.codec_args().into_iter().map(String::from).collect();
+    // lame stops at stereo
+    if format == AudioFormat::Mp3 && !mono && track.channels.is_some_and(|channels| channels > 2) {
+        args.extend(["-ac", "2"].map(String::from));
+    }
+    args
+}
+
+/// Saves one audio track, measuring its loudness first when normalizing
+///
+/// Returns the loudness of the source as measured by the first pass.
+pub fn extract_audio(
+    source: &AudioSource,
+    track: &AudioTrack,
+    format: AudioFormat,
+    filters: &AudioFilters,
+    output: &Path,
+) -> Result<Option<Loudness>, String> {
+    if format == AudioFormat::Copy && !filters.is_empty() {
+        return Err("A copied stream cannot be normalized, trimmed or downmixed, pick another format".to_string());
+    }
+    let measured = match filters.normalize {
+        Some(_) => Some(measure_loudness(&input_args(source, track), filters)?),
+        None => None,
+    };
+    let result = Command::new(path_for("ffmpeg"))
+        .args(extract_args(source, track, format, filters, measured.as_ref(), output))
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+
+    if result.status.success() {
+        Ok(measured)
+    } else {
+        Err(format!("Error during extraction: {}", String::from_utf8_lossy(&result.stderr)))
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use regex::Regex;
+use lib_ffmpeg_utils::edit::parse_timestamp;
+use lib_ffmpeg_utils::probe::{ProbeResult, Tags};
+use lib_ffmpeg_utils::utils::path_for;
+use crate::extract::{encode_args, output_path, tag_args, AudioFormat, AudioSource, AudioTrack};
+
+/// Pieces shorter than this, in seconds, are left out, they are the noise between two silences
+const MIN_SEGMENT: f64 = 1.0;
+/// Frames per second of the INDEX times of a cue sheet
+const CUE_FRAMES: f64 = 75.0;
+
+/// A part of a track saved as its own file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Segment {
+    /// Seconds
+    pub start: f64,
+    /// Seconds, `None` runs to the end of the track
+    pub end: Option<f64>,
+    pub title: Option<String>,
+}
+
+/// Where a track is cut
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitMode {
+    /// At the chapters of the source
+    Chapters,
+    /// In the silences quieter than `noise` dB lasting at least `min_duration` seconds
+    Silence { noise: f64, min_duration: f64 },
+    /// At the times of a cue sheet or a timestamp list
+    Cues(Vec<Segment>),
+}
+
+impl Default for SplitMode {
+    fn default() -> Self {
+        SplitMode::Silence { noise: -40.0, min_duration: 2.0 }
+    }
+}
+
+impl fmt::Display for SplitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitMode::Chapters => f.write_str("Chapters"),
+            SplitMode::Silence { .. } => f.write_str("Silences"),
+            SplitMode::Cues(_) => f.write_str("Cue list"),
+        }
+    }
+}
+
+/// Chapters of a probed file, with their titles
+pub fn chapter_segments(metadata: &ProbeResult) -> Vec<Segment> {
+    metadata
+        .chapters
+        .iter()
+        .filter_map(|chapter| {
+            Some(Segment {
+                start: chapter.start_time?.as_secs_f64(),
+                end: chapter.end_time.map(|end| end.as_secs_f64()),
+                title: chapter.tags.title.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Reads a cue sheet, or a list of `timestamp title` lines like the ones under videos
+///
+/// Each piece ends where the next one starts, lines without a timestamp are skipped.
+pub fn parse_cues(text: &str) -> Result<Vec<Segment>, String> {
+    let segments = if text.lines().any(|line| line.trim_start().starts_with("TRACK ")) {
+        parse_cue_sheet(text)?
+    } else {
+        parse_timestamp_list(text)
+    };
+    if segments.is_empty() {
+        return Err("No timestamps found in the cue list".to_string());
+    }
+    Ok(close_segments(segments))
+}
+
+fn parse_cue_sheet(text: &str) -> Result<Vec<Segment>, String> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut title = None;
+    let mut in_track = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("TRACK ") {
+            in_track = true;
+            title = None;
+        } else if let Some(value) = line.strip_prefix("TITLE ").filter(|_| in_track) {
+            title = Some(value.trim().trim_matches('"').to_string());
+        } else if let Some(time) = line.strip_prefix("INDEX 01 ") {
+            let start = parse_cue_time(time.trim()).ok_or_else(|| format!("Invalid cue time {}", time))?;
+            segments.push(Segment { start, end: None, title: title.take() });
+        }
+    }
+    Ok(segments)
+}
+
+/// `mm:ss:ff` with 75 frames a second
+fn parse_cue_time(time: &str) -> Option<f64> {
+    let parts: Vec<f64> = time.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    match parts[..] {
+        [minutes, seconds, frames] => Some(minutes * 60.0 + seconds + frames / CUE_FRAMES),
+        _ => None,
+    }
+}
+
+fn parse_timestamp_list(text: &str) -> Vec<Segment> {
+    let re = Regex::new(r"^\W*?(\d+(?::\d{1,2}){1,2}(?:\.\d+)?)\]?\)?\s*[-–:.|]?\s*(.*)$").unwrap();
+    text.lines()
+        .filter_map(|line| {
+            let caps = re.captures(line.trim())?;
+            let title = caps[2].trim();
+            Some(Segment {
+                start: parse_timestamp(&caps[1])?,
+                end: None,
+                title: (!title.is_empty()).then(|| title.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Sorts the segments and ends each one where the next starts
+fn close_segments(mut segments: Vec<Segment>) -> Vec<Segment> {
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let starts: Vec<f64> = segments.iter().skip(1).map(|segment| segment.start).collect();
+    for (segment, next) in segments.iter_mut().zip(starts) {
+        segment.end = Some(segment.end.map_or(next, |end| end.min(next)));
+    }
+    segments
+}
+
+/// Silences reported by `silencedetect`, a silence lasting to the end has no end
+pub fn parse_silences(stderr: &str) -> Vec<(f64, Option<f64>)> {
+    let re = Regex::new(r"silence_(start|end): (-?[0-9.]+)").unwrap();
+    let mut silences: Vec<(f64, Option<f64>)> = Vec::new();
+    for caps in re.captures_iter(stderr) {
+        let Ok(time) = caps[2].parse::<f64>() else {
+            continue;
+        };
+        match &caps[1] {
+            "start" => silences.push((time.max(0.0), None)),
+            _ => {
+                if let Some(last) = silences.last_mut().filter(|last| last.1.is_none()) {
+                    last.1 = Some(time);
+                }
+            }
+        }
+    }
+    silences
+}
+
+/// The sound between the silences, numbered titles are left to the tags
+pub fn silence_segments(silences: &[(f64, Option<f64>)], duration: Option<f64>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut start = 0.0;
+    for &(silence_start, silence_end) in silences {
+        if silence_start - start >= MIN_SEGMENT {
+            segments.push(Segment { start, end: Some(silence_start), title: None });
+        }
+        match silence_end {
+            Some(end) => start = end,
+            None => return segments,
+        }
+    }
+    if duration.is_none_or(|duration| duration - start >= MIN_SEGMENT) {
+        segments.push(Segment { start, end: None, title: None });
+    }
+    segments
+}
+
+/// Runs `silencedetect` over one audio track
+pub fn detect_silences(source: &AudioSource, track: &AudioTrack, noise: f64, min_duration: f64) -> Result<Vec<(f64, Option<f64>)>, String> {
+    let output = Command::new(path_for("ffmpeg"))
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(&source.path)
+        .args(["-map", &format!("0:a:{}", track.track)])
+        .args(["-af", &format!("silencedetect=noise={}dB:d={}", noise, min_duration), "-f", "null", "-"])
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("ffmpeg error: {}", stderr.lines().last().unwrap_or_default()));
+    }
+    Ok(parse_silences(&stderr))
+}
+
+/// The pieces a track is cut into, silences are detected with ffmpeg
+pub fn plan_segments(source: &AudioSource, track: &AudioTrack, mode: &SplitMode) -> Result<Vec<Segment>, String> {
+    let segments = match mode {
+        SplitMode::Chapters => source.chapters.clone(),
+        SplitMode::Silence { noise, min_duration } => {
+            silence_segments(&detect_silences(source, track, *noise, *min_duration)?, source.duration)
+        }
+        SplitMode::Cues(cues) => cues.iter().filter(|cue| source.duration.is_none_or(|duration| cue.start < duration)).cloned().collect(),
+    };
+    if segments.is_empty() {
+        return Err(format!("Nothing to split {} at", source.path.display()));
+    }
+    Ok(segments)
+}
+
+/// File of the segment `index`, like `concert.mkv - 02 - Encore.mp3` for `concert.mkv.mp3`
+pub fn segment_output_path(track_output: &Path, segments: &[Segment], index: usize) -> PathBuf {
+    let width = segments.len().to_string().len().max(2);
+    let mut name = format!("{} - {:0width$}", track_output.file_stem().unwrap_or_default().to_string_lossy(), index + 1);
+    if let Some(title) = &segments[index].title {
+        name.push_str(&format!(" - {}", title.replace(['/', '\\', ':'], "-")));
+    }
+    if let Some(extension) = track_output.extension() {
+        name.push_str(&format!(".{}", extension.to_string_lossy()));
+    }
+    track_output.with_file_name(name)
+}
+
+/// Tags of the segment `index`, its title and number, the source title becomes the album
+pub fn segment_tags(tags: &Tags, segments: &[Segment], index: usize) -> Tags {
+    Tags {
+        title: Some(segments[index].title.clone().unwrap_or_else(|| format!("Track {}", index + 1))),
+        album: tags.album.clone().or_else(|| tags.title.clone()),
+        ..tags.clone()
+    }
+}
+
+/// Arguments of the ffmpeg command saving the segment `index` of one audio track into `output`, replacing it
+pub fn split_args(source: &AudioSource, track: &AudioTrack, format: AudioFormat, segments: &[Segment], index: usize, output: &Path) -> Vec<String> {
+    let segment = &segments[index];
+    let mut args = vec!["-y".to_string(), "-ss".to_string(), format!("{:.3}", segment.start), "-i".to_string(), source.path.to_string_lossy().to_string()];
+    if let Some(end) = segment.end {
+        args.extend(["-t".to_string(), format!("{:.3}", end - segment.start)]);
+    }
+    args.extend(["-map".to_string(), format!("0:a:{}", track.track), "-map_chapters".to_string(), "-1".to_string()]);
+    args.extend(tag_args(&segment_tags(&source.tags, segments, index)));
+    args.extend(["-metadata".to_string(), format!("track={}/{}", index + 1, segments.len())]);
+    args.extend(encode_args(format, track, false));
+    args.push(output.to_string_lossy().to_string());
+    args
+}
+
+/// Saves one file per segment of a track, `numbered` as for [output_path], returns the files in order
+pub fn split_audio(
+    source: &AudioSource,
+    track: &AudioTrack,
+    format: AudioFormat,
+    segments: &[Segment],
+    numbered: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let track_output = output_path(&source.path, track, format, numbered);
+    let mut outputs = Vec::new();
+    for index in 0..segments.len() {
+        let output = segment_output_path(&track_output, segments, index);
+        let result = Command::new(path_for("ffmpeg"))
+            .args(split_args(source, track, format, segments, index, &output))
+            .output()
+            .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+        if !result.status.success() {
+            return Err(format!(
+                "Error while saving {}: {}",
+                output.display(),
+                String::from_utf8_lossy(&result.stderr).lines().last().unwrap_or_default()
+            ));
+        }
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
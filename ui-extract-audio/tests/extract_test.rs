@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use lib_ffmpeg_utils::loudness::AudioFilters;
+    use lib_ffmpeg_utils::probe::parse_probe_output;
+    use ui_extract_audio::extract::{collect_inputs, extract_args, AudioFormat, AudioSource};
+
+    fn concert() -> AudioSource {
+        let metadata = parse_probe_output(include_str!("../../lib-ffmpeg-utils/tests/fixtures/concert_mkv.json")).unwrap();
+        AudioSource::from_probe(Path::new("/videos/concert.mkv"), &metadata)
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!("M4A".parse::<AudioFormat>(), Ok(AudioFormat::M4a));
+        assert_eq!("copy".parse::<AudioFormat>(), Ok(AudioFormat::Copy));
+        assert!("wma".parse::<AudioFormat>().is_err());
+        assert_eq!(AudioFormat::Copy.extension("opus"), "opus");
+        assert_eq!(AudioFormat::Copy.extension("dts"), "mka");
+        assert_eq!(AudioFormat::Flac.extension("opus"), "flac");
+    }
+
+    #[test]
+    fn tracks_and_outputs() {
+        let source = concert();
+        let labels: Vec<String> = source.tracks.iter().map(|track| track.label()).collect();
+        assert_eq!(labels, ["#0 jpn, Surround (opus, 5.1, 48 kHz) default", "#1 eng, Commentary (aac, stereo, 44.1 kHz)"]);
+        assert_eq!(source.default_track(), Some(0));
+
+        let outputs: Vec<PathBuf> = source.outputs(&[1], AudioFormat::Mp3).into_iter().map(|(_, path)| path).collect();
+        assert_eq!(outputs, [Path::new("/videos/concert.mkv.mp3")]);
+        let outputs: Vec<PathBuf> = source.outputs(&[0, 1], AudioFormat::Copy).into_iter().map(|(_, path)| path).collect();
+        assert_eq!(outputs, [Path::new("/videos/concert.mkv.0.jpn.opus"), Path::new("/videos/concert.mkv.1.eng.m4a")]);
+
+        let args = extract_args(&source, &source.tracks[0], AudioFormat::Mp3, &AudioFilters::default(), None, Path::new("out.mp3"));
+        assert_eq!(
+            args.join(" "),
+            "-y -i /videos/concert.mkv -map 0:a:0 -metadata title=Summer Concert -c:a libmp3lame -b:a 320k -ac 2 out.mp3"
+        );
+        let mono = AudioFilters { mono: true, ..Default::default() };
+        let args = extract_args(&source, &source.tracks[0], AudioFormat::Mp3, &mono, None, Path::new("out.mp3"));
+        assert_eq!(
+            args.join(" "),
+            "-y -i /videos/concert.mkv -map 0:a:0 -metadata title=Summer Concert -af aformat=channel_layouts=mono -c:a libmp3lame -b:a 320k out.mp3"
+        );
+    }
+
+    #[test]
+    fn folders() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("day 2/.thumbnails")).unwrap();
+        for name in ["a.mov", "notes.txt", "day 2/b.MKV", "day 2/.thumbnails/c.mp4"] {
+            fs::write(dir.path().join(name), "data").unwrap();
+        }
+        let inputs = collect_inputs(&[dir.path().to_path_buf(), dir.path().join("a.mov")]);
+        assert_eq!(inputs, [dir.path().join("a.mov"), dir.path().join("day 2/b.MKV")]);
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use lib_ffmpeg_utils::probe::parse_probe_output;
+    use ui_extract_audio::extract::{AudioFormat, AudioSource};
+    use ui_extract_audio::split::{parse_cues, parse_silences, segment_output_path, silence_segments, split_args, Segment};
+
+    fn segment(start: f64, end: Option<f64>, title: Option<&str>) -> Segment {
+        Segment { start, end, title: title.map(String::from) }
+    }
+
+    #[test]
+    fn cue_sheets_and_timestamp_lists() {
+        let sheet = "PERFORMER \"Band\"\nTITLE \"Live\"\nFILE \"live.wav\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Intro\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Song\"\n    INDEX 00 03:40:00\n    INDEX 01 03:45:30\n";
+        assert_eq!(parse_cues(sheet).unwrap(), [segment(0.0, Some(225.4), Some("Intro")), segment(225.4, None, Some("Song"))]);
+
+        let list = "Setlist:\n0:00 Intro\n[03:45] - Song two\n1:02:03.5 Finale\n";
+        assert_eq!(
+            parse_cues(list).unwrap(),
+            [segment(0.0, Some(225.0), Some("Intro")), segment(225.0, Some(3723.5), Some("Song two")), segment(3723.5, None, Some("Finale"))]
+        );
+        assert!(parse_cues("no times here").is_err());
+    }
+
+    #[test]
+    fn silences() {
+        let stderr = "[silencedetect @ 0x1] silence_start: -0.01\n[silencedetect @ 0x1] silence_end: 2.5 | silence_duration: 2.51\n\
+                      [silencedetect @ 0x1] silence_start: 60.2\n[silencedetect @ 0x1] silence_end: 63 | silence_duration: 2.8\n\
+                      [silencedetect @ 0x1] silence_start: 119.6\n";
+        let silences = parse_silences(stderr);
+        assert_eq!(silences, [(0.0, Some(2.5)), (60.2, Some(63.0)), (119.6, None)]);
+        assert_eq!(silence_segments(&silences, Some(120.0)), [segment(2.5, Some(60.2), None), segment(63.0, Some(119.6), None)]);
+        assert_eq!(silence_segments(&silences[..2], Some(120.0)).last(), Some(&segment(63.0, None, None)));
+    }
+
+    #[test]
+    fn chapters_to_files() {
+        let metadata = parse_probe_output(include_str!("../../lib-ffmpeg-utils/tests/fixtures/concert_mkv.json")).unwrap();
+        let source = AudioSource::from_probe(Path::new("/videos/concert.mkv"), &metadata);
+        assert_eq!(source.chapters, [segment(0.0, Some(95.0), Some("Opening")), segment(95.0, Some(300.033), Some("Encore"))]);
+
+        let output = segment_output_path(Path::new("/videos/concert.mkv.mp3"), &source.chapters, 1);
+        assert_eq!(output, Path::new("/videos/concert.mkv - 02 - Encore.mp3"));
+        assert_eq!(
+            split_args(&source, &source.tracks[1], AudioFormat::Mp3, &source.chapters, 1, &output).join(" "),
+            "-y -ss 95.000 -i /videos/concert.mkv -t 205.033 -map 0:a:1 -map_chapters -1 -metadata title=Encore \
+             -metadata album=Summer Concert -metadata track=2/2 -c:a libmp3lame -b:a 320k /videos/concert.mkv - 02 - Encore.mp3"
+        );
+    }
+}